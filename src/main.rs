@@ -1,591 +1,10882 @@
 extern crate argparse;
+extern crate libc;
 extern crate rand;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
 extern crate termion;
+extern crate unicode_width;
 
-use argparse::{ArgumentParser, Store, StoreTrue};
-use rand::Rng;
-use std::io::{stdout, Read, Write};
+use argparse::{ArgumentParser, Store, StoreOption, StoreTrue};
+use rand::{Rng, SeedableRng, StdRng};
+use std::collections::VecDeque;
+use std::env;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, stdout, BufWriter, Read, Write};
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::rc::Rc;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
 use std::thread;
-use std::time::Duration;
-use termion::{async_stdin, clear, color, cursor};
-use termion::raw::IntoRawMode;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use termion::event::{Event, Key};
+use termion::input::TermRead;
+use termion::raw::{IntoRawMode, RawTerminal};
+use termion::screen::{ToAlternateScreen, ToMainScreen};
+use termion::{clear, color, cursor};
+use unicode_width::UnicodeWidthStr;
 
 // Unit for game
 type Unit = usize;
 
-mod graphics {
-    pub const BORDER_HORIZONTAL: &str = "═";
-    pub const BORDER_VERTICAL: &str = "║";
-    pub const TOP_LEFT_BORDER: &str = "╔";
-    pub const TOP_RIGHT_BORDER: &str = "╗";
-    pub const BOTTOM_LEFT_BORDER: &str = "╚";
-    pub const BOTTOM_RIGHT_BORDER: &str = "╝";
-
-    pub const GAME_START:      &str =  "╔══════════════════════════════╗\n\
-                                        ║──   Welcome to rbreakout   ──║\n\
-                                        ║──────────────────────────────║\n\
-                                        ║       <space>  begin         ║\n\
-                                        ║       q        quit          ║\n\
-                                        ║                              ║\n\
-                                        ║  Controls                    ║\n\
-                                        ║       h    move left         ║\n\
-                                        ║       l    move left         ║\n\
-                                        ║       r    reset             ║\n\
-                                        ║       q    quit              ║\n\
-                                        ║                              ║\n\
-                                        ╚══════════════════════════════╝";
-    pub const GAME_OVER: &str = "╔═════════════════╗\n\
-                                 ║──  GAME OVER  ──║\n\
-                                 ║   r  replay     ║\n\
-                                 ║   q  quit       ║\n\
-                                 ╚═════════════════╝";
-    pub const GAME_WIN: &str =
-        "╔════════════════════╗\n\
-         ║── CONGRATULATION ──║\n\
-         ║     r  replay      ║\n\
-         ║     q  quit        ║\n\
-         ╚════════════════════╝";
-    pub const BALL_GRAPHIC: &str = "●";
-    pub const PEDDLE_GRAPHIC: &str = "════════════";
+/// Number of terminal columns `text` occupies, accounting for wide and
+/// zero-width characters.
+fn display_width(text: &str) -> Unit {
+    UnicodeWidthStr::width(text)
 }
 
-trait Drawable {
-    fn write<W: Write>(&self, stdout: &mut W) {
-        write!(
-            stdout,
-            "{}{}{}",
-            color::Fg(self.get_color()),
-            self.get_cursor_pos(),
-            self.get_graphics(),
-        ).unwrap();
-    }
-    fn clear<W: Write>(&self, stdout: &mut W) {
-        write!(
-            stdout,
-            "{}{}{}",
-            color::Bg(color::Reset),
-            self.get_cursor_pos(),
-            " ".repeat(Self::get_width() as usize)
-        ).unwrap();
-    }
+/// Output the game is drawn to. Owns the terminal modes the game relies on
+/// so they can be swapped out, e.g. for an in-memory buffer.
+trait Screen: Write {
+    /// Prepares the screen for drawing the game.
+    fn enter(&mut self) -> io::Result<()>;
+    /// Restores the state from before `enter`.
+    fn leave(&mut self) -> io::Result<()>;
+    /// Whether color escapes should be written, all color changes are skipped otherwise.
+    fn uses_color(&self) -> bool;
 
-    fn get_pos(&self) -> (Unit, Unit);
-    fn x(&self) -> (Unit) {
-        self.get_pos().0
-    }
-    fn y(&self) -> (Unit) {
-        self.get_pos().1
+    /// Current size in columns and rows, `None` if the screen has no size.
+    fn size(&self) -> Option<(Unit, Unit)> {
+        None
     }
-    fn get_cursor_pos(&self) -> cursor::Goto {
-        let pos = self.get_pos();
-        cursor::Goto(pos.0 as u16, pos.1 as u16)
+
+    fn set_fg(&mut self, color: &dyn color::Color) {
+        if self.uses_color() {
+            write!(self, "{}", color::Fg(color)).unwrap();
+        }
     }
 
-    fn get_color(&self) -> &color::Color;
-    fn get_graphics(&self) -> String;
+    fn set_bg(&mut self, color: &dyn color::Color) {
+        if self.uses_color() {
+            write!(self, "{}", color::Bg(color)).unwrap();
+        }
+    }
 
-    fn get_width() -> Unit;
-    fn get_height(&self) -> Unit {
-        return 1 as Unit;
+    /// Columns everything is drawn right of its game position.
+    fn offset(&self) -> Unit {
+        0
     }
-}
 
-struct Cell {
-    pos: (Unit, Unit),
-    color: Box<color::Color>,
-}
+    /// Draws everything that follows `columns` further right, screens that
+    /// cannot shift ignore it.
+    fn set_offset(&mut self, _columns: Unit) {}
 
-impl Drawable for Cell {
-    fn get_pos(&self) -> (Unit, Unit) {
-        (self.pos.0, self.pos.1)
+    /// Cursor movement to the game position `pos`.
+    fn goto(&self, pos: (Unit, Unit)) -> cursor::Goto {
+        coords::to_cursor(pos, self.offset())
     }
 
-    fn get_color(&self) -> &color::Color {
-        self.color.as_ref()
-    }
-    fn get_graphics(&self) -> String {
-        "█".repeat(Cell::get_width() as usize)
+    fn clear_all(&mut self) {
+        write!(self, "{}{}", clear::All, cursor::Goto(1, 1)).unwrap();
     }
 
-    fn get_width() -> Unit {
-        8 as Unit
+    /// Writes `text` in `color` starting at the game position `pos`.
+    fn draw_text(&mut self, pos: (Unit, Unit), color: &dyn color::Color, text: &str) {
+        self.set_fg(color);
+        let goto = self.goto(pos);
+        write!(self, "{}{}", goto, text).unwrap();
     }
 }
 
-struct Ball {
-    game_pos: (f32, f32),
-    vel: (f32, f32),
+/// The device behind a `Terminal`, switched to raw mode and asked for its
+/// size apart from the output drawn to it.
+trait Tty {
+    /// Restores the previous terminal mode when dropped
+    type RawMode;
+
+    fn raw_mode(&mut self) -> io::Result<Self::RawMode>;
+    /// Size in columns and rows, `None` if it cannot be queried.
+    fn size(&self) -> Option<(Unit, Unit)>;
 }
 
-impl Drawable for Ball {
-    fn get_pos(&self) -> (Unit, Unit) {
-        (
-            self.game_pos.0.round() as Unit,
-            self.game_pos.1.round() as Unit,
-        )
-    }
+/// The terminal the process's standard output goes to.
+struct StdoutTty;
 
-    fn get_color(&self) -> &color::Color {
-        &color::Red
-    }
-    fn get_graphics(&self) -> String {
-        graphics::BALL_GRAPHIC.to_string()
+impl Tty for StdoutTty {
+    type RawMode = RawTerminal<io::Stdout>;
+
+    fn raw_mode(&mut self) -> io::Result<Self::RawMode> {
+        io::stdout().into_raw_mode()
     }
 
-    fn get_width() -> Unit {
-        1
+    fn size(&self) -> Option<(Unit, Unit)> {
+        termion::terminal_size()
+            .ok()
+            .map(|(width, height)| (width as Unit, height as Unit))
     }
 }
 
-impl Ball {
-    fn update(&mut self, game_size: (Unit, Unit), player_pos: (Unit, Unit)) -> bool {
-        if self.x() <= 2 || self.x() >= (game_size.0 as Unit - 1) {
-            self.vel.0 *= -1f32;
-        }
-        if self.y() <= 2 {
-            self.vel.1 *= -1f32;
-        }
-
-        if self.y() >= (game_size.1 as Unit - 1) {
-            return false;
-        }
+/// Asks the terminal to report focus changes as `\x1b[I` and `\x1b[O`, as
+/// XTerm and most terminals modeled after it do.
+const FOCUS_REPORTING_ON: &str = "\x1b[?1004h";
+const FOCUS_REPORTING_OFF: &str = "\x1b[?1004l";
 
-        if (self.x() >= player_pos.0 && self.x() <= player_pos.0 + Peddle::get_width())
-            && self.y() == player_pos.1
-        {
-            let xoffset = self.game_pos.0 - (player_pos.0 + Peddle::get_width() / 2 as Unit) as f32;
-            self.vel.0 = xoffset / 8f32;
-            self.vel.1 = -1f32;
-            self.normalize_vel();
-        }
+/// Length of a frame, the game ticks once per frame.
+const FRAME_MILLIS: u64 = 20;
 
-        self.game_pos.0 += self.vel.0;
-        self.game_pos.1 += self.vel.1;
+/// The real terminal: raw mode, a hidden cursor and the alternate screen
+/// while entered, all restored when leaving or when dropped. Drawing in the
+/// alternate screen leaves the shell's scrollback untouched.
+struct Terminal<W: Write, T: Tty> {
+    output: W,
+    tty: T,
+    /// Restores the previous terminal mode when dropped
+    raw_mode: Option<T::RawMode>,
+    color: bool,
+    /// Turn on focus reporting while entered
+    report_focus: bool,
+    /// Columns the game is drawn right of the terminal's left edge, non-zero
+    /// while the screen shakes
+    offset: Unit,
+}
 
-        fn clamp(val: f32, min: f32, max: f32) -> f32 {
-            val.max(min).min(max)
+impl<W: Write, T: Tty> Terminal<W, T> {
+    pub fn new(output: W, tty: T, color: bool) -> Terminal<W, T> {
+        Terminal {
+            output,
+            tty,
+            raw_mode: None,
+            color,
+            report_focus: false,
+            offset: 0,
         }
-        self.game_pos = (
-            clamp(self.game_pos.0, 2f32, (game_size.0 - 1) as f32),
-            clamp(self.game_pos.1, 2f32, (game_size.1 - 1) as f32),
-        );
-
-        true
     }
 
-    fn collides_with<T: Drawable>(&self, target: &T) -> Option<Direction> {
-        let target_x = target.x() as f32;
-        let target_y = target.y() as f32;
-        let target_width = T::get_width() as f32;
-
-        let x = self.game_pos.0;
-        let y = self.game_pos.1;
+    /// Has the terminal report focus changes while entered when `on`.
+    pub fn report_focus(mut self, on: bool) -> Terminal<W, T> {
+        self.report_focus = on;
+        self
+    }
+}
 
-        fn in_range(val: f32, min: f32, max: f32) -> bool {
-            val >= min && val <= max
+impl<W: Write, T: Tty> Screen for Terminal<W, T> {
+    fn enter(&mut self) -> io::Result<()> {
+        self.raw_mode = Some(self.tty.raw_mode()?);
+        write!(self.output, "{}{}", ToAlternateScreen, cursor::Hide)?;
+        if self.report_focus {
+            write!(self.output, "{}", FOCUS_REPORTING_ON)?;
         }
+        self.output.flush()
+    }
 
-        if in_range(y, target_y, target_y + 1f32) {
-            if in_range(x, target_x, target_x + 0.3) && self.vel.0 > 0f32 {
-                return Some(Direction::LEFT);
-            }
-            if in_range(x, target_x + target_width - 0.3, target_x + target_width)
-                && self.vel.0 < 0f32
-            {
-                return Some(Direction::LEFT);
-            }
+    fn leave(&mut self) -> io::Result<()> {
+        if self.color {
+            write!(self.output, "{}", color::Fg(color::Reset))?;
         }
-
-        if in_range(x, target_x, target_x + target_width) {
-            if in_range(y, target_y, target_y + 0.5f32) {
-                return Some(Direction::DOWN);
-            }
-            if in_range(y, target_y + 0.5, target_y + 1f32) {
-                return Some(Direction::UP);
-            }
+        if self.report_focus {
+            write!(self.output, "{}", FOCUS_REPORTING_OFF)?;
         }
-
-        return None;
+        write!(self.output, "{}{}", cursor::Show, ToMainScreen)?;
+        self.output.flush()?;
+        self.raw_mode = None;
+        Ok(())
     }
 
-    pub fn change_direction(&mut self, dir: Direction) {
-        match dir {
-            Direction::LEFT | Direction::RIGHT => self.vel.0 *= -1f32,
-            Direction::UP | Direction::DOWN => self.vel.1 *= -1f32,
-        }
+    fn uses_color(&self) -> bool {
+        self.color
     }
 
-    fn normalize_vel(&mut self) {
-        let magnitude: f32 = (self.vel.0*self.vel.0 + self.vel.1*self.vel.1).sqrt();
-        self.vel.0 /= magnitude;
-        self.vel.1 /= magnitude;
+    fn size(&self) -> Option<(Unit, Unit)> {
+        self.tty.size()
+    }
 
-        self.vel.0 *= Self::get_speed();
-        self.vel.1 *= Self::get_speed();
+    fn offset(&self) -> Unit {
+        self.offset
     }
 
-    fn get_speed() -> f32 {
-        0.4
+    fn set_offset(&mut self, columns: Unit) {
+        self.offset = columns;
     }
 }
 
-enum Direction {
-    LEFT,
-    RIGHT,
-    UP,
-    DOWN,
-}
+impl<W: Write, T: Tty> Write for Terminal<W, T> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.output.write(buf)
+    }
 
-struct Peddle {
-    pos: (Unit, Unit),
+    fn flush(&mut self) -> io::Result<()> {
+        self.output.flush()
+    }
 }
 
-impl Drawable for Peddle {
-    fn get_pos(&self) -> (Unit, Unit) {
-        (self.pos.0, self.pos.1)
+impl<W: Write, T: Tty> Drop for Terminal<W, T> {
+    fn drop(&mut self) {
+        if self.raw_mode.is_some() {
+            let _ = self.leave();
+        }
     }
+}
 
-    fn get_color(&self) -> &color::Color {
-        &color::Red
+/// Discards everything drawn to it, used to run the simulation headless.
+struct NullScreen;
+
+impl Screen for NullScreen {
+    fn enter(&mut self) -> io::Result<()> {
+        Ok(())
     }
-    fn get_graphics(&self) -> String {
-        graphics::PEDDLE_GRAPHIC.to_string()
+
+    fn leave(&mut self) -> io::Result<()> {
+        Ok(())
     }
 
-    fn get_width() -> Unit {
-        graphics::PEDDLE_GRAPHIC.chars().count() as Unit
+    fn uses_color(&self) -> bool {
+        false
     }
 }
 
-impl Peddle {
-    pub fn move_in_dir(&mut self, dir: Direction, game_width: Unit) {
-        match dir {
-            Direction::LEFT => match self.pos.0.checked_sub(Self::get_speed()) {
-                None => self.pos.0 = 2,
-                _ => self.pos.0 -= Self::get_speed(),
-            },
-            Direction::RIGHT => self.pos.0 += Peddle::get_speed(),
-            _ => panic!("Unexpected direction for peddle"),
-        }
-        // clamp position
-        fn clamp(val: Unit, min: Unit, max: Unit) -> Unit {
-            val.max(min).min(max)
-        }
-        self.pos.0 = clamp(self.pos.0, 2, game_width - Self::get_width());
+impl Write for NullScreen {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
     }
 
-    pub fn get_speed() -> Unit {
-        3 as Unit
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
     }
 }
 
-struct BreakoutGame<R, W> {
-    stdin: R,
-    stdout: W,
+/// Something read from the terminal: a key press or a change of focus.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Input {
+    Key(Key),
+    /// The terminal gained (`true`) or lost focus, only reported with focus
+    /// reporting on
+    Focus(bool),
+}
 
-    ball: Ball,
-    peddle: Peddle,
-    cells: Vec<Cell>,
+impl Input {
+    /// The input `event` stands for, `None` for events the game ignores.
+    /// termion does not know the focus sequences and leaves them unsupported.
+    pub fn from_event(event: Event) -> Option<Input> {
+        match event {
+            Event::Key(key) => Some(Input::Key(key)),
+            Event::Unsupported(ref bytes) if bytes.as_slice() == b"\x1b[I" => {
+                Some(Input::Focus(true))
+            }
+            Event::Unsupported(ref bytes) if bytes.as_slice() == b"\x1b[O" => {
+                Some(Input::Focus(false))
+            }
+            _ => None,
+        }
+    }
+}
 
-    height: Unit,
-    width: Unit,
+/// Where the game gets key presses from, polled once per frame.
+trait KeySource {
+    /// The next input, `None` without waiting if there is none yet.
+    fn next_input(&mut self) -> Option<Input>;
 }
 
-impl<R: Read, W: Write> BreakoutGame<R, W> {
-    pub fn new(stdin: R, stdout: W, width: Unit, height: Unit) -> BreakoutGame<R, W> {
-        let (ball, peddle, cells) = Self::get_start_values(width, height);
-        BreakoutGame {
-            width: width,
-            height: height,
-            stdin: stdin,
-            stdout: stdout,
-            ball: ball,
-            peddle: peddle,
-            cells: cells,
+impl<R: Read> KeySource for R {
+    fn next_input(&mut self) -> Option<Input> {
+        for event in self.by_ref().events() {
+            match event.map(Input::from_event) {
+                Ok(Some(input)) => return Some(input),
+                Ok(None) => {}
+                Err(_) => return None,
+            }
         }
+        None
     }
+}
 
-    pub fn get_start_values(width: Unit, height: Unit) -> (Ball, Peddle, Vec<Cell>) {
-        let half_peddle_width = Peddle::get_width() / 2 as Unit;
-        let peddle_pos = (
-            (width as Unit / 2) - half_peddle_width,
-            (height - 2) as Unit,
-        );
-
-        let ball_pos = ((width as f32) / 2f32 - 10f32, (height as f32) / 1.5f32);
+/// Decodes key presses from `input` on a background thread and hands them
+/// over a channel, so escape sequences and bursts of keys arrive whole.
+struct InputThread {
+    keys: mpsc::Receiver<Input>,
+    /// Tells the reading thread to finish
+    stop: Arc<AtomicBool>,
+    handle: Option<thread::JoinHandle<()>>,
+}
 
-        (
-            Ball {
-                game_pos: ball_pos,
-                vel: (0.3, 0.3),
-            },
-            Peddle { pos: peddle_pos },
-            Self::generate_cell_grid((width, height)),
-        )
+impl InputThread {
+    /// Reads the keys typed into standard input.
+    pub fn stdin() -> InputThread {
+        let stop = Arc::new(AtomicBool::new(false));
+        let input = PolledStdin { stop: stop.clone() };
+        InputThread::spawn(input, stop)
     }
 
-    pub fn reset_game(&mut self) {
-        let (ball, peddle, cells) = Self::get_start_values(self.width, self.height);
-        self.ball = ball;
-        self.peddle = peddle;
-        self.cells = cells;
-
-        write!(self.stdout, "{}{}", clear::All, cursor::Goto(1, 1),).unwrap();
-        self.draw_game_borders();
-        for cell in &mut self.cells {
-            cell.write(&mut self.stdout);
+    /// Starts reading `input`, which has to end once `stop` is set.
+    fn spawn<R: Read + Send + 'static>(input: R, stop: Arc<AtomicBool>) -> InputThread {
+        let (sender, keys) = mpsc::channel();
+        let handle = thread::spawn(move || forward_keys(input, &sender));
+        InputThread {
+            keys,
+            stop,
+            handle: Some(handle),
         }
-        self.stdout.flush().unwrap();
     }
 
-    pub fn run(&mut self) {
-        write!(self.stdout, "{}", cursor::Hide).unwrap();
-        self.reset_game(); // Display dummy game scene
+    /// Longest the reading thread waits for input before checking whether to stop.
+    fn get_poll_millis() -> libc::c_int {
+        50
+    }
+}
 
-        if !self.start_screen() {
-            return;
+impl KeySource for InputThread {
+    fn next_input(&mut self) -> Option<Input> {
+        self.keys.try_recv().ok()
+    }
+}
+
+impl Drop for InputThread {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
         }
+    }
+}
 
-        self.reset_game();
-        loop {
-            if !self.update() {
-                break;
-            }
+/// Sends every key decoded from `input` until the input ends, fails or
+/// nobody is receiving anymore.
+fn forward_keys<R: Read>(input: R, sender: &mpsc::Sender<Input>) {
+    for event in input.events() {
+        match event.map(Input::from_event) {
+            Ok(Some(input)) if sender.send(input).is_err() => return,
+            Ok(_) => {}
+            Err(_) => return,
+        }
+    }
+}
 
-            if !self.ball.update((self.width, self.height), self.peddle.pos) {
-                if self.game_over_screen() {
-                    self.reset_game();
-                } else {
-                    break;
-                }
-            }
+/// Standard input read straight from its file descriptor, waiting at most a
+/// poll interval at a time so the reader notices when it should stop.
+struct PolledStdin {
+    /// Makes reads return the end of input
+    stop: Arc<AtomicBool>,
+}
 
-            let mut to_kill = vec![];
-            for (index, cell) in &mut self.cells.iter().enumerate() {
-                let hit_dir = self.ball.collides_with(cell);
-                match hit_dir {
-                    None => { /***/ }
-                    _ => {
-                        to_kill.push(index);
-                        self.ball.change_direction(hit_dir.unwrap());
-                    }
+impl Read for PolledStdin {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut fds = libc::pollfd {
+            fd: 0,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        while !self.stop.load(Ordering::SeqCst) {
+            let ready = unsafe { libc::poll(&mut fds, 1, InputThread::get_poll_millis()) };
+            if ready < 0 {
+                let error = io::Error::last_os_error();
+                if error.kind() == io::ErrorKind::Interrupted {
+                    continue;
                 }
+                return Err(error);
             }
-            for i in to_kill {
-                self.cells[i].clear(&mut self.stdout);
-                self.cells.remove(i);
-            }
-            if self.cells.is_empty() {
-                if self.game_won_screen() {
-                    self.reset_game();
-                } else {
-                    break;
+            if ready > 0 {
+                // Unbuffered, bytes left in a buffer would not wake up the poll
+                let count =
+                    unsafe { libc::read(0, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
+                if count < 0 {
+                    return Err(io::Error::last_os_error());
                 }
+                return Ok(count as usize);
             }
+        }
+        Ok(0)
+    }
+}
 
-            self.ball.write(&mut self.stdout);
-            self.peddle.write(&mut self.stdout);
-
-            self.stdout.flush().unwrap();
-            thread::sleep(Duration::from_millis(20));
+/// Game positions are 1-based like `cursor::Goto` and name the terminal cells
+/// of a game drawn at the terminal's top left corner, `to_terminal` and
+/// `from_terminal` convert them for a game drawn further right. The game
+/// border takes up the outermost rows and columns of the game area and the
+/// playfield is everything inside of it.
+mod coords {
+    use super::Unit;
+    use termion::cursor;
 
-            self.ball.clear(&mut self.stdout);
-            self.peddle.clear(&mut self.stdout);
-        }
+    /// First playfield column, right of the left border.
+    pub fn left() -> Unit {
+        2
+    }
 
-        writeln!(self.stdout, "{}", cursor::Show).unwrap();
+    /// First playfield row, below the top border.
+    pub fn top() -> Unit {
+        2
     }
 
-    fn update(&mut self) -> bool {
-        let mut key_bytes = [0];
-        self.stdin.read(&mut key_bytes).unwrap();
-
-        match key_bytes[0] {
-            b'q' => return false,
-            b'r' => self.reset_game(),
-            b'h' | b'a' => self.peddle.move_in_dir(Direction::LEFT, self.width),
-            b'l' | b'd' => self.peddle.move_in_dir(Direction::RIGHT, self.width),
-            _ => {}
-        }
+    /// Last playfield column of a game `width` columns wide.
+    pub fn right(width: Unit) -> Unit {
+        right_border(width) - 1
+    }
 
-        true
+    /// Last playfield row of a game `height` rows high.
+    pub fn bottom(height: Unit) -> Unit {
+        bottom_border(height) - 1
     }
 
-    fn start_screen(&mut self) -> bool {
-        self.yes_no_dialog(graphics::GAME_START, Box::new(color::Blue), ' ', 'q')
+    pub fn left_border() -> Unit {
+        1
     }
 
-    fn game_over_screen(&mut self) -> bool {
-        self.yes_no_dialog(graphics::GAME_OVER, Box::new(color::Red), 'r', 'q')
+    pub fn top_border() -> Unit {
+        1
     }
 
-    fn game_won_screen(&mut self) -> bool {
-        self.yes_no_dialog(graphics::GAME_WIN, Box::new(color::Green), 'r', 'q')
+    pub fn right_border(width: Unit) -> Unit {
+        width
     }
 
-    fn yes_no_dialog(
-        &mut self,
-        graphics: &str,
-        color: Box<color::Color>,
-        yes: char,
-        no: char,
-    ) -> bool {
-        for (index, l) in graphics.lines().enumerate() {
-            write!(
-                self.stdout,
-                "{}{}{}",
-                color::Fg(color.as_ref()),
-                cursor::Goto(
-                    (self.width as u16 / 2) - l.chars().count() as u16 / 2,
-                    (self.height as u16 / 2) + index as u16
-                ),
-                l
-            ).unwrap();
-        }
-        self.stdout.flush().unwrap();
+    pub fn bottom_border(height: Unit) -> Unit {
+        height
+    }
 
-        let mut key_bytes = [0u8];
-        loop {
-            self.stdin.read(&mut key_bytes).unwrap();
-            if key_bytes[0] == yes as u8 {
-                return true;
-            }
-            if key_bytes[0] == no as u8 {
-                return false;
-            }
-        }
+    /// Number of playfield columns between the side borders.
+    pub fn inner_width(width: Unit) -> Unit {
+        right(width) + 1 - left()
     }
 
-    fn draw_game_borders(&mut self) {
-        let horizontal_border = graphics::BORDER_HORIZONTAL.repeat(self.width as usize - 2);
+    /// Terminal cell showing the game position `pos` while the game is drawn
+    /// `shift` columns right of the terminal's left edge.
+    pub fn to_terminal(pos: (Unit, Unit), shift: Unit) -> (u16, u16) {
+        ((pos.0 + shift) as u16, pos.1 as u16)
+    }
 
-        write!(self.stdout, "{}", color::Fg(color::Blue)).unwrap();
-        write!(
-            self.stdout,
-            "{}{}{}{}",
-            cursor::Goto(1, 1),
-            graphics::TOP_LEFT_BORDER,
-            horizontal_border,
-            graphics::TOP_RIGHT_BORDER
-        ).unwrap();
-        for y in 2..(self.height) as u16 {
-            write!(
-                self.stdout,
-                "{}{}",
-                cursor::Goto(1, y),
-                graphics::BORDER_VERTICAL
-            ).unwrap();
-            write!(
-                self.stdout,
-                "{}{}",
-                cursor::Goto(self.width as u16, y),
-                graphics::BORDER_VERTICAL
-            ).unwrap();
+    /// Game position shown in the terminal cell `cell` while the game is drawn
+    /// `shift` columns right of the terminal's left edge, `None` left of the
+    /// game.
+    pub fn from_terminal(cell: (u16, u16), shift: Unit) -> Option<(Unit, Unit)> {
+        match (cell.0 as Unit).checked_sub(shift) {
+            Some(x) if x >= left_border() => Some((x, cell.1 as Unit)),
+            _ => None,
         }
-        write!(
-            self.stdout,
-            "{}{}{}{}",
-            cursor::Goto(1, self.height as u16),
-            graphics::BOTTOM_LEFT_BORDER,
-            horizontal_border,
-            graphics::BOTTOM_RIGHT_BORDER
-        ).unwrap();
     }
 
-    fn generate_cell_grid(game_size: (Unit, Unit)) -> Vec<Cell> {
-        let cell_width = Cell::get_width();
-        let cell_margin = 0;
-        let num_cells_horizontally = game_size.0 / (cell_margin + cell_width) - 2;
-        let num_cells_vertically = game_size.1 / 3;
+    /// Cursor movement to the game position `pos` while the game is drawn
+    /// `shift` columns right of the terminal's left edge.
+    pub fn to_cursor(pos: (Unit, Unit), shift: Unit) -> cursor::Goto {
+        let (x, y) = to_terminal(pos, shift);
+        cursor::Goto(x, y)
+    }
+}
 
-        let vec_capacity = (num_cells_vertically * num_cells_horizontally) as usize;
-        let mut cells = Vec::with_capacity(vec_capacity);
-        for cy in 0..num_cells_vertically {
-            for cx in 0..num_cells_horizontally {
-                let mut rng = rand::thread_rng();
-                let c = rng.gen_range(0, 5);
-
-                let xpos = cell_width + cx * (cell_width + cell_margin);
-                let ypos = 4 + cy;
-                cells.push(Cell {
-                    pos: (xpos, ypos),
-                    color: match c {
-                        0 => Box::new(color::Red),
-                        1 => Box::new(color::Green),
-                        2 => Box::new(color::Blue),
-                        4 => Box::new(color::Magenta),
-                        _ => Box::new(color::Red),
+mod graphics {
+    pub const BORDER_HORIZONTAL: &str = "═";
+    pub const BORDER_VERTICAL: &str = "║";
+    pub const TOP_LEFT_BORDER: &str = "╔";
+    pub const TOP_RIGHT_BORDER: &str = "╗";
+    pub const BOTTOM_LEFT_BORDER: &str = "╚";
+    pub const BOTTOM_RIGHT_BORDER: &str = "╝";
+    /// Walls inside the playfield added by a non-rectangular field shape
+    pub const BORDER_FILL: &str = "░";
+
+    pub const MENU_TOP: &str = "╔══════════════════════════════╗";
+    pub const MENU_SEPARATOR: &str = "║──────────────────────────────║";
+    /// Shown in the start banner unless `--title` replaces it
+    pub const MENU_TITLE: &str = "Welcome to rbreakout";
+    pub const MENU_FOOTER: &str = "║                              ║\n\
+                                        ╚══════════════════════════════╝";
+    pub const MENU_INNER_WIDTH: usize = 30;
+    pub const SCOREBOARD_HEADER: &str = "╔══════════════════════════════╗\n\
+                                         ║──        Scoreboard        ──║\n\
+                                         ║──────────────────────────────║";
+    pub const GAME_OVER: &str = "╔═════════════════╗\n\
+                                 ║──  GAME OVER  ──║\n\
+                                 ║   r  replay     ║\n\
+                                 ║   q  quit       ║\n\
+                                 ╚═════════════════╝";
+    pub const GAME_WIN: &str =
+        "╔════════════════════╗\n\
+         ║── CONGRATULATION ──║\n\
+         ║     r  replay      ║\n\
+         ║     q  quit        ║\n\
+         ╚════════════════════╝";
+    pub const STATS: &str = "╔════════════════════╗\n\
+                             ║──    SUMMARY     ──║\n\
+                             ║                    ║\n\
+                             ║  any key continue  ║\n\
+                             ╚════════════════════╝";
+    pub const BALL_GRAPHIC: &str = "●";
+    /// Fills every cell of a ball larger than one cell
+    pub const BALL_BLOCK: &str = "█";
+    pub const BRICK_FULL: &str = "█";
+    pub const BRICK_DAMAGED: &str = "▓";
+    pub const BRICK_BROKEN: &str = "▒";
+    pub const BRICK_FROZEN: &str = "▦";
+    pub const BRICK_CRACKED: &str = "▞";
+    pub const PEDDLE_GRAPHIC: &str = "════════════";
+    /// Repeated for every column of a peddle with a configured width
+    pub const PEDDLE_SEGMENT: &str = "═";
+    pub const BOSS_HEALTH_FULL: &str = "▰";
+    pub const BOSS_HEALTH_EMPTY: &str = "▱";
+    pub const POWER_UP_STICKY: &str = "S";
+    pub const POWER_UP_SAFETY_NET: &str = "N";
+    pub const POWER_UP_LASER: &str = "L";
+    pub const PROJECTILE: &str = "|";
+    pub const SAFETY_NET: &str = "┄";
+    /// Brick debris glyphs, from the youngest to the oldest particle
+    pub const PARTICLES: [&str; 3] = ["*", "+", "."];
+    /// Ball trail glyphs, from the newest to the oldest position
+    pub const TRAIL: [&str; 3] = ["•", "∙", "·"];
+    /// Dots of the predicted ball path
+    pub const PREDICTION: &str = "⋅";
+    /// Line under the peddle marking where it catches the ball
+    pub const GUIDE: &str = "╌";
+    pub const PROGRESS_FULL: &str = "█";
+    pub const PROGRESS_EMPTY: &str = "░";
+}
+
+trait Drawable {
+    fn write<W: Screen>(&self, stdout: &mut W) {
+        stdout.set_fg(self.get_color());
+        for (row, line) in self.get_graphics().lines().enumerate() {
+            let goto = stdout.goto(self.get_draw_pos(row));
+            write!(stdout, "{}{}", goto, line).unwrap();
+        }
+    }
+    fn clear<W: Screen>(&self, stdout: &mut W) {
+        stdout.set_bg(&color::Reset);
+        for row in 0..self.get_height() {
+            let goto = stdout.goto(self.get_draw_pos(row));
+            write!(stdout, "{}{}", goto, " ".repeat(self.get_width())).unwrap();
+        }
+    }
+
+    fn get_pos(&self) -> (Unit, Unit);
+    fn x(&self) -> Unit {
+        self.get_pos().0
+    }
+    fn y(&self) -> Unit {
+        self.get_pos().1
+    }
+    /// Game position of the start of the `row`th line of the graphics.
+    fn get_draw_pos(&self, row: Unit) -> (Unit, Unit) {
+        let pos = self.get_pos();
+        (pos.0, pos.1 + row)
+    }
+
+    fn get_color(&self) -> &dyn color::Color;
+    /// One line per row occupied on screen
+    fn get_graphics(&self) -> String;
+
+    fn get_width(&self) -> Unit;
+    fn get_height(&self) -> Unit {
+        1
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+enum BrickKind {
+    /// Destroyed by a single hit
+    Normal,
+    /// Takes two hits to destroy
+    Tough,
+    /// Can never be destroyed, the ball always bounces off
+    Solid,
+    /// Destroyed by a single hit, taking its neighbors with it
+    Explosive,
+    /// Destroyed by a single hit, releasing an extra ball
+    MultiBall,
+    /// Cracked by a first hit and destroyed by a second one soon after, the
+    /// crack freezes over again if the second hit takes too long
+    Frozen,
+}
+
+struct Cell {
+    pos: (Unit, Unit),
+    width: Unit,
+    color: Box<dyn color::Color>,
+    kind: BrickKind,
+    hits_left: u8,
+    /// Name of the brick color for scoring, `None` for colors set by the kind
+    named_color: Option<NamedColor>,
+    /// Set when the brick changed since it was last drawn
+    dirty: bool,
+    /// Colors for the hits left, shared by the bricks of a layout. `None`
+    /// keeps the brick color when damaged
+    damage_colors: Option<Rc<DamageColors>>,
+    /// Frames a cracked frozen brick has left for the breaking hit
+    thaw_ticks: u32,
+}
+
+impl Cell {
+    pub fn new(
+        pos: (Unit, Unit),
+        width: Unit,
+        color: Box<dyn color::Color>,
+        kind: BrickKind,
+    ) -> Cell {
+        Cell {
+            pos,
+            width,
+            color,
+            kind,
+            hits_left: Self::get_max_hits(kind),
+            named_color: None,
+            dirty: true,
+            damage_colors: None,
+            thaw_ticks: 0,
+        }
+    }
+
+    /// A brick drawn in `brick_color`, unless its kind has a color of its own.
+    pub fn colored(
+        pos: (Unit, Unit),
+        width: Unit,
+        kind: BrickKind,
+        brick_color: NamedColor,
+    ) -> Cell {
+        let (color, named_color): (Box<dyn color::Color>, _) = match kind {
+            BrickKind::Solid => (Box::new(color::LightBlack), None),
+            BrickKind::Explosive => (Box::new(color::Yellow), None),
+            BrickKind::MultiBall => (Box::new(color::Cyan), None),
+            BrickKind::Frozen => (Box::new(color::LightCyan), None),
+            _ => (brick_color.to_color(), Some(brick_color)),
+        };
+        let mut cell = Cell::new(pos, width, color, kind);
+        cell.named_color = named_color;
+        cell
+    }
+
+    pub fn get_max_hits(kind: BrickKind) -> u8 {
+        match kind {
+            BrickKind::Normal => 1,
+            BrickKind::Tough => 2,
+            BrickKind::Solid => 0,
+            BrickKind::Explosive => 1,
+            BrickKind::MultiBall => 1,
+            BrickKind::Frozen => 2,
+        }
+    }
+
+    /// Block character that gets lighter the more damage the brick has taken,
+    /// or the ice of a frozen brick.
+    fn get_glyph(&self) -> &'static str {
+        if self.kind == BrickKind::Frozen {
+            return if self.is_cracked() {
+                graphics::BRICK_CRACKED
+            } else {
+                graphics::BRICK_FROZEN
+            };
+        }
+        match Self::get_max_hits(self.kind).saturating_sub(self.hits_left) {
+            0 => graphics::BRICK_FULL,
+            1 => graphics::BRICK_DAMAGED,
+            _ => graphics::BRICK_BROKEN,
+        }
+    }
+
+    pub fn get_default_width() -> Unit {
+        8 as Unit
+    }
+
+    pub fn is_breakable(&self) -> bool {
+        self.kind != BrickKind::Solid
+    }
+
+    /// Whether the brick took its last hit and only waits to be removed.
+    pub fn is_destroyed(&self) -> bool {
+        self.is_breakable() && self.hits_left == 0
+    }
+
+    /// Whether `other` touches this brick, including diagonally, in a grid
+    /// with `gap` empty cells between bricks.
+    pub fn is_neighbor(&self, other: &Cell, gap: Unit) -> bool {
+        let row_step = 1 + gap;
+        let close_rows = self.y() <= other.y() + row_step && other.y() <= self.y() + row_step;
+        let close_columns = self.x() <= other.x() + other.get_width() + gap
+            && other.x() <= self.x() + self.get_width() + gap;
+        self.pos != other.pos && close_rows && close_columns
+    }
+
+    /// Registers a hit from the ball and returns true if the brick is destroyed.
+    pub fn hit(&mut self) -> bool {
+        if !self.is_breakable() {
+            return false;
+        }
+        self.hits_left = self.hits_left.saturating_sub(1);
+        self.dirty = true;
+        if self.is_cracked() {
+            self.thaw_ticks = Self::get_thaw_ticks();
+        }
+        self.hits_left == 0
+    }
+
+    /// Destroys the brick at once however many hits it has left, returns true
+    /// if it was breakable and still standing.
+    pub fn shatter(&mut self) -> bool {
+        if !self.is_breakable() || self.hits_left == 0 {
+            return false;
+        }
+        self.hits_left = 0;
+        self.dirty = true;
+        true
+    }
+
+    /// Whether this is a frozen brick waiting for its breaking hit.
+    pub fn is_cracked(&self) -> bool {
+        self.kind == BrickKind::Frozen
+            && self.hits_left > 0
+            && self.hits_left < Self::get_max_hits(self.kind)
+    }
+
+    /// Counts down the time a cracked frozen brick has for its breaking hit,
+    /// freezing it over again once the time is up.
+    pub fn refreeze(&mut self) {
+        if self.thaw_ticks == 0 {
+            return;
+        }
+        self.thaw_ticks -= 1;
+        if self.thaw_ticks == 0 && self.is_cracked() {
+            self.hits_left = Self::get_max_hits(self.kind);
+            self.dirty = true;
+        }
+    }
+
+    /// Frames between cracking a frozen brick and it freezing over again,
+    /// one and a half seconds.
+    fn get_thaw_ticks() -> u32 {
+        3 * (1000 / FRAME_MILLIS) as u32 / 2
+    }
+}
+
+impl Drawable for Cell {
+    fn get_pos(&self) -> (Unit, Unit) {
+        (self.pos.0, self.pos.1)
+    }
+
+    fn get_color(&self) -> &dyn color::Color {
+        if self.is_cracked() {
+            return &color::LightWhite;
+        }
+        if self.hits_left < Self::get_max_hits(self.kind) {
+            let damage_colors = self.damage_colors.as_ref();
+            if let Some(color) = damage_colors.and_then(|colors| colors.get(self.hits_left)) {
+                return color.as_color();
+            }
+        }
+        self.color.as_ref()
+    }
+    fn get_graphics(&self) -> String {
+        self.get_glyph().repeat(self.width)
+    }
+
+    fn get_width(&self) -> Unit {
+        self.width
+    }
+}
+
+/// Bricks bucketed by the cells they cover, so a ball only tests the bricks
+/// around it instead of every brick on the field.
+struct BrickIndex {
+    /// Brick indices of every bucket, row by row
+    buckets: Vec<Vec<usize>>,
+    columns: Unit,
+    rows: Unit,
+    /// Bricks found by the last `near`, kept to reuse its allocation
+    near: Vec<usize>,
+}
+
+impl BrickIndex {
+    /// Indexes `cells` of a game of `game_size`. The index refers to the
+    /// bricks by position in `cells`, so it has to be rebuilt once bricks move
+    /// or are removed.
+    pub fn new(cells: &[Cell], game_size: (Unit, Unit)) -> BrickIndex {
+        let size = Self::get_bucket_size();
+        let columns = game_size.0 / size + 1;
+        let rows = game_size.1 / size + 1;
+        let mut index = BrickIndex {
+            buckets: vec![vec![]; columns * rows],
+            columns,
+            rows,
+            near: vec![],
+        };
+        index.rebuild(cells);
+        index
+    }
+
+    /// Indexes `cells` anew, keeping the buckets' allocations.
+    pub fn rebuild(&mut self, cells: &[Cell]) {
+        for bucket in &mut self.buckets {
+            bucket.clear();
+        }
+        for (i, cell) in cells.iter().enumerate() {
+            let last = (
+                cell.x() + cell.get_width() - 1,
+                cell.y() + cell.get_height() - 1,
+            );
+            for bucket in self.buckets_between(cell.get_pos(), last) {
+                self.buckets[bucket].push(i);
+            }
+        }
+    }
+
+    /// Bricks `ball` can collide with, a superset of the ones it touches in
+    /// ascending order, the order a check of all bricks sees them in.
+    pub fn near(&mut self, ball: &Ball) -> &[usize] {
+        // A ball collides with the bricks reaching into the cells from one left
+        // of and above its position to its far corner, the side padding
+        // widens that by its width
+        let (x, y) = ball.game_pos;
+        let extent = ball.get_extent();
+        let reach = ball.padding.side.ceil();
+        let first = (
+            (x - reach).floor().max(1f32) as Unit - 1,
+            y.floor().max(1f32) as Unit - 1,
+        );
+        let last = (
+            (x + extent + reach).ceil().max(0f32) as Unit,
+            (y + extent).ceil().max(0f32) as Unit,
+        );
+        self.near.clear();
+        for bucket in self.buckets_between(first, last) {
+            self.near.extend_from_slice(&self.buckets[bucket]);
+        }
+        self.near.sort_unstable();
+        self.near.dedup();
+        &self.near
+    }
+
+    /// Buckets covering the cells from `first` to `last`, cells past the game
+    /// fall into the buckets along its edges.
+    fn buckets_between(
+        &self,
+        first: (Unit, Unit),
+        last: (Unit, Unit),
+    ) -> impl Iterator<Item = usize> {
+        let size = Self::get_bucket_size();
+        let columns = self.columns;
+        let column = |x: Unit| (x / size).min(columns - 1);
+        let row = |y: Unit| (y / size).min(self.rows - 1);
+        let (first_column, last_column) = (column(first.0), column(last.0));
+        (row(first.1)..=row(last.1)).flat_map(move |row| {
+            (first_column..=last_column).map(move |column| row * columns + column)
+        })
+    }
+
+    /// Side length of a bucket in cells, about the size of a brick.
+    fn get_bucket_size() -> Unit {
+        4
+    }
+}
+
+/// Large brick that takes many hits, shown with a health bar above it.
+/// Destroying it clears the level.
+struct Boss {
+    pos: (Unit, Unit),
+    width: Unit,
+    height: Unit,
+    hp: u32,
+    max_hp: u32,
+}
+
+impl Boss {
+    pub fn new(pos: (Unit, Unit), width: Unit, height: Unit) -> Boss {
+        Boss {
+            pos,
+            width,
+            height,
+            hp: Self::get_default_hp(),
+            max_hp: Self::get_default_hp(),
+        }
+    }
+
+    pub fn get_default_hp() -> u32 {
+        20
+    }
+
+    /// Width of the boss in bricks
+    pub fn get_brick_span() -> Unit {
+        3
+    }
+
+    pub fn get_default_height() -> Unit {
+        3
+    }
+
+    /// Registers a hit from the ball and returns true if the boss is destroyed.
+    pub fn hit(&mut self) -> bool {
+        self.hp = self.hp.saturating_sub(1);
+        self.hp == 0
+    }
+
+    /// Whether the boss or its health bar covers `pos`.
+    pub fn covers(&self, pos: (Unit, Unit)) -> bool {
+        pos.0 >= self.x()
+            && pos.0 < self.x() + self.width
+            && pos.1 + 1 >= self.y()
+            && pos.1 < self.y() + self.height
+    }
+
+    /// Bar as wide as the boss, filled in proportion to the remaining health.
+    fn health_bar(&self) -> String {
+        let filled = (self.width as u32 * self.hp + self.max_hp - 1) / self.max_hp;
+        let filled = filled as Unit;
+        format!(
+            "{}{}",
+            graphics::BOSS_HEALTH_FULL.repeat(filled),
+            graphics::BOSS_HEALTH_EMPTY.repeat(self.width - filled)
+        )
+    }
+
+    /// Draws the health bar on the row above the boss.
+    fn write_health_bar<W: Screen>(&self, stdout: &mut W) {
+        stdout.set_fg(&color::Red);
+        let goto = stdout.goto((self.x(), self.y() - 1));
+        write!(stdout, "{}{}", goto, self.health_bar()).unwrap();
+    }
+}
+
+impl Drawable for Boss {
+    fn get_pos(&self) -> (Unit, Unit) {
+        self.pos
+    }
+
+    fn get_color(&self) -> &dyn color::Color {
+        &color::Magenta
+    }
+    fn get_graphics(&self) -> String {
+        vec![graphics::BRICK_FULL.repeat(self.width); self.height].join("\n")
+    }
+
+    fn get_width(&self) -> Unit {
+        self.width
+    }
+    fn get_height(&self) -> Unit {
+        self.height
+    }
+}
+
+/// How the ball's position inside a brick or the peddle decides which face
+/// it bounced off.
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct CollisionPadding {
+    /// Width of the band along the left and right edge that counts as a side
+    /// hit. Wider bands send more hits sideways instead of up or down.
+    side: f32,
+    /// Part of the height, from the top, that counts as a hit on the top face.
+    /// The rest counts as a hit on the bottom face.
+    face: f32,
+}
+
+impl Default for CollisionPadding {
+    fn default() -> CollisionPadding {
+        CollisionPadding {
+            side: 0.3,
+            face: 0.5,
+        }
+    }
+}
+
+/// Range the ball speed can be set to with the speed keys, as multiples of
+/// the regular speed.
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct SpeedLimits {
+    min: f32,
+    max: f32,
+}
+
+impl SpeedLimits {
+    pub fn clamp(self, multiplier: f32) -> f32 {
+        multiplier.max(self.min).min(self.max)
+    }
+}
+
+impl Default for SpeedLimits {
+    fn default() -> SpeedLimits {
+        SpeedLimits { min: 0.5, max: 2.0 }
+    }
+}
+
+/// Which sides of the playfield bounce the ball back. A ball reaching an
+/// open side is lost.
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct Walls {
+    left: bool,
+    right: bool,
+    top: bool,
+    bottom: bool,
+}
+
+/// Every side but the bottom, the classic breakout field.
+impl Default for Walls {
+    fn default() -> Walls {
+        Walls {
+            left: true,
+            right: true,
+            top: true,
+            bottom: false,
+        }
+    }
+}
+
+/// Parses a comma separated list of the closed sides like `left,right,top`,
+/// or `none` for no walls at all.
+impl FromStr for Walls {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Walls, String> {
+        let mut walls = Walls {
+            left: false,
+            right: false,
+            top: false,
+            bottom: false,
+        };
+        if s.trim() == "none" {
+            return Ok(walls);
+        }
+        for side in s.split(',') {
+            match side.trim() {
+                "left" => walls.left = true,
+                "right" => walls.right = true,
+                "top" => walls.top = true,
+                "bottom" => walls.bottom = true,
+                other => return Err(format!("unknown wall '{}'", other)),
+            }
+        }
+        Ok(walls)
+    }
+}
+
+/// Shape of the playfield. Shapes other than the rectangle add wall cells
+/// inside the borders that the ball bounces off.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum FieldShape {
+    Rectangle,
+    /// Side walls narrowing towards the peddle, leaving half the width open
+    Funnel,
+}
+
+impl FieldShape {
+    /// Whether the cell at `(x, y)` is a wall inside the borders of a game of
+    /// `game_size`. The row right above the peddle is always open, so a ball
+    /// held by the peddle is never inside a wall.
+    pub fn is_wall(self, (x, y): (Unit, Unit), game_size: (Unit, Unit)) -> bool {
+        match self {
+            FieldShape::Rectangle => false,
+            FieldShape::Funnel => {
+                let rows = Self::get_funnel_rows();
+                let last = coords::bottom(game_size.1).saturating_sub(3);
+                let first = last.saturating_sub(rows - 1);
+                if y < first || y > last {
+                    return false;
+                }
+                let depth = y - first + 1;
+                let wall = coords::inner_width(game_size.0) * depth / (4 * rows);
+                x < coords::left() + wall || x + wall > coords::right(game_size.0)
+            }
+        }
+    }
+
+    /// Every wall cell of the shape, row by row.
+    pub fn wall_cells(self, game_size: (Unit, Unit)) -> Vec<(Unit, Unit)> {
+        let mut cells = vec![];
+        for y in coords::top()..=coords::bottom(game_size.1) {
+            for x in coords::left()..=coords::right(game_size.0) {
+                if self.is_wall((x, y), game_size) {
+                    cells.push((x, y));
+                }
+            }
+        }
+        cells
+    }
+
+    /// Which velocity components a ball at `pos`, `extent` cells beyond its
+    /// top left cell, has to flip to not move into a wall with `step`. A ball
+    /// running into a corner flips both.
+    pub fn deflect(
+        self,
+        pos: (f32, f32),
+        step: (f32, f32),
+        extent: Unit,
+        game_size: (Unit, Unit),
+    ) -> (bool, bool) {
+        if self == FieldShape::Rectangle {
+            return (false, false);
+        }
+        let blocked = |x: f32, y: f32| {
+            let (x, y) = (x.round().max(0f32) as Unit, y.round().max(0f32) as Unit);
+            (x..=x + extent).any(|x| (y..=y + extent).any(|y| self.is_wall((x, y), game_size)))
+        };
+        let next = (pos.0 + step.0, pos.1 + step.1);
+        if !blocked(next.0, next.1) {
+            return (false, false);
+        }
+        match (blocked(next.0, pos.1), blocked(pos.0, next.1)) {
+            (false, false) => (true, true),
+            flips => flips,
+        }
+    }
+
+    fn get_funnel_rows() -> Unit {
+        4
+    }
+}
+
+/// Parses a field shape name: rectangle or funnel.
+impl FromStr for FieldShape {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<FieldShape, String> {
+        match s {
+            "rectangle" => Ok(FieldShape::Rectangle),
+            "funnel" => Ok(FieldShape::Funnel),
+            _ => Err(format!("unknown field shape '{}'", s)),
+        }
+    }
+}
+
+/// Edge of the playfield the peddles guard. With the peddles at the top the
+/// field is mirrored vertically, the bricks sit near the bottom and the ball
+/// is lost through the top.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum PeddleSide {
+    Bottom,
+    Top,
+}
+
+impl PeddleSide {
+    /// Row the peddles move along in a game `height` rows high, one row away
+    /// from the edge they guard.
+    pub fn peddle_row(self, height: Unit) -> Unit {
+        match self {
+            PeddleSide::Bottom => coords::bottom(height) - 1,
+            PeddleSide::Top => coords::top() + 1,
+        }
+    }
+
+    /// Playfield row along the guarded edge, between the peddles and the border.
+    pub fn edge_row(self, height: Unit) -> Unit {
+        match self {
+            PeddleSide::Bottom => coords::bottom(height),
+            PeddleSide::Top => coords::top(),
+        }
+    }
+
+    /// Vertical direction towards the peddles, 1 is down.
+    pub fn towards_peddle(self) -> f32 {
+        match self {
+            PeddleSide::Bottom => 1f32,
+            PeddleSide::Top => -1f32,
+        }
+    }
+
+    /// Where `row` of a game `height` rows high ends up on this side, rows are
+    /// laid out for the bottom and mirrored for the top.
+    pub fn mirror_row(self, row: Unit, height: Unit) -> Unit {
+        match self {
+            PeddleSide::Bottom => row,
+            PeddleSide::Top => coords::top() + coords::bottom(height) - row,
+        }
+    }
+
+    /// `walls` given as seen from a bottom peddle, the top and bottom wall
+    /// trade places for a top peddle.
+    pub fn orient(self, walls: Walls) -> Walls {
+        match self {
+            PeddleSide::Bottom => walls,
+            PeddleSide::Top => Walls {
+                top: walls.bottom,
+                bottom: walls.top,
+                ..walls
+            },
+        }
+    }
+}
+
+/// Parses a peddle side name: bottom or top.
+impl FromStr for PeddleSide {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<PeddleSide, String> {
+        match s {
+            "bottom" => Ok(PeddleSide::Bottom),
+            "top" => Ok(PeddleSide::Top),
+            _ => Err(format!("unknown peddle side '{}'", s)),
+        }
+    }
+}
+
+struct Ball {
+    game_pos: (f32, f32),
+    vel: (f32, f32),
+    /// Downward acceleration added to the velocity every frame
+    gravity: f32,
+    speed: f32,
+    /// Horizontal offset from the peddle while the ball is held by it
+    stuck_offset: Option<f32>,
+    /// Index of the peddle holding the ball while `stuck_offset` is set
+    holder: usize,
+    /// Most recent positions first, at most `trail_length` long
+    trail: VecDeque<(Unit, Unit)>,
+    trail_length: usize,
+    /// Consecutive frames the ball has been moving almost horizontally
+    shallow_ticks: u32,
+    /// Strength of the aim assist towards `target`, 0 disables it
+    assist: f32,
+    /// Share of the horizontal velocity's distance to the baseline removed
+    /// per frame, 0 disables the decay
+    spin_decay: f32,
+    /// Share of the speed going sideways when the ball last left the peddle,
+    /// the baseline the spin decays towards
+    shot_share: f32,
+    /// Side length in cells, the position is the top left cell
+    size: Unit,
+    padding: CollisionPadding,
+    color: NamedColor,
+    walls: Walls,
+    field: FieldShape,
+    /// Edge the peddles are at, the ball is sent off away from it
+    side: PeddleSide,
+    /// Save the ball one row below the peddle if the peddle is still above it
+    grace: bool,
+    /// Bounces since the game last collected them into its stats
+    bounces: u32,
+    /// Frames left as a fireball, which passes through the bricks it destroys
+    fire_ticks: u32,
+}
+
+impl Drawable for Ball {
+    fn get_pos(&self) -> (Unit, Unit) {
+        (
+            self.game_pos.0.round() as Unit,
+            self.game_pos.1.round() as Unit,
+        )
+    }
+
+    fn get_color(&self) -> &dyn color::Color {
+        if self.fire_ticks > 0 {
+            return &color::LightYellow;
+        }
+        self.color.as_color()
+    }
+    fn get_graphics(&self) -> String {
+        if self.size <= 1 {
+            return graphics::BALL_GRAPHIC.to_string();
+        }
+        vec![graphics::BALL_BLOCK.repeat(self.size); self.size].join("\n")
+    }
+
+    fn get_width(&self) -> Unit {
+        if self.size <= 1 {
+            display_width(graphics::BALL_GRAPHIC)
+        } else {
+            self.size
+        }
+    }
+
+    fn get_height(&self) -> Unit {
+        self.size.max(1)
+    }
+}
+
+impl Ball {
+    /// Moves the ball one frame. `target` is the position the aim assist
+    /// curves the ball towards, if enabled.
+    fn update(
+        &mut self,
+        game_size: (Unit, Unit),
+        peddles: &[Peddle],
+        target: Option<(f32, f32)>,
+        time_scale: f32,
+    ) -> BallUpdate {
+        if self.stuck_offset.is_some() {
+            return BallUpdate::Moving;
+        }
+
+        let extent = self.get_extent() as Unit;
+        let right = coords::right(game_size.0);
+        let bottom = coords::bottom(game_size.1);
+        let at_left = self.x() <= coords::left();
+        let at_right = self.x() + extent >= right;
+        let at_top = self.y() <= coords::top();
+        let at_bottom = self.y() + extent >= bottom;
+        let walls = self.walls;
+        let at_peddle_edge = match self.side {
+            PeddleSide::Bottom => at_bottom && !walls.bottom,
+            PeddleSide::Top => at_top && !walls.top,
+        };
+        if at_peddle_edge && self.grace {
+            if let Some(index) = peddles.iter().position(|peddle| self.is_above(peddle)) {
+                self.game_pos.1 = self.front_of(&peddles[index]);
+                self.aim_from(&peddles[index]);
+                self.bounces += 1;
+                return BallUpdate::HitPeddle(index);
+            }
+        }
+        if (at_left && !walls.left)
+            || (at_right && !walls.right)
+            || (at_top && !walls.top)
+            || (at_bottom && !walls.bottom)
+        {
+            return BallUpdate::Lost;
+        }
+        if at_left || at_right {
+            self.vel.0 *= -1f32;
+            self.bounces += 1;
+        }
+        if at_top {
+            self.vel.1 *= -1f32;
+            self.bounces += 1;
+        }
+        if at_bottom {
+            self.vel.1 = -self.vel.1.abs();
+            self.bounces += 1;
+        }
+        let step = (self.vel.0 * time_scale, self.vel.1 * time_scale);
+        let (flip_x, flip_y) = self.field.deflect(self.game_pos, step, extent, game_size);
+        if flip_x {
+            self.vel.0 *= -1f32;
+        }
+        if flip_y {
+            self.vel.1 *= -1f32;
+        }
+        if flip_x || flip_y {
+            self.bounces += 1;
+        }
+
+        let mut peddle_hit = None;
+        for (index, peddle) in peddles.iter().enumerate() {
+            if let Some(hit_dir) = self.bounce_off_peddle(peddle) {
+                peddle_hit = Some((index, hit_dir));
+                break;
+            }
+        }
+
+        self.apply_gravity(time_scale);
+        if let Some(target) = target {
+            self.apply_assist(target, time_scale);
+        }
+        self.apply_spin_decay(time_scale);
+        self.advance(time_scale);
+
+        fn clamp(val: f32, min: f32, max: f32) -> f32 {
+            val.max(min).min(max)
+        }
+        self.game_pos = (
+            clamp(
+                self.game_pos.0,
+                coords::left() as f32,
+                (right - extent) as f32,
+            ),
+            clamp(
+                self.game_pos.1,
+                coords::top() as f32,
+                (bottom - extent) as f32,
+            ),
+        );
+
+        match peddle_hit {
+            Some((index, Direction::Up)) | Some((index, Direction::Down)) => {
+                BallUpdate::HitPeddle(index)
+            }
+            _ => BallUpdate::Moving,
+        }
+    }
+
+    /// Bounces off the peddle's faces. The top face steers the ball depending on
+    /// which third of it was hit, the sides reflect it horizontally.
+    fn bounce_off_peddle(&mut self, peddle: &Peddle) -> Option<Direction> {
+        let hit_dir = self.collides_with(peddle);
+        match hit_dir {
+            Some(Direction::Left) | Some(Direction::Right) => {
+                self.change_direction(Direction::Left)
+            }
+            Some(Direction::Up) | Some(Direction::Down) => {
+                self.aim_from(peddle);
+                self.bounces += 1;
+            }
+            None => {}
+        }
+        hit_dir
+    }
+
+    /// Whether the ball shares a column with `peddle`.
+    fn is_above(&self, peddle: &Peddle) -> bool {
+        let x = self.game_pos.0;
+        x + self.get_extent() >= peddle.x() as f32 && x < (peddle.x() + peddle.get_width()) as f32
+    }
+
+    /// Points the ball away from the peddle at the same speed. The outer thirds
+    /// of the peddle send it off at 45 degrees to their side, the center third
+    /// sends it off steeply, keeping its horizontal direction.
+    fn aim_from(&mut self, peddle: &Peddle) {
+        let center = self.game_pos.0 + self.get_extent() / 2f32;
+        self.vel.0 = match peddle.third_at(center) {
+            Some(Direction::Left) => -1f32,
+            Some(_) => 1f32,
+            None => 0.5 * self.vel.0.signum(),
+        };
+        self.vel.1 = -self.side.towards_peddle();
+        self.normalize_vel();
+        self.shot_share = self.vel.0.abs() / self.speed;
+    }
+
+    /// Vertical position right next to `peddle` on the side facing the bricks.
+    fn front_of(&self, peddle: &Peddle) -> f32 {
+        match self.side {
+            PeddleSide::Bottom => (peddle.y() - self.get_height()) as f32,
+            PeddleSide::Top => (peddle.y() + 1) as f32,
+        }
+    }
+
+    /// Remembers the current position for the trail, unless the ball has not
+    /// moved to a new cell since the last call.
+    pub fn record_trail(&mut self) {
+        let pos = self.get_pos();
+        if self.trail_length == 0 || self.trail.front() == Some(&pos) {
+            return;
+        }
+        self.trail.push_front(pos);
+        self.trail.truncate(self.trail_length);
+    }
+
+    /// Trail positions behind the ball paired with their glyph, fading with age.
+    pub fn trail_cells(&self) -> Vec<((Unit, Unit), &'static str)> {
+        let pos = self.get_pos();
+        self.trail
+            .iter()
+            .filter(|&&trail_pos| trail_pos != pos)
+            .enumerate()
+            .map(|(age, &trail_pos)| {
+                let glyph = graphics::TRAIL[age.min(graphics::TRAIL.len() - 1)];
+                (trail_pos, glyph)
+            })
+            .collect()
+    }
+
+    /// Cells the ball moves through in the next `frames` frames, bouncing off
+    /// the walls like `update` but passing through bricks. Stops at the peddle
+    /// row and leaves out the cell the ball is in.
+    pub fn predict_path(&self, game_size: (Unit, Unit), frames: usize) -> Vec<(Unit, Unit)> {
+        let extent = self.get_extent() as Unit;
+        let right = coords::right(game_size.0);
+        let bottom = coords::bottom(game_size.1);
+        let peddle_row = self.side.peddle_row(game_size.1);
+        let (mut pos, mut vel) = (self.game_pos, self.vel);
+        let mut last = self.get_pos();
+        let mut path = vec![];
+        for _ in 0..frames {
+            let cell = (pos.0.round() as Unit, pos.1.round() as Unit);
+            let at_left = cell.0 <= coords::left();
+            let at_right = cell.0 + extent >= right;
+            let at_top = cell.1 <= coords::top();
+            let at_bottom = cell.1 + extent >= bottom;
+            if (at_left && !self.walls.left)
+                || (at_right && !self.walls.right)
+                || (at_top && !self.walls.top)
+                || (at_bottom && !self.walls.bottom)
+            {
+                break;
+            }
+            if at_left || at_right {
+                vel.0 *= -1f32;
+            }
+            if at_top {
+                vel.1 *= -1f32;
+            }
+            if at_bottom {
+                vel.1 = -vel.1.abs();
+            }
+            let (flip_x, flip_y) = self.field.deflect(pos, vel, extent, game_size);
+            if flip_x {
+                vel.0 *= -1f32;
+            }
+            if flip_y {
+                vel.1 *= -1f32;
+            }
+            pos = (pos.0 + vel.0, pos.1 + vel.1);
+
+            let cell = (pos.0.round() as Unit, pos.1.round() as Unit);
+            let at_peddle = match self.side {
+                PeddleSide::Bottom => cell.1 + extent >= peddle_row,
+                PeddleSide::Top => cell.1 <= peddle_row,
+            };
+            if at_peddle {
+                break;
+            }
+            if cell != last {
+                path.push(cell);
+                last = cell;
+            }
+        }
+        path
+    }
+
+    /// Holds the ball on the peddle `index` of `peddles`, keeping its current
+    /// horizontal offset.
+    pub fn stick_to(&mut self, peddles: &[Peddle], index: usize) {
+        self.holder = index;
+        self.stuck_offset = Some(self.game_pos.0 - peddles[index].x() as f32);
+        self.follow(peddles);
+    }
+
+    /// Keeps a held ball in front of its peddle as it moves.
+    pub fn follow(&mut self, peddles: &[Peddle]) {
+        if let Some(offset) = self.stuck_offset {
+            let peddle = &peddles[self.holder];
+            self.game_pos = (peddle.x() as f32 + offset, self.front_of(peddle));
+        }
+    }
+
+    /// A new ball with the same settings, heading away from the peddle from `pos`.
+    pub fn spawn_at(&self, pos: (f32, f32)) -> Ball {
+        let mut ball = Ball {
+            game_pos: pos,
+            vel: (0.5, -self.side.towards_peddle()),
+            gravity: self.gravity,
+            speed: self.speed,
+            stuck_offset: None,
+            holder: 0,
+            trail: VecDeque::with_capacity(self.trail_length),
+            trail_length: self.trail_length,
+            shallow_ticks: 0,
+            assist: self.assist,
+            spin_decay: self.spin_decay,
+            shot_share: 0f32,
+            size: self.size,
+            padding: self.padding,
+            color: self.color,
+            walls: self.walls,
+            field: self.field,
+            side: self.side,
+            grace: self.grace,
+            bounces: 0,
+            fire_ticks: 0,
+        };
+        ball.normalize_vel();
+        ball.shot_share = ball.vel.0.abs() / ball.speed;
+        ball
+    }
+
+    /// Releases a held ball from the peddle.
+    pub fn launch(&mut self, peddles: &[Peddle]) {
+        if self.stuck_offset.take().is_some() {
+            self.aim_from(&peddles[self.holder]);
+        }
+    }
+
+    /// Whether the cells of this ball and `other` overlap.
+    pub fn touches(&self, other: &Ball) -> bool {
+        let (ax, ay) = self.get_pos();
+        let (bx, by) = other.get_pos();
+        ax < bx + other.get_width()
+            && bx < ax + self.get_width()
+            && ay < by + other.get_height()
+            && by < ay + self.get_height()
+    }
+
+    /// Bounces two touching balls off each other if they are heading towards
+    /// each other. Like an elastic collision of equal masses the balls trade
+    /// velocities, so each keeps the speed the other came in with.
+    pub fn collide_with(&mut self, other: &mut Ball) -> bool {
+        if self.stuck_offset.is_some() || other.stuck_offset.is_some() || !self.touches(other) {
+            return false;
+        }
+        let offset = (
+            other.game_pos.0 - self.game_pos.0,
+            other.game_pos.1 - self.game_pos.1,
+        );
+        let closing = (other.vel.0 - self.vel.0, other.vel.1 - self.vel.1);
+        if offset.0 * closing.0 + offset.1 * closing.1 >= 0f32 {
+            return false;
+        }
+        std::mem::swap(&mut self.vel, &mut other.vel);
+        self.bounces += 1;
+        other.bounces += 1;
+        true
+    }
+
+    /// Pushes a free ball sideways towards the column `target_x` like a tilt of
+    /// a pinball table, changing its horizontal velocity by a fixed share of
+    /// its speed but never beyond the maximum speed.
+    pub fn nudge(&mut self, target_x: f32) {
+        let dx = target_x - (self.game_pos.0 + self.get_extent() / 2f32);
+        if self.stuck_offset.is_some() || dx.abs() < 0.5 {
+            return;
+        }
+        self.vel.0 += dx.signum() * Self::get_nudge() * self.speed;
+        self.normalize_vel();
+    }
+
+    /// Share of the speed a nudge adds to the horizontal velocity.
+    fn get_nudge() -> f32 {
+        0.4
+    }
+
+    /// Releases a held ball like `launch`, `boost` times as fast but no faster
+    /// than the maximum speed.
+    pub fn launch_boosted(&mut self, peddles: &[Peddle], boost: f32) {
+        if self.stuck_offset.is_some() {
+            self.launch(peddles);
+            let factor = boost.min(self.get_max_speed() / self.speed);
+            self.vel.0 *= factor;
+            self.vel.1 *= factor;
+        }
+    }
+
+    /// Sends a ball that got past the peddle back from the peddle row `floor_y`.
+    fn bounce_off_floor(&mut self, floor_y: Unit) {
+        self.game_pos.1 = match self.side {
+            PeddleSide::Bottom => floor_y as f32 - self.get_extent(),
+            PeddleSide::Top => floor_y as f32,
+        };
+        self.vel.1 = -self.side.towards_peddle() * self.vel.1.abs();
+    }
+
+    /// Curves a ball heading away from the peddle slightly towards `target`
+    /// without changing its speed.
+    fn apply_assist(&mut self, target: (f32, f32), time_scale: f32) {
+        let dx = target.0 - self.game_pos.0;
+        if self.assist <= 0f32 || self.vel.1 * self.side.towards_peddle() >= 0f32 || dx.abs() < 0.5
+        {
+            return;
+        }
+        let speed = (self.vel.0 * self.vel.0 + self.vel.1 * self.vel.1).sqrt();
+        self.vel.0 += dx.signum() * self.assist * speed * time_scale;
+        let new_speed = (self.vel.0 * self.vel.0 + self.vel.1 * self.vel.1).sqrt();
+        self.vel.0 *= speed / new_speed;
+        self.vel.1 *= speed / new_speed;
+    }
+
+    /// Eases the horizontal velocity back towards the angle the ball last left
+    /// the peddle at, removing only the spin gained since. Keeps the speed
+    /// and direction, the speed is capped like gravity caps it, so a falling
+    /// ball does not get a wider baseline.
+    fn apply_spin_decay(&mut self, time_scale: f32) {
+        if self.spin_decay <= 0f32 || self.vel.0 == 0f32 {
+            return;
+        }
+        let speed = (self.vel.0 * self.vel.0 + self.vel.1 * self.vel.1)
+            .sqrt()
+            .min(self.get_max_speed());
+        let baseline = self.vel.0.signum() * speed * self.shot_share;
+        let share = (self.spin_decay * time_scale).min(1f32);
+        self.vel.0 += (baseline - self.vel.0) * share;
+        let vertical = (speed * speed - self.vel.0 * self.vel.0).max(0f32).sqrt();
+        self.vel.1 = if self.vel.1 < 0f32 {
+            -vertical
+        } else {
+            vertical
+        };
+    }
+
+    /// A ball moving almost horizontally can bounce between the side walls
+    /// forever. Once that has gone on for too long, steepen its angle.
+    fn check_shallow_angle(&mut self) {
+        if self.vel.1.abs() >= Self::get_min_vertical_ratio() * self.speed {
+            self.shallow_ticks = 0;
+            return;
+        }
+        self.shallow_ticks += 1;
+        if self.shallow_ticks > Self::get_shallow_tick_limit() {
+            self.restore_vertical_velocity();
+        }
+    }
+
+    /// Turns the ball to a clearly vertical angle, keeping its speed and
+    /// horizontal and vertical direction. A ball with no vertical motion heads down.
+    fn restore_vertical_velocity(&mut self) {
+        let speed = (self.vel.0 * self.vel.0 + self.vel.1 * self.vel.1).sqrt();
+        let vertical = speed * Self::get_restored_vertical_ratio();
+        let horizontal = (speed * speed - vertical * vertical).sqrt();
+        self.vel.1 = if self.vel.1 < 0f32 {
+            -vertical
+        } else {
+            vertical
+        };
+        self.vel.0 = if self.vel.0 < 0f32 {
+            -horizontal
+        } else {
+            horizontal
+        };
+        self.shallow_ticks = 0;
+    }
+
+    /// Pulls the ball towards the peddle, limited to the maximum ball speed. A
+    /// peddle hit normalizes the velocity again which undoes the acceleration.
+    fn apply_gravity(&mut self, time_scale: f32) {
+        let towards = self.side.towards_peddle();
+        let pulled = (self.vel.1 * towards + self.gravity * time_scale).min(self.get_max_speed());
+        self.vel.1 = pulled * towards;
+    }
+
+    /// Number of substeps to split a frame's movement into, so that no step
+    /// advances more than `get_max_advance` in either axis and cannot skip
+    /// over a brick however fast the ball is.
+    pub fn substeps(&self, time_scale: f32) -> u32 {
+        let largest = self.vel.0.abs().max(self.vel.1.abs()) * time_scale;
+        (largest / Self::get_max_advance()).ceil().max(1f32) as u32
+    }
+
+    /// Frames a ball stays a fireball, three seconds.
+    fn get_fire_ticks() -> u32 {
+        3 * (1000 / FRAME_MILLIS) as u32
+    }
+
+    /// Longest distance the ball moves in one step, half a cell.
+    fn get_max_advance() -> f32 {
+        0.5
+    }
+
+    /// Moves the ball along its velocity, scaled by `time_scale` (1.0 is one frame).
+    fn advance(&mut self, time_scale: f32) {
+        self.game_pos.0 += self.vel.0 * time_scale;
+        self.game_pos.1 += self.vel.1 * time_scale;
+    }
+
+    fn collides_with<T: Drawable>(&self, target: &T) -> Option<Direction> {
+        let target_x = target.x() as f32;
+        let target_y = target.y() as f32;
+        let target_width = target.get_width() as f32;
+        let target_height = target.get_height() as f32;
+
+        // Left and top edge of the ball, a larger ball reaches `extent` further
+        let x = self.game_pos.0;
+        let y = self.game_pos.1;
+        let extent = self.get_extent();
+
+        fn in_range(val: f32, min: f32, max: f32) -> bool {
+            val >= min && val <= max
+        }
+
+        let side = self.padding.side;
+        let face = self.padding.face * target_height;
+        let mut side_hit = None;
+        if in_range(y, target_y - extent, target_y + target_height) {
+            if in_range(x + extent, target_x, target_x + side) && self.vel.0 > 0f32 {
+                side_hit = Some(Direction::Left);
+            }
+            if in_range(x, target_x + target_width - side, target_x + target_width)
+                && self.vel.0 < 0f32
+            {
+                side_hit = Some(Direction::Left);
+            }
+        }
+
+        let mut face_hit = None;
+        if in_range(x, target_x - extent, target_x + target_width) {
+            if in_range(y + extent, target_y, target_y + face) {
+                face_hit = Some(Direction::Down);
+            } else if in_range(y, target_y + face, target_y + target_height) {
+                face_hit = Some(Direction::Up);
+            }
+        }
+
+        match (side_hit, face_hit) {
+            (Some(side_hit), Some(face_hit)) => {
+                Some(Self::resolve_corner(self.vel, side_hit, face_hit))
+            }
+            (side_hit, face_hit) => side_hit.or(face_hit),
+        }
+    }
+
+    /// Picks the face a ball with velocity `vel` bounces off when it touches a
+    /// side and the top or bottom at once. The ball bounces off the side only
+    /// if it moves more horizontally than vertically, ties go to the top or bottom.
+    fn resolve_corner(vel: (f32, f32), side_hit: Direction, face_hit: Direction) -> Direction {
+        if vel.0.abs() > vel.1.abs() {
+            side_hit
+        } else {
+            face_hit
+        }
+    }
+
+    /// Whether the ball is drawn over any cell of `brick`.
+    pub fn overlaps(&self, brick: &Cell) -> bool {
+        let (x, y) = self.get_pos();
+        let size = self.get_height();
+        x < brick.x() + brick.get_width()
+            && brick.x() < x + size
+            && y <= brick.y()
+            && brick.y() < y + size
+    }
+
+    /// Distance from the top left cell to the bottom right cell of the ball.
+    fn get_extent(&self) -> f32 {
+        (self.get_height() - 1) as f32
+    }
+
+    pub fn change_direction(&mut self, dir: Direction) {
+        match dir {
+            Direction::Left | Direction::Right => self.vel.0 *= -1f32,
+            Direction::Up | Direction::Down => self.vel.1 *= -1f32,
+        }
+        self.bounces += 1;
+    }
+
+    fn normalize_vel(&mut self) {
+        let magnitude: f32 = (self.vel.0*self.vel.0 + self.vel.1*self.vel.1).sqrt();
+        self.vel.0 /= magnitude;
+        self.vel.1 /= magnitude;
+
+        self.vel.0 *= self.speed;
+        self.vel.1 *= self.speed;
+    }
+
+    fn get_speed() -> f32 {
+        0.4
+    }
+
+    /// How far ahead the predicted path reaches, in frames.
+    fn get_prediction_frames() -> usize {
+        150
+    }
+
+    /// Vertical velocity, relative to the speed, below which the ball counts as too shallow.
+    fn get_min_vertical_ratio() -> f32 {
+        0.15
+    }
+
+    fn get_restored_vertical_ratio() -> f32 {
+        0.5
+    }
+
+    /// Three seconds at the regular frame rate.
+    fn get_shallow_tick_limit() -> u32 {
+        150
+    }
+
+    fn get_max_speed(&self) -> f32 {
+        2f32 * self.speed
+    }
+}
+
+/// A launch charged by holding the launch key with `--charge-launch`.
+/// Terminals do not report released keys, so the key counts as released once
+/// its repeats stop arriving.
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct LaunchCharge {
+    player: usize,
+    /// Frames since the key was first pressed
+    ticks: u32,
+    /// Frames since the key last repeated
+    idle_ticks: u32,
+}
+
+impl LaunchCharge {
+    pub fn new(player: usize) -> LaunchCharge {
+        LaunchCharge {
+            player,
+            ticks: 0,
+            idle_ticks: 0,
+        }
+    }
+
+    /// Frames the key was held, up to its last repeat.
+    pub fn held_ticks(&self) -> u32 {
+        self.ticks - self.idle_ticks
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum GameState {
+    /// Counting down before the round starts, holds the remaining ticks
+    Countdown(u32),
+    Playing,
+}
+
+impl GameState {
+    /// The state after one more frame has passed.
+    pub fn next(self) -> GameState {
+        match self {
+            GameState::Countdown(ticks) if ticks > 1 => GameState::Countdown(ticks - 1),
+            _ => GameState::Playing,
+        }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+enum BallUpdate {
+    Moving,
+    /// Bounced off the top of the peddle with this index
+    HitPeddle(usize),
+    /// Fell past the peddle
+    Lost,
+}
+
+#[derive(PartialEq, Debug)]
+enum TickResult {
+    Running,
+    BallLost,
+    Won,
+    /// The time attack timer ran out before the field was cleared
+    TimeUp,
+    /// The descending bricks reached the peddle row
+    Overrun,
+}
+
+#[derive(PartialEq, Debug)]
+enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+struct Peddle {
+    /// Horizontal position, eased towards `target_x` every frame
+    game_x: f32,
+    /// Where the controls want the peddle to be, always inside the borders
+    target_x: f32,
+    y: Unit,
+    /// Characters the peddle is drawn with, its display width is the peddle width
+    graphic: String,
+    color: NamedColor,
+    /// Frames left of the bounce animation, the peddle is drawn a row lower
+    /// meanwhile without moving its collision box
+    bounce_ticks: u32,
+    /// Frames left of blinking, shown while the player is spared from losing
+    flash_ticks: u32,
+}
+
+impl Drawable for Peddle {
+    fn get_pos(&self) -> (Unit, Unit) {
+        (self.game_x.round() as Unit, self.y)
+    }
+
+    /// Drawing and clearing both go through here, so the nudged peddle is
+    /// erased where it was drawn.
+    fn get_draw_pos(&self, row: Unit) -> (Unit, Unit) {
+        let nudge = if self.bounce_ticks > 0 { 1 } else { 0 };
+        (self.x(), self.y + nudge + row)
+    }
+
+    fn get_color(&self) -> &dyn color::Color {
+        if (self.flash_ticks / Self::get_flash_ticks()) % 2 == 1 {
+            return &color::White;
+        }
+        self.color.as_color()
+    }
+    fn get_graphics(&self) -> String {
+        self.graphic.clone()
+    }
+
+    fn get_width(&self) -> Unit {
+        display_width(&self.graphic)
+    }
+}
+
+impl Peddle {
+    /// Moves one step left or right. The peddle is kept between the side
+    /// borders, other directions are ignored.
+    pub fn move_in_dir(&mut self, dir: Direction, game_width: Unit) {
+        let speed = Self::get_speed() as isize;
+        match dir {
+            Direction::Left => self.move_by(-speed, game_width),
+            Direction::Right => self.move_by(speed, game_width),
+            Direction::Up | Direction::Down => {}
+        }
+    }
+
+    /// Moves the target `dx` columns, clamped so the whole peddle stays inside
+    /// the borders. The peddle itself follows in `ease`.
+    pub fn move_by(&mut self, dx: isize, game_width: Unit) {
+        self.target_x = Self::clamp_x(self.target_x + dx as f32, self.get_width(), game_width);
+    }
+
+    /// Places the peddle at `x` right away, without easing.
+    pub fn jump_to(&mut self, x: f32, game_width: Unit) {
+        self.target_x = Self::clamp_x(x, self.get_width(), game_width);
+        self.game_x = self.target_x;
+    }
+
+    /// Moves part of the way towards the target, snapping once close enough.
+    pub fn ease(&mut self, time_scale: f32) {
+        let distance = self.target_x - self.game_x;
+        if distance.abs() < Self::get_snap_distance() {
+            self.game_x = self.target_x;
+        } else {
+            self.game_x += distance * (Self::get_easing() * time_scale).min(1f32);
+        }
+    }
+
+    /// The third of the peddle below column `x`: `Left` or `Right` for the
+    /// outer thirds and `None` for the center one. Columns past the ends count
+    /// as the outer thirds.
+    pub fn third_at(&self, x: f32) -> Option<Direction> {
+        let third = (x - self.game_x) * 3f32 / self.get_width().max(1) as f32;
+        if third < 1f32 {
+            Some(Direction::Left)
+        } else if third >= 2f32 {
+            Some(Direction::Right)
+        } else {
+            None
+        }
+    }
+
+    /// Leftmost column the peddle can occupy is the first playfield column.
+    /// The rightmost keeps its last column on the last playfield column.
+    pub fn clamp_x(x: f32, width: Unit, game_width: Unit) -> f32 {
+        let min_x = coords::left();
+        let max_x = (coords::right(game_width) + 1)
+            .saturating_sub(width)
+            .max(min_x);
+        x.max(min_x as f32).min(max_x as f32)
+    }
+
+    /// Horizontal step that brings the peddle center closer to `target_x`.
+    /// A proportional controller clamped to the peddle speed, used by the demo AI.
+    pub fn steer_towards(&self, target_x: f32) -> isize {
+        let center = self.target_x + (self.get_width() / 2) as f32;
+        let step = ((target_x - center) * Self::get_ai_gain()).round() as isize;
+        let max_step = Self::get_speed() as isize;
+        step.max(-max_step).min(max_step)
+    }
+
+    pub fn get_speed() -> Unit {
+        3 as Unit
+    }
+
+    fn get_ai_gain() -> f32 {
+        0.5
+    }
+
+    /// Starts the bounce animation after a ball came off the peddle.
+    pub fn bounce(&mut self) {
+        self.bounce_ticks = Self::get_bounce_ticks();
+    }
+
+    /// Frames the peddle stays in each color while it blinks.
+    fn get_flash_ticks() -> u32 {
+        5
+    }
+
+    fn get_bounce_ticks() -> u32 {
+        1
+    }
+
+    /// Share of the remaining distance to the target covered each frame.
+    fn get_easing() -> f32 {
+        0.5
+    }
+
+    fn get_snap_distance() -> f32 {
+        0.05
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum PowerUpKind {
+    /// The peddle catches the ball until it is launched again
+    Sticky,
+    /// A floor below the peddle that saves the ball once
+    SafetyNet,
+    /// The launch key fires projectiles from the peddle for a while
+    Laser,
+}
+
+/// A power-up capsule falling from a destroyed brick.
+struct PowerUp {
+    game_pos: (f32, f32),
+    kind: PowerUpKind,
+}
+
+impl Drawable for PowerUp {
+    fn get_pos(&self) -> (Unit, Unit) {
+        (
+            self.game_pos.0.round() as Unit,
+            self.game_pos.1.round() as Unit,
+        )
+    }
+
+    fn get_color(&self) -> &dyn color::Color {
+        match self.kind {
+            PowerUpKind::Sticky => &color::Yellow,
+            PowerUpKind::SafetyNet => &color::Green,
+            PowerUpKind::Laser => &color::Magenta,
+        }
+    }
+    fn get_graphics(&self) -> String {
+        match self.kind {
+            PowerUpKind::Sticky => graphics::POWER_UP_STICKY.to_string(),
+            PowerUpKind::SafetyNet => graphics::POWER_UP_SAFETY_NET.to_string(),
+            PowerUpKind::Laser => graphics::POWER_UP_LASER.to_string(),
+        }
+    }
+
+    fn get_width(&self) -> Unit {
+        1
+    }
+}
+
+impl PowerUp {
+    /// Moves towards the peddles at `side`.
+    pub fn fall(&mut self, side: PeddleSide, time_scale: f32) {
+        self.game_pos.1 += side.towards_peddle() * Self::get_fall_speed() * time_scale;
+    }
+
+    pub fn is_caught_by(&self, peddle: &Peddle) -> bool {
+        self.y() == peddle.y()
+            && self.x() >= peddle.x()
+            && self.x() < peddle.x() + peddle.get_width()
+    }
+
+    fn get_fall_speed() -> f32 {
+        0.2
+    }
+}
+
+/// A shot fired away from the laser peddle, destroying the first brick it meets.
+struct Projectile {
+    game_pos: (f32, f32),
+}
+
+impl Drawable for Projectile {
+    fn get_pos(&self) -> (Unit, Unit) {
+        (
+            self.game_pos.0.round() as Unit,
+            self.game_pos.1.round() as Unit,
+        )
+    }
+
+    fn get_color(&self) -> &dyn color::Color {
+        &color::Magenta
+    }
+    fn get_graphics(&self) -> String {
+        graphics::PROJECTILE.to_string()
+    }
+
+    fn get_width(&self) -> Unit {
+        1
+    }
+}
+
+impl Projectile {
+    /// Moves away from the peddles at `side` at most one row per frame, so no
+    /// brick is skipped.
+    pub fn rise(&mut self, side: PeddleSide, time_scale: f32) {
+        self.game_pos.1 -= side.towards_peddle() * Self::get_speed() * time_scale;
+    }
+
+    pub fn hits<T: Drawable>(&self, target: &T) -> bool {
+        let (x, y) = self.get_pos();
+        y >= target.y()
+            && y < target.y() + target.get_height()
+            && x >= target.x()
+            && x < target.x() + target.get_width()
+    }
+
+    fn get_speed() -> f32 {
+        1.0
+    }
+}
+
+/// Debris flying off a destroyed brick, purely visual.
+struct Particle {
+    game_pos: (f32, f32),
+    vel: (f32, f32),
+    /// Frames left before the particle disappears
+    ticks_left: u32,
+}
+
+impl Drawable for Particle {
+    fn get_pos(&self) -> (Unit, Unit) {
+        (
+            self.game_pos.0.round() as Unit,
+            self.game_pos.1.round() as Unit,
+        )
+    }
+
+    fn get_color(&self) -> &dyn color::Color {
+        &color::LightYellow
+    }
+    fn get_graphics(&self) -> String {
+        let age = (Self::get_lifetime() - self.ticks_left) as usize;
+        let glyphs = graphics::PARTICLES.len();
+        graphics::PARTICLES[age * glyphs / Self::get_lifetime() as usize].to_string()
+    }
+
+    fn get_width(&self) -> Unit {
+        1
+    }
+}
+
+impl Particle {
+    /// A particle leaving `pos` in a random direction.
+    pub fn scatter<T: Rng>(pos: (f32, f32), rng: &mut T) -> Particle {
+        Particle {
+            game_pos: pos,
+            vel: (rng.gen_range(-1f32, 1f32), rng.gen_range(-0.5, 0.5)),
+            ticks_left: Self::get_lifetime(),
+        }
+    }
+
+    pub fn advance(&mut self, time_scale: f32) {
+        self.game_pos.0 += self.vel.0 * time_scale;
+        self.game_pos.1 += self.vel.1 * time_scale;
+        self.ticks_left = self.ticks_left.saturating_sub(1);
+    }
+
+    /// Whether the particle is still alive and inside the playfield of a game
+    /// of `game_size`, so it never draws over the border.
+    pub fn is_visible(&self, game_size: (Unit, Unit)) -> bool {
+        let (x, y) = self.game_pos;
+        self.ticks_left > 0
+            && x >= coords::left() as f32
+            && x <= coords::right(game_size.0) as f32
+            && y >= coords::top() as f32
+            && y <= coords::bottom(game_size.1) as f32
+    }
+
+    pub fn get_lifetime() -> u32 {
+        8
+    }
+
+    pub fn get_count_per_brick() -> usize {
+        4
+    }
+}
+
+/// A short message shown at the top of the playfield for a few frames.
+struct Toast {
+    text: String,
+    /// Frames left to show the message, it is blanked once this reaches 0
+    ticks_left: u32,
+}
+
+/// A dialog row with `text` centered between the side borders.
+fn dialog_line(inner_width: Unit, text: &str) -> String {
+    format!(
+        "{}{}{}",
+        graphics::BORDER_VERTICAL,
+        centered(inner_width, text),
+        graphics::BORDER_VERTICAL
+    )
+}
+
+/// `text` padded with spaces on both sides to `width` columns.
+fn centered(width: Unit, text: &str) -> String {
+    let padding = width.saturating_sub(display_width(text));
+    format!(
+        "{}{}{}",
+        " ".repeat(padding / 2),
+        text,
+        " ".repeat(padding - padding / 2)
+    )
+}
+
+/// Adds a centered line of `text` above the bottom border of a dialog box.
+fn insert_dialog_line(dialog: &str, text: &str) -> String {
+    let mut lines: Vec<&str> = dialog.lines().collect();
+    let inner_width = display_width(lines[0]) - 2;
+    let line = dialog_line(inner_width, text);
+    let bottom = lines.len() - 1;
+    lines.insert(bottom, &line);
+    lines.join("\n")
+}
+
+/// Smoothed frame time measurement for the debug overlay.
+struct FrameTimer {
+    last_frame: Option<Instant>,
+    average_millis: f32,
+}
+
+impl FrameTimer {
+    pub fn new() -> FrameTimer {
+        FrameTimer {
+            last_frame: None,
+            average_millis: 0f32,
+        }
+    }
+
+    /// Records that a frame has ended now.
+    pub fn tick(&mut self) {
+        let now = Instant::now();
+        if let Some(last_frame) = self.last_frame {
+            let elapsed = now.duration_since(last_frame);
+            self.record(elapsed.as_secs_f32() * 1000f32);
+        }
+        self.last_frame = Some(now);
+    }
+
+    /// Folds a frame time into the exponential moving average.
+    pub fn record(&mut self, frame_millis: f32) {
+        if self.average_millis == 0f32 {
+            self.average_millis = frame_millis;
+        } else {
+            self.average_millis += (frame_millis - self.average_millis) * Self::get_smoothing();
+        }
+    }
+
+    pub fn fps(&self) -> f32 {
+        if self.average_millis > 0f32 {
+            1000f32 / self.average_millis
+        } else {
+            0f32
+        }
+    }
+
+    pub fn label(&self) -> String {
+        format!(" {:5.1}ms {:3.0}fps ", self.average_millis, self.fps())
+    }
+
+    fn get_smoothing() -> f32 {
+        0.1
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    /// Multiplier applied to the ball speed.
+    pub fn get_speed_factor(self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.75,
+            Difficulty::Normal => 1.0,
+            Difficulty::Hard => 1.25,
+        }
+    }
+
+    /// Rows between the peddle and the ball at the start of a game, giving
+    /// the player more time to get under it on easier settings.
+    pub fn get_start_gap(self) -> Unit {
+        match self {
+            Difficulty::Easy => 10,
+            Difficulty::Normal => 8,
+            Difficulty::Hard => 6,
+        }
+    }
+
+    pub fn next(self) -> Difficulty {
+        match self {
+            Difficulty::Easy => Difficulty::Normal,
+            Difficulty::Normal => Difficulty::Hard,
+            Difficulty::Hard => Difficulty::Easy,
+        }
+    }
+
+    pub fn prev(self) -> Difficulty {
+        self.next().next()
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match *self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Difficulty, String> {
+        match s {
+            "easy" => Ok(Difficulty::Easy),
+            "normal" => Ok(Difficulty::Normal),
+            "hard" => Ok(Difficulty::Hard),
+            _ => Err(format!("unknown difficulty '{}'", s)),
+        }
+    }
+}
+
+/// Look of the game border.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Theme {
+    /// A static blue border
+    Classic,
+    /// A border cycling through the colors of the rainbow
+    Rainbow,
+}
+
+impl Theme {
+    pub fn border_color(self, frame: u64) -> Box<dyn color::Color> {
+        match self {
+            Theme::Classic => Box::new(color::Blue),
+            Theme::Rainbow => match Self::rainbow_index(frame) {
+                0 => Box::new(color::Red),
+                1 => Box::new(color::Yellow),
+                2 => Box::new(color::Green),
+                3 => Box::new(color::Cyan),
+                4 => Box::new(color::Blue),
+                _ => Box::new(color::Magenta),
+            },
+        }
+    }
+
+    /// Whether the border looks different at `frame` than the frame before.
+    pub fn border_changes(self, frame: u64) -> bool {
+        self == Theme::Rainbow
+            && frame > 0
+            && Self::rainbow_index(frame) != Self::rainbow_index(frame - 1)
+    }
+
+    /// Rainbow color shown at `frame`, advancing every few frames.
+    pub fn rainbow_index(frame: u64) -> usize {
+        (frame / Self::get_frames_per_color()) as usize % Self::get_rainbow_len()
+    }
+
+    fn get_rainbow_len() -> usize {
+        6
+    }
+
+    fn get_frames_per_color() -> u64 {
+        10
+    }
+}
+
+impl FromStr for Theme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Theme, String> {
+        match s {
+            "classic" => Ok(Theme::Classic),
+            "rainbow" => Ok(Theme::Rainbow),
+            _ => Err(format!("unknown theme '{}'", s)),
+        }
+    }
+}
+
+/// Colors that can be picked by name on the command line.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+enum NamedColor {
+    Red,
+    Green,
+    Blue,
+    Magenta,
+    Cyan,
+    Yellow,
+}
+
+impl NamedColor {
+    pub fn to_color(self) -> Box<dyn color::Color> {
+        match self {
+            NamedColor::Red => Box::new(color::Red),
+            NamedColor::Green => Box::new(color::Green),
+            NamedColor::Blue => Box::new(color::Blue),
+            NamedColor::Magenta => Box::new(color::Magenta),
+            NamedColor::Cyan => Box::new(color::Cyan),
+            NamedColor::Yellow => Box::new(color::Yellow),
+        }
+    }
+
+    /// The same color as `to_color`, for drawables that keep only the name.
+    pub fn as_color(self) -> &'static dyn color::Color {
+        match self {
+            NamedColor::Red => &color::Red,
+            NamedColor::Green => &color::Green,
+            NamedColor::Blue => &color::Blue,
+            NamedColor::Magenta => &color::Magenta,
+            NamedColor::Cyan => &color::Cyan,
+            NamedColor::Yellow => &color::Yellow,
+        }
+    }
+
+    /// The name accepted on the command line.
+    pub fn name(self) -> &'static str {
+        match self {
+            NamedColor::Red => "red",
+            NamedColor::Green => "green",
+            NamedColor::Blue => "blue",
+            NamedColor::Magenta => "magenta",
+            NamedColor::Cyan => "cyan",
+            NamedColor::Yellow => "yellow",
+        }
+    }
+}
+
+impl FromStr for NamedColor {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<NamedColor, String> {
+        match s {
+            "red" => Ok(NamedColor::Red),
+            "green" => Ok(NamedColor::Green),
+            "blue" => Ok(NamedColor::Blue),
+            "magenta" => Ok(NamedColor::Magenta),
+            "cyan" => Ok(NamedColor::Cyan),
+            "yellow" => Ok(NamedColor::Yellow),
+            _ => Err(format!("unknown color '{}'", s)),
+        }
+    }
+}
+
+/// Colors of damaged bricks by the hits they have left, so the damage stays
+/// readable whatever color the brick started with.
+#[derive(Clone, PartialEq, Debug)]
+struct DamageColors {
+    /// The color with one hit left first
+    colors: Vec<NamedColor>,
+}
+
+impl DamageColors {
+    /// Color of a damaged brick with `hits_left` hits left, `None` keeps the
+    /// brick's own color.
+    pub fn get(&self, hits_left: u8) -> Option<NamedColor> {
+        if hits_left == 0 {
+            return None;
+        }
+        self.colors.get(hits_left as usize - 1).cloned()
+    }
+}
+
+impl Default for DamageColors {
+    /// Red for the last hit, yellow and then green for sturdier bricks.
+    fn default() -> DamageColors {
+        DamageColors {
+            colors: vec![NamedColor::Red, NamedColor::Yellow, NamedColor::Green],
+        }
+    }
+}
+
+/// Parses a comma separated list of colors like `red,yellow`, the first one
+/// for one hit left, or `none` to keep the brick colors.
+impl FromStr for DamageColors {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<DamageColors, String> {
+        if s.trim() == "none" {
+            return Ok(DamageColors { colors: vec![] });
+        }
+        let colors = s
+            .split(',')
+            .map(|name| name.trim().parse())
+            .collect::<Result<Vec<NamedColor>, String>>()?;
+        Ok(DamageColors { colors })
+    }
+}
+
+/// Relative odds of each color for normal and tough bricks in a random layout.
+#[derive(Debug)]
+struct ColorWeights {
+    weights: Vec<(NamedColor, u32)>,
+}
+
+impl Default for ColorWeights {
+    fn default() -> ColorWeights {
+        ColorWeights {
+            weights: vec![
+                (NamedColor::Red, 1),
+                (NamedColor::Green, 1),
+                (NamedColor::Blue, 1),
+                (NamedColor::Magenta, 1),
+            ],
+        }
+    }
+}
+
+impl ColorWeights {
+    /// Picks a color with a probability proportional to its weight.
+    pub fn pick<T: Rng>(&self, rng: &mut T) -> NamedColor {
+        let total: u32 = self.weights.iter().map(|&(_, weight)| weight).sum();
+        let mut roll = rng.gen_range(0, total);
+        for &(color, weight) in &self.weights {
+            if roll < weight {
+                return color;
+            }
+            roll -= weight;
+        }
+        unreachable!("roll is below the total weight")
+    }
+}
+
+/// Parses a comma separated list like `red=3,blue=1`.
+impl FromStr for ColorWeights {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ColorWeights, String> {
+        let mut weights = vec![];
+        for entry in s.split(',') {
+            let mut parts = entry.trim().splitn(2, '=');
+            let color = parts.next().unwrap_or("").parse()?;
+            let weight = match parts.next() {
+                Some(weight) => weight
+                    .parse()
+                    .map_err(|_| format!("invalid weight '{}'", weight))?,
+                None => return Err(format!("missing weight for '{}'", entry)),
+            };
+            weights.push((color, weight));
+        }
+        if weights.iter().map(|&(_, weight)| weight).sum::<u32>() == 0 {
+            return Err("at least one color needs a weight above 0".to_string());
+        }
+        Ok(ColorWeights { weights })
+    }
+}
+
+/// How bricks are colored: drawn from the color weights, or one color per
+/// row like the classic arcade game.
+#[derive(Clone, PartialEq, Debug)]
+enum BrickColors {
+    Random,
+    /// Colors of the rows from the top, repeating when there are more rows
+    Rows(Vec<NamedColor>),
+}
+
+impl BrickColors {
+    /// Color of the brick in row `row`, or `None` to draw it from the weights.
+    pub fn row_color(&self, row: usize) -> Option<NamedColor> {
+        match *self {
+            BrickColors::Random => None,
+            BrickColors::Rows(ref colors) => Some(colors[row % colors.len()]),
+        }
+    }
+
+    pub fn get_row_colors() -> Vec<NamedColor> {
+        vec![
+            NamedColor::Red,
+            NamedColor::Yellow,
+            NamedColor::Green,
+            NamedColor::Cyan,
+            NamedColor::Blue,
+            NamedColor::Magenta,
+        ]
+    }
+}
+
+/// Parses `random`, `rows` or rows with their own colors like `rows=red,blue`.
+impl FromStr for BrickColors {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<BrickColors, String> {
+        let mut parts = s.trim().splitn(2, '=');
+        match (parts.next(), parts.next()) {
+            (Some("random"), None) => Ok(BrickColors::Random),
+            (Some("rows"), None) => Ok(BrickColors::Rows(Self::get_row_colors())),
+            (Some("rows"), Some(list)) => {
+                let colors = list
+                    .split(',')
+                    .map(|name| name.trim().parse())
+                    .collect::<Result<Vec<NamedColor>, String>>()?;
+                Ok(BrickColors::Rows(colors))
+            }
+            _ => Err(format!("unknown brick colors '{}'", s)),
+        }
+    }
+}
+
+/// Points for destroying a brick, looked up by its color first and its kind
+/// second. Bricks matching neither are worth the base points.
+#[derive(Debug)]
+struct ScoreTable {
+    colors: Vec<(NamedColor, u32)>,
+    kinds: Vec<(BrickKind, u32)>,
+}
+
+impl Default for ScoreTable {
+    fn default() -> ScoreTable {
+        ScoreTable {
+            colors: vec![],
+            kinds: vec![(BrickKind::Tough, 20)],
+        }
+    }
+}
+
+impl ScoreTable {
+    pub fn points(&self, cell: &Cell) -> u32 {
+        let by_color = self
+            .colors
+            .iter()
+            .find(|&&(color, _)| Some(color) == cell.named_color)
+            .map(|&(_, points)| points);
+        let by_kind = self
+            .kinds
+            .iter()
+            .find(|&&(kind, _)| kind == cell.kind)
+            .map(|&(_, points)| points);
+        by_color.or(by_kind).unwrap_or(Self::get_base_points())
+    }
+
+    /// Worth of a brick not in the table, also scored for every boss hit.
+    pub fn get_base_points() -> u32 {
+        10
+    }
+
+    fn parse_kind(name: &str) -> Option<BrickKind> {
+        match name {
+            "normal" => Some(BrickKind::Normal),
+            "tough" => Some(BrickKind::Tough),
+            "explosive" => Some(BrickKind::Explosive),
+            "multiball" => Some(BrickKind::MultiBall),
+            "frozen" => Some(BrickKind::Frozen),
+            _ => None,
+        }
+    }
+}
+
+/// Parses a comma separated list like `red=20,tough=30` of colors or brick
+/// kinds and their points, overriding the defaults.
+impl FromStr for ScoreTable {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<ScoreTable, String> {
+        let mut table = ScoreTable::default();
+        for entry in s.split(',') {
+            let mut parts = entry.trim().splitn(2, '=');
+            let name = parts.next().unwrap_or("");
+            let points = match parts.next() {
+                Some(points) => points
+                    .parse()
+                    .map_err(|_| format!("invalid points '{}'", points))?,
+                None => return Err(format!("missing points for '{}'", entry)),
+            };
+            if let Some(kind) = ScoreTable::parse_kind(name) {
+                table.kinds.retain(|&(other, _)| other != kind);
+                table.kinds.push((kind, points));
+            } else {
+                let color: NamedColor = name
+                    .parse()
+                    .map_err(|_| format!("unknown brick kind or color '{}'", name))?;
+                table.colors.retain(|&(other, _)| other != color);
+                table.colors.push((color, points));
+            }
+        }
+        Ok(table)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum MenuItem {
+    Start,
+    Difficulty,
+    Seed,
+    Scores,
+    Quit,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum MenuAction {
+    Start,
+    Scores,
+    Quit,
+}
+
+/// Interactive start menu, edits the game config in place.
+struct Menu {
+    selected: usize,
+}
+
+impl Menu {
+    pub fn new() -> Menu {
+        Menu { selected: 0 }
+    }
+
+    pub fn get_items() -> [MenuItem; 5] {
+        [
+            MenuItem::Start,
+            MenuItem::Difficulty,
+            MenuItem::Seed,
+            MenuItem::Scores,
+            MenuItem::Quit,
+        ]
+    }
+
+    pub fn selected_item(&self) -> MenuItem {
+        Self::get_items()[self.selected]
+    }
+
+    /// Applies a key press to the menu, returns an action once the player
+    /// decided to start or quit.
+    pub fn handle_key(&mut self, key: Key, config: &mut GameConfig) -> Option<MenuAction> {
+        let item_count = Self::get_items().len();
+        match (key, self.selected_item()) {
+            (Key::Char('q'), _) => return Some(MenuAction::Quit),
+            (Key::Up, _) | (Key::Char('k'), _) => {
+                self.selected = (self.selected + item_count - 1) % item_count
+            }
+            (Key::Down, _) | (Key::Char('j'), _) => {
+                self.selected = (self.selected + 1) % item_count
+            }
+            (Key::Char(' '), MenuItem::Start) | (Key::Char('\n'), MenuItem::Start) => {
+                return Some(MenuAction::Start)
+            }
+            (Key::Char(' '), MenuItem::Scores) | (Key::Char('\n'), MenuItem::Scores) => {
+                return Some(MenuAction::Scores)
+            }
+            (Key::Char(' '), MenuItem::Quit) | (Key::Char('\n'), MenuItem::Quit) => {
+                return Some(MenuAction::Quit)
+            }
+            (Key::Left, MenuItem::Difficulty) | (Key::Char('h'), MenuItem::Difficulty) => {
+                config.difficulty = config.difficulty.prev()
+            }
+            (Key::Right, MenuItem::Difficulty)
+            | (Key::Char('l'), MenuItem::Difficulty)
+            | (Key::Char(' '), MenuItem::Difficulty)
+            | (Key::Char('\n'), MenuItem::Difficulty) => {
+                config.difficulty = config.difficulty.next()
+            }
+            (Key::Char(' '), MenuItem::Seed) | (Key::Char('\n'), MenuItem::Seed) => {
+                config.seed = Some(u64::from(rand::random::<u32>()))
+            }
+            (Key::Backspace, MenuItem::Seed) => {
+                config.seed = config.seed.map(|seed| seed / 10).filter(|&seed| seed > 0)
+            }
+            (Key::Char(digit), MenuItem::Seed) if digit.is_ascii_digit() => {
+                let digit = u64::from(digit.to_digit(10).unwrap());
+                let seed = config.seed.unwrap_or(0);
+                config.seed = seed
+                    .checked_mul(10)
+                    .and_then(|seed| seed.checked_add(digit))
+                    .or(config.seed);
+            }
+            _ => {}
+        }
+        None
+    }
+
+    /// The top of the start banner with `title` centered between the dashes.
+    pub fn header_lines(title: &str) -> Vec<String> {
+        let title = centered(Self::get_max_title_width(), title);
+        vec![
+            graphics::MENU_TOP.to_string(),
+            menu_line(&format!("──{}──", title)),
+            graphics::MENU_SEPARATOR.to_string(),
+        ]
+    }
+
+    /// Columns of the banner between the dashes around the title.
+    pub fn get_max_title_width() -> Unit {
+        graphics::MENU_INNER_WIDTH - 4
+    }
+
+    /// The menu rows, padded to the width of the start banner.
+    pub fn lines(&self, config: &GameConfig) -> Vec<String> {
+        Self::get_items()
+            .iter()
+            .enumerate()
+            .map(|(index, item)| {
+                let marker = if index == self.selected { ">" } else { " " };
+                let label = match *item {
+                    MenuItem::Start => "Start".to_string(),
+                    MenuItem::Difficulty => format!("Difficulty   {}", config.difficulty),
+                    MenuItem::Seed => match config.seed {
+                        Some(seed) => format!("Seed         {}", seed),
+                        None => "Seed         random".to_string(),
+                    },
+                    MenuItem::Scores => "Scoreboard".to_string(),
+                    MenuItem::Quit => "Quit".to_string(),
+                };
+                menu_line(&format!("    {} {}", marker, label))
+            })
+            .collect()
+    }
+
+    /// The controls section below the menu, listing the active key bindings.
+    pub fn controls_lines(bindings: &KeyBindings) -> Vec<String> {
+        let mut entries = vec!["j/k select".to_string()];
+        entries.extend(Self::binding_entries(bindings));
+        let mut lines = vec![menu_line(""), menu_line("  Controls")];
+        lines.extend(Self::column_lines(&entries));
+        lines
+    }
+
+    fn binding_entries(bindings: &KeyBindings) -> Vec<String> {
+        bindings
+            .help()
+            .iter()
+            .map(|&(ref keys, action)| format!("{} {}", keys, action))
+            .collect()
+    }
+
+    /// Lays out `entries` two to a line to keep the banner short. Entries too
+    /// wide for half the banner get a line of their own.
+    fn column_lines(entries: &[String]) -> Vec<String> {
+        let column_width = graphics::MENU_INNER_WIDTH / 2 - 1;
+        let mut lines = vec![];
+        let mut pending: Option<&String> = None;
+        for entry in entries {
+            if display_width(entry) > column_width {
+                lines.extend(pending.take().map(|left| menu_line(&format!(" {}", left))));
+                lines.push(menu_line(&format!(" {}", entry)));
+                continue;
+            }
+            match pending.take() {
+                Some(left) => lines.push(menu_line(&format!(
+                    " {}{} {}",
+                    left,
+                    " ".repeat(column_width - display_width(left)),
+                    entry
+                ))),
+                None => pending = Some(entry),
+            }
+        }
+        lines.extend(pending.map(|left| menu_line(&format!(" {}", left))));
+        lines
+    }
+}
+
+/// Pads `text` to the width of the start banner and adds its side borders.
+fn menu_line(text: &str) -> String {
+    format!(
+        "{}{:<width$}{}",
+        graphics::BORDER_VERTICAL,
+        text,
+        graphics::BORDER_VERTICAL,
+        width = graphics::MENU_INNER_WIDTH
+    )
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Action {
+    Left,
+    Right,
+    Launch,
+    /// Starts a new game with a fresh brick grid
+    Reset,
+    /// Puts the ball and peddles back at the start, keeping the bricks and score
+    SoftReset,
+    /// Replaces the bricks with a new layout, keeping everything else
+    Regenerate,
+    /// Writes the game in progress to the save file
+    Save,
+    /// Change the ball speed within the configured limits
+    SpeedUp,
+    SlowDown,
+    Quit,
+    /// Puts a fresh ball on the peddle, only bound in practice mode
+    Respawn,
+    /// Pushes the balls sideways towards the peddle, a few times per life
+    Nudge,
+}
+
+/// Keys driving the game, an action can be bound to several keys.
+struct KeyBindings {
+    bindings: Vec<(Action, Vec<Key>)>,
+}
+
+/// Both vim and WASD movement keys.
+impl Default for KeyBindings {
+    fn default() -> KeyBindings {
+        KeyBindings::with_movement(
+            vec![Key::Char('h'), Key::Char('a')],
+            vec![Key::Char('l'), Key::Char('d')],
+            vec![Key::Char(' ')],
+        )
+    }
+}
+
+/// Parses a layout preset name: vim, wasd or arrows.
+impl FromStr for KeyBindings {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<KeyBindings, String> {
+        match s {
+            "vim" => Ok(KeyBindings::vim()),
+            "wasd" => Ok(KeyBindings::wasd()),
+            "arrows" => Ok(KeyBindings::arrows()),
+            _ => Err(format!("unknown layout '{}'", s)),
+        }
+    }
+}
+
+impl KeyBindings {
+    /// Moves with h and l.
+    pub fn vim() -> KeyBindings {
+        KeyBindings::with_movement(
+            vec![Key::Char('h')],
+            vec![Key::Char('l')],
+            vec![Key::Char(' '), Key::Char('k')],
+        )
+    }
+
+    /// Moves with a and d.
+    pub fn wasd() -> KeyBindings {
+        KeyBindings::with_movement(
+            vec![Key::Char('a')],
+            vec![Key::Char('d')],
+            vec![Key::Char(' '), Key::Char('w')],
+        )
+    }
+
+    /// Moves with the arrow keys.
+    pub fn arrows() -> KeyBindings {
+        KeyBindings::with_movement(
+            vec![Key::Left],
+            vec![Key::Right],
+            vec![Key::Char(' '), Key::Up],
+        )
+    }
+
+    /// Reset, save and quit are the same in every layout, the replay prompt
+    /// relies on reset and quit.
+    fn with_movement(left: Vec<Key>, right: Vec<Key>, launch: Vec<Key>) -> KeyBindings {
+        KeyBindings {
+            bindings: vec![
+                (Action::Left, left),
+                (Action::Right, right),
+                (Action::Launch, launch),
+                (Action::Reset, vec![Key::Char('r')]),
+                (Action::SoftReset, vec![Key::Char('e')]),
+                (Action::Regenerate, vec![Key::Char('n')]),
+                (Action::Save, vec![Key::Ctrl('s')]),
+                (Action::Nudge, vec![Key::Char('x')]),
+                (Action::SpeedUp, vec![Key::Char('+')]),
+                (Action::SlowDown, vec![Key::Char('-')]),
+                (Action::Quit, vec![Key::Char('q')]),
+            ],
+        }
+    }
+
+    /// The action bound to `key`, if any.
+    pub fn action(&self, key: Key) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(_, keys)| keys.contains(&key))
+            .map(|&(action, _)| action)
+    }
+
+    /// Whether a key moving the peddle in `other` already does something here.
+    pub fn conflicts_with(&self, other: &KeyBindings) -> bool {
+        other
+            .bindings
+            .iter()
+            .filter(|&&(action, _)| action == Action::Left || action == Action::Right)
+            .any(|(_, keys)| keys.iter().any(|&key| self.action(key).is_some()))
+    }
+
+    /// Adds `key` to the keys triggering `action`.
+    pub fn bind(&mut self, action: Action, key: Key) {
+        match self
+            .bindings
+            .iter_mut()
+            .find(|&&mut (bound, _)| bound == action)
+        {
+            Some(&mut (_, ref mut keys)) => keys.push(key),
+            None => self.bindings.push((action, vec![key])),
+        }
+    }
+
+    /// Swaps the keys moving left and right. The help lists the swapped keys.
+    pub fn invert(&mut self) {
+        for &mut (ref mut action, _) in &mut self.bindings {
+            *action = match *action {
+                Action::Left => Action::Right,
+                Action::Right => Action::Left,
+                other => other,
+            };
+        }
+    }
+
+    /// First character key bound to `action`, used for the replay and quit prompts.
+    pub fn key_for(&self, action: Action) -> Option<char> {
+        self.bindings
+            .iter()
+            .find(|&&(bound, _)| bound == action)
+            .and_then(|(_, keys)| {
+                keys.iter()
+                    .filter_map(|&key| match key {
+                        Key::Char(c) => Some(c),
+                        _ => None,
+                    })
+                    .next()
+            })
+    }
+
+    /// Key names and descriptions for every bound action, in binding order.
+    /// Moving left and right share an entry, listing the left keys first.
+    pub fn help(&self) -> Vec<(String, &'static str)> {
+        let movement = match (self.key_names(Action::Left), self.key_names(Action::Right)) {
+            (Some(left), Some(right)) => Some(format!("{} {}", left, right)),
+            _ => None,
+        };
+        let mut help = vec![];
+        for &(action, _) in &self.bindings {
+            match (action, &movement) {
+                (Action::Left, Some(keys)) => help.push((keys.clone(), "move")),
+                (Action::Right, Some(_)) => {}
+                _ => help.extend(
+                    self.key_names(action)
+                        .map(|keys| (keys, Self::describe(action))),
+                ),
+            }
+        }
+        help
+    }
+
+    /// Names of the keys bound to `action` joined by slashes, `None` if unbound.
+    fn key_names(&self, action: Action) -> Option<String> {
+        self.bindings
+            .iter()
+            .find(|&&(bound, ref keys)| bound == action && !keys.is_empty())
+            .map(|(_, keys)| {
+                let names: Vec<String> = keys.iter().map(|&key| Self::key_name(key)).collect();
+                names.join("/")
+            })
+    }
+
+    fn key_name(key: Key) -> String {
+        match key {
+            Key::Char(' ') => "space".to_string(),
+            Key::Char(c) => c.to_string(),
+            Key::Left => "←".to_string(),
+            Key::Right => "→".to_string(),
+            Key::Up => "↑".to_string(),
+            Key::Down => "↓".to_string(),
+            Key::Ctrl(c) => format!("ctrl-{}", c),
+            _ => "?".to_string(),
+        }
+    }
+
+    fn describe(action: Action) -> &'static str {
+        match action {
+            Action::Left => "move left",
+            Action::Right => "move right",
+            Action::Launch => "launch",
+            Action::Reset => "reset",
+            Action::SoftReset => "restart round",
+            Action::Regenerate => "new layout",
+            Action::Save => "save",
+            Action::SpeedUp => "faster",
+            Action::SlowDown => "slower",
+            Action::Quit => "quit",
+            Action::Respawn => "new ball",
+            Action::Nudge => "nudge",
+        }
+    }
+}
+
+/// Measures play time, excluding the time spent paused.
+struct Stopwatch {
+    running_since: Option<Instant>,
+    accumulated: Duration,
+}
+
+impl Stopwatch {
+    pub fn new() -> Stopwatch {
+        Stopwatch {
+            running_since: None,
+            accumulated: Duration::from_secs(0),
+        }
+    }
+
+    pub fn start(&mut self, now: Instant) {
+        if self.running_since.is_none() {
+            self.running_since = Some(now);
+        }
+    }
+
+    /// Stops the clock, keeping the time measured so far. Also used for pausing.
+    pub fn stop(&mut self, now: Instant) {
+        if let Some(since) = self.running_since.take() {
+            self.accumulated += now.duration_since(since);
+        }
+    }
+
+    pub fn elapsed(&self, now: Instant) -> Duration {
+        match self.running_since {
+            Some(since) => self.accumulated + now.duration_since(since),
+            None => self.accumulated,
+        }
+    }
+}
+
+/// Formats a duration as mm:ss.
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{:02}:{:02}", secs / 60, secs % 60)
+}
+
+/// Formats seconds since the Unix epoch as a `yyyy-mm-dd` date in UTC.
+fn format_date(timestamp: u64) -> String {
+    // Civil from days, see http://howardhinnant.github.io/date_algorithms.html
+    let days = (timestamp / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Seconds since the Unix epoch, used to date high scores.
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct ScoreEntry {
+    score: u32,
+    /// When the score was set, in seconds since the Unix epoch
+    date: u64,
+}
+
+/// Counters describing how a single game went, shown once it ends.
+#[derive(Default)]
+struct GameStats {
+    bricks_destroyed: u32,
+    /// Destroyed bricks of each color, indexed by `NamedColor as usize`
+    bricks_by_color: [u32; 6],
+    peddle_hits: u32,
+    balls_lost: u32,
+    /// Ball bounces off walls, bricks, the boss and peddles
+    bounces: u32,
+    /// Bricks destroyed since a ball last touched a peddle or was lost
+    combo: u32,
+    longest_combo: u32,
+}
+
+impl GameStats {
+    pub fn bounces(&self) -> u32 {
+        self.bounces
+    }
+
+    pub fn peddle_hits(&self) -> u32 {
+        self.peddle_hits
+    }
+
+    pub fn longest_combo(&self) -> u32 {
+        self.longest_combo
+    }
+
+    pub fn bricks_of(&self, color: NamedColor) -> u32 {
+        self.bricks_by_color[color as usize]
+    }
+
+    fn record_brick(&mut self, color: Option<NamedColor>) {
+        self.bricks_destroyed += 1;
+        if let Some(color) = color {
+            self.bricks_by_color[color as usize] += 1;
+        }
+        self.combo += 1;
+        self.longest_combo = self.longest_combo.max(self.combo);
+    }
+
+    fn record_peddle_hit(&mut self) {
+        self.peddle_hits += 1;
+        self.combo = 0;
+    }
+
+    fn record_ball_lost(&mut self) {
+        self.balls_lost += 1;
+        self.combo = 0;
+    }
+
+    /// The color with the most destroyed bricks, the first one listed on ties.
+    fn top_color(&self) -> Option<NamedColor> {
+        let colors = [
+            NamedColor::Red,
+            NamedColor::Green,
+            NamedColor::Blue,
+            NamedColor::Magenta,
+            NamedColor::Cyan,
+            NamedColor::Yellow,
+        ];
+        colors
+            .iter()
+            .cloned()
+            .filter(|&color| self.bricks_of(color) > 0)
+            .fold(None, |best: Option<NamedColor>, color| match best {
+                Some(best) if self.bricks_of(best) >= self.bricks_of(color) => Some(best),
+                _ => Some(color),
+            })
+    }
+
+    /// Share of returned balls in percent, 100 when the ball never came back down.
+    pub fn accuracy(&self) -> u32 {
+        let attempts = self.peddle_hits() + self.balls_lost;
+        if attempts == 0 {
+            return 100;
+        }
+        self.peddle_hits() * 100 / attempts
+    }
+
+    /// Label and value rows of the summary panel.
+    pub fn summary(&self, score: u32, time: Duration) -> Vec<(&'static str, String)> {
+        let mut rows = vec![
+            ("score", score.to_string()),
+            ("bricks", self.bricks_destroyed.to_string()),
+            ("time", format_duration(time)),
+            ("accuracy", format!("{}%", self.accuracy())),
+            ("bounces", self.bounces().to_string()),
+            ("combo", self.longest_combo().to_string()),
+        ];
+        if let Some(color) = self.top_color() {
+            rows.push(("top color", color.name().to_string()));
+        }
+        rows
+    }
+}
+
+/// Personal records kept between sessions.
+struct Records {
+    best_time: Option<Duration>,
+    /// Highest scores first, at most `get_max_scores` entries
+    scores: Vec<ScoreEntry>,
+}
+
+impl Records {
+    pub fn new() -> Records {
+        Records {
+            best_time: None,
+            scores: vec![],
+        }
+    }
+
+    /// Loads the records file, missing or malformed entries are ignored.
+    pub fn load(path: &Path) -> Records {
+        let mut records = Records::new();
+        let mut contents = String::new();
+        if File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut contents))
+            .is_err()
+        {
+            return records;
+        }
+
+        for line in contents.lines() {
+            let mut parts = line.split_whitespace();
+            match (parts.next(), parts.next(), parts.next()) {
+                (Some("best_time"), Some(value), _) => {
+                    records.best_time = value.parse().ok().map(Duration::from_secs);
+                }
+                (Some("score"), Some(score), Some(date)) => {
+                    if let (Ok(score), Ok(date)) = (score.parse(), date.parse()) {
+                        records.submit_score(score, date);
+                    }
+                }
+                _ => {}
+            }
+        }
+        records
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut file = File::create(path)?;
+        if let Some(best_time) = self.best_time {
+            writeln!(file, "best_time {}", best_time.as_secs())?;
+        }
+        for entry in &self.scores {
+            writeln!(file, "score {} {}", entry.score, entry.date)?;
+        }
+        Ok(())
+    }
+
+    /// Records a final score, returns its rank starting at 0 if it made the
+    /// scoreboard.
+    pub fn submit_score(&mut self, score: u32, date: u64) -> Option<usize> {
+        if score == 0 {
+            return None;
+        }
+        // Ties keep the older score first
+        let rank = self
+            .scores
+            .iter()
+            .take_while(|entry| entry.score >= score)
+            .count();
+        if rank >= Self::get_max_scores() {
+            return None;
+        }
+        self.scores.insert(rank, ScoreEntry { score, date });
+        self.scores.truncate(Self::get_max_scores());
+        Some(rank)
+    }
+
+    pub fn get_max_scores() -> usize {
+        10
+    }
+
+    /// Records a completion time, returns true if it is a new best.
+    pub fn submit_time(&mut self, time: Duration) -> bool {
+        match self.best_time {
+            Some(best_time) if best_time <= time => false,
+            _ => {
+                self.best_time = Some(time);
+                true
+            }
+        }
+    }
+
+    pub fn get_default_path() -> PathBuf {
+        let home = env::var("HOME").unwrap_or_else(|_| ".".to_string());
+        Path::new(&home).join(".rbreakout_records")
+    }
+}
+
+/// The `--log` file, one line with the state at the start of every simulated
+/// frame. Writes are buffered and failing ones are dropped, the log must not
+/// get in the way of the game.
+struct FrameLog {
+    out: BufWriter<File>,
+    /// Frames logged so far, numbers the lines
+    frames: u64,
+}
+
+impl FrameLog {
+    pub fn new(file: File) -> FrameLog {
+        FrameLog {
+            out: BufWriter::new(file),
+            frames: 0,
+        }
+    }
+
+    pub fn record(&mut self, balls: &[Ball], peddles: &[Peddle], bricks: usize) {
+        let line = Self::line(self.frames, balls, peddles, bricks);
+        let _ = writeln!(self.out, "{}", line);
+        self.frames += 1;
+    }
+
+    /// Formats a line like
+    /// `frame 3 ball 40.50 20.25 vel 0.283 -0.283 peddle 44.0 bricks 52`,
+    /// with a `ball` and `vel` pair for every ball and a `peddle` for every peddle.
+    pub fn line(frame: u64, balls: &[Ball], peddles: &[Peddle], bricks: usize) -> String {
+        let mut line = format!("frame {}", frame);
+        for ball in balls {
+            line.push_str(&format!(
+                " ball {:.2} {:.2} vel {:.3} {:.3}",
+                ball.game_pos.0, ball.game_pos.1, ball.vel.0, ball.vel.1
+            ));
+        }
+        for peddle in peddles {
+            line.push_str(&format!(" peddle {:.1}", peddle.game_x));
+        }
+        line.push_str(&format!(" bricks {}", bricks));
+        line
+    }
+}
+
+/// A hand-made brick layout with one character per brick slot: `#` is a
+/// normal brick, `T` a tough one, `X` an indestructible one, `*` an explosive
+/// one, `o` one releasing an extra ball, `F` a frozen one and `.` or a space
+/// leaves the slot empty.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+struct Level {
+    rows: Vec<Vec<Option<BrickKind>>>,
+}
+
+impl Level {
+    pub fn parse(text: &str) -> Result<Level, String> {
+        let mut rows = vec![];
+        for (line_number, line) in text.lines().enumerate() {
+            let mut row = vec![];
+            for c in line.trim_end().chars() {
+                row.push(match c {
+                    '#' => Some(BrickKind::Normal),
+                    'T' => Some(BrickKind::Tough),
+                    'X' => Some(BrickKind::Solid),
+                    '*' => Some(BrickKind::Explosive),
+                    'o' => Some(BrickKind::MultiBall),
+                    'F' => Some(BrickKind::Frozen),
+                    '.' | ' ' => None,
+                    _ => {
+                        return Err(format!(
+                            "unexpected character '{}' on line {}",
+                            c,
+                            line_number + 1
+                        ))
+                    }
+                });
+            }
+            rows.push(row);
+        }
+        while rows.last().map_or(false, |row| row.is_empty()) {
+            rows.pop();
+        }
+
+        let breakable = rows
+            .iter()
+            .flat_map(|row| row.iter())
+            .any(|&slot| slot.map_or(false, |kind| kind != BrickKind::Solid));
+        if !breakable {
+            return Err("the level has no breakable bricks".to_string());
+        }
+        Ok(Level { rows })
+    }
+
+    pub fn width(&self) -> Unit {
+        self.rows.iter().map(|row| row.len()).max().unwrap_or(0)
+    }
+
+    pub fn height(&self) -> Unit {
+        self.rows.len()
+    }
+
+    /// The brick at grid position (`x`, `y`), if any.
+    pub fn get(&self, x: Unit, y: Unit) -> Option<BrickKind> {
+        self.rows
+            .get(y)
+            .and_then(|row| row.get(x))
+            .and_then(|&slot| slot)
+    }
+}
+
+/// Points standard input back at the controlling terminal, used after a level
+/// was piped in so the game can still be controlled from the keyboard.
+fn reattach_stdin_to_tty() -> io::Result<()> {
+    let tty = termion::get_tty()?;
+    if unsafe { libc::dup2(tty.as_raw_fd(), 0) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Set when the player asked to quit with Ctrl-C, checked by every input loop.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn handle_sigint(_: libc::c_int) {
+    // Only async-signal-safe work here, the game loop does the cleanup
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+/// Routes SIGINT through the normal quit path so the terminal gets restored.
+fn install_sigint_handler() {
+    let handler = handle_sigint as extern "C" fn(libc::c_int);
+    unsafe {
+        libc::signal(libc::SIGINT, handler as libc::sighandler_t);
+    }
+}
+
+/// Tunable game settings, mostly set from the command line.
+struct GameConfig {
+    /// Number of brick rows, derived from the game height when `None`
+    rows: Option<Unit>,
+    /// Number of brick columns, derived from the game width when `None`
+    cols: Option<Unit>,
+    /// Width of a single brick
+    brick_width: Unit,
+    /// Empty cells between neighbouring bricks, both horizontally and vertically
+    brick_gap: Unit,
+    /// Share of the game height the brick grid may take up. `None` lets a
+    /// derived grid take a third and an explicit one as much as fits
+    grid_share: Option<f32>,
+    /// Show the frame time overlay
+    debug: bool,
+    /// Accelerate the ball downwards over time
+    gravity: bool,
+    difficulty: Difficulty,
+    /// Rows between the peddle and the starting ball, set by the difficulty when `None`
+    start_gap: Option<Unit>,
+    /// Seed for the brick layout, a new random layout every game when `None`
+    seed: Option<u64>,
+    /// Number of positions drawn behind the ball, 0 disables the trail
+    trail: usize,
+    /// Draw without any color escapes, for monochrome terminals
+    no_color: bool,
+    /// Show the lives as a row of balls instead of a number
+    reserve_balls: bool,
+    /// Pause while the terminal is out of focus
+    autopause: bool,
+    /// Start playing right away, without the start screen and countdown
+    skip_intro: bool,
+    /// Heading of the start banner
+    title: String,
+    /// Fixed brick layout used instead of a randomly generated one
+    level: Option<Level>,
+    /// How long a caught safety net lasts, 0 disables the power-up
+    safety_net_secs: u32,
+    /// How long after losing a life lost balls are launched again, 0 disables it
+    mercy_secs: u32,
+    /// Combo turning the balls into fireballs for a while, 0 disables them
+    fireball_combo: u32,
+    /// Nudges per life, 0 disables nudging
+    nudges: u32,
+    key_bindings: KeyBindings,
+    /// Keys of a second player with their own peddle, single player when `None`
+    coop_bindings: Option<KeyBindings>,
+    /// Side length of the ball in cells
+    ball_size: Unit,
+    color_weights: ColorWeights,
+    brick_colors: BrickColors,
+    brick_points: ScoreTable,
+    ball_color: NamedColor,
+    peddle_color: NamedColor,
+    /// Columns the peddle spans
+    peddle_width: Unit,
+    /// Most columns a peddle moves per frame, however many keys are queued
+    max_peddle_step: Unit,
+    /// Hold the launch key to launch a held ball faster
+    charge_launch: bool,
+    /// Let balls bounce off each other instead of passing through
+    ball_collisions: bool,
+    theme: Theme,
+    damage_colors: DamageColors,
+    /// Mix tough, solid, explosive and multiball bricks into random layouts
+    special_bricks: bool,
+    /// Mix frozen bricks into random layouts
+    frozen_bricks: bool,
+    collision_padding: CollisionPadding,
+    speed_limits: SpeedLimits,
+    walls: Walls,
+    field: FieldShape,
+    /// Edge the peddles guard, the rest of the field is mirrored to match
+    peddle_side: PeddleSide,
+    /// Give the peddle a last chance at a ball that slipped below it
+    grace: bool,
+    /// Losing the ball puts a new one on the peddle instead of ending the game
+    practice: bool,
+    /// Points between extra lives, 0 disables them
+    extra_life_every: u32,
+    /// Seconds to clear the field in, 0 plays without a time limit
+    time_attack_secs: u32,
+    /// Seconds between the bricks moving down a row, 0 keeps them in place
+    descend_secs: u32,
+    /// Debris flying off destroyed bricks
+    effects: bool,
+    /// Show the path the ball takes off the walls
+    predict: bool,
+    /// Mark the span each peddle catches the ball in behind it
+    guides: bool,
+    /// Show the share of destroyed bricks as a bar in the top border
+    progress: bool,
+    /// Slow the ball down once few bricks are left
+    easy_finish: bool,
+    /// Number of breakable bricks left at which the ball slows down
+    easy_finish_bricks: usize,
+    /// Place a boss in the brick grid, destroying it clears the level
+    boss: bool,
+    /// Curve the ball towards the nearest brick
+    assist: bool,
+    /// How strongly the aim assist curves the ball per frame
+    assist_strength: f32,
+    /// Share of the ball's spin removed per second, 0 disables the decay
+    spin_decay: f32,
+    /// Game to resume instead of starting a fresh one
+    saved_game: Option<GameSnapshot>,
+    /// Where the save key writes the game to
+    save_path: PathBuf,
+    /// Where the personal records are kept, without one they last only as
+    /// long as the game
+    records_path: Option<PathBuf>,
+    /// File to log every frame to, opened for appending
+    log_file: Option<File>,
+    /// Where the result of every finished game is written to
+    result_path: Option<PathBuf>,
+}
+
+impl Default for GameConfig {
+    fn default() -> GameConfig {
+        GameConfig {
+            rows: None,
+            cols: None,
+            brick_width: Cell::get_default_width(),
+            brick_gap: 0,
+            grid_share: None,
+            debug: false,
+            gravity: false,
+            difficulty: Difficulty::Normal,
+            start_gap: None,
+            seed: None,
+            trail: 0,
+            no_color: false,
+            reserve_balls: false,
+            autopause: false,
+            skip_intro: false,
+            title: graphics::MENU_TITLE.to_string(),
+            level: None,
+            safety_net_secs: 15,
+            mercy_secs: 0,
+            fireball_combo: 0,
+            nudges: 0,
+            key_bindings: KeyBindings::default(),
+            coop_bindings: None,
+            ball_size: 1,
+            color_weights: ColorWeights::default(),
+            brick_colors: BrickColors::Random,
+            brick_points: ScoreTable::default(),
+            ball_color: NamedColor::Red,
+            peddle_color: NamedColor::Red,
+            peddle_width: display_width(graphics::PEDDLE_GRAPHIC),
+            max_peddle_step: 2 * Peddle::get_speed(),
+            charge_launch: false,
+            ball_collisions: false,
+            theme: Theme::Classic,
+            damage_colors: DamageColors::default(),
+            special_bricks: false,
+            frozen_bricks: false,
+            collision_padding: CollisionPadding::default(),
+            speed_limits: SpeedLimits::default(),
+            walls: Walls::default(),
+            field: FieldShape::Rectangle,
+            peddle_side: PeddleSide::Bottom,
+            grace: false,
+            practice: false,
+            extra_life_every: 500,
+            time_attack_secs: 0,
+            descend_secs: 0,
+            effects: false,
+            predict: false,
+            guides: false,
+            progress: false,
+            easy_finish: false,
+            easy_finish_bricks: 1,
+            boss: false,
+            assist: false,
+            assist_strength: 0.05,
+            spin_decay: 0.0,
+            saved_game: None,
+            save_path: SavedGame::get_default_path(),
+            records_path: None,
+            log_file: None,
+            result_path: None,
+        }
+    }
+}
+
+impl GameConfig {
+    /// Resolves the brick grid size (columns, rows) for the given game size and
+    /// checks that it fits between the borders.
+    pub fn grid_dimensions(&self, game_size: (Unit, Unit)) -> Result<(Unit, Unit), GameError> {
+        let invalid = |message: String| Self::invalid("brick grid", message);
+        let cell_width = self.brick_width;
+        let gap = self.brick_gap;
+        if cell_width == 0 {
+            return Err(invalid(
+                "bricks must be at least one column wide".to_string(),
+            ));
+        }
+
+        // Bricks keep one cell width of margin to the side borders and never
+        // extend below a third of the game height by default.
+        let max_span_x = game_size.0.saturating_sub(2 * cell_width);
+        let max_span_y = match self.grid_share {
+            Some(share) if share <= 0f32 || share > 1f32 => {
+                return Err(invalid(format!(
+                    "a grid share of {} is not between 0 and 1",
+                    share
+                )));
+            }
+            Some(share) => (game_size.1 as f32 * share) as Unit,
+            None => game_size.1 / 3,
+        };
+
+        let (cols, rows) = match self.level {
+            Some(ref level) => (Some(level.width()), Some(level.height())),
+            None => (self.cols, self.rows),
+        };
+        let cols = cols.unwrap_or((max_span_x + gap) / (cell_width + gap));
+        let rows = rows.unwrap_or((max_span_y + gap) / (1 + gap));
+
+        if cols == 0 || rows == 0 {
+            return Err(invalid(format!(
+                "a game size of {}x{} leaves no room for bricks ({} columns, {} rows)",
+                game_size.0, game_size.1, cols, rows
+            )));
+        }
+
+        let span_x = (cols * (cell_width + gap)).saturating_sub(gap);
+        let span_y = (rows * (1 + gap)).saturating_sub(gap);
+        if span_x > max_span_x {
+            return Err(invalid(format!(
+                "{} columns of bricks with a gap of {} do not fit in a width of {}",
+                cols, gap, game_size.0
+            )));
+        }
+        if Self::get_grid_top() + span_y > Self::get_grid_bottom(game_size.1) {
+            return Err(invalid(format!(
+                "{} rows of bricks with a gap of {} do not fit in a height of {}",
+                rows, gap, game_size.1
+            )));
+        }
+        if self.grid_share.is_some() && span_y > max_span_y {
+            return Err(invalid(format!(
+                "{} rows of bricks with a gap of {} take more than {} of the {} rows of the game",
+                rows, gap, max_span_y, game_size.1
+            )));
+        }
+
+        Ok((cols, rows))
+    }
+
+    /// Checks that the title fits in the start banner.
+    pub fn check_title(&self) -> Result<(), GameError> {
+        let width = display_width(&self.title);
+        if width > Menu::get_max_title_width() {
+            return Err(Self::invalid(
+                "title",
+                format!(
+                    "the title is {} columns wide, the start screen fits {}",
+                    width,
+                    Menu::get_max_title_width()
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Checks that the peddle has a left and a right half and fits between the
+    /// side borders of a game `game_width` wide.
+    pub fn check_peddle_width(&self, game_width: Unit) -> Result<(), GameError> {
+        if self.peddle_width < Self::get_min_peddle_width() {
+            return Err(Self::invalid(
+                "peddle",
+                format!(
+                    "a peddle needs at least {} columns, {} is too narrow",
+                    Self::get_min_peddle_width(),
+                    self.peddle_width
+                ),
+            ));
+        }
+        if self.peddle_width > coords::inner_width(game_width) {
+            return Err(Self::invalid(
+                "peddle",
+                format!(
+                    "a peddle of {} columns does not fit in a width of {}",
+                    self.peddle_width, game_width
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Hash of the options a headless simulation depends on, besides the
+    /// seed, so a recording can tell it is replayed with different ones.
+    pub fn fingerprint(&self) -> u64 {
+        let options: [&dyn fmt::Debug; 41] = [
+            &self.rows,
+            &self.cols,
+            &self.brick_width,
+            &self.brick_gap,
+            &self.grid_share,
+            &self.gravity,
+            &self.difficulty,
+            &self.start_gap,
+            &self.level,
+            &self.safety_net_secs,
+            &self.mercy_secs,
+            &self.fireball_combo,
+            &self.nudges,
+            &self.coop_bindings.is_some(),
+            &self.ball_size,
+            &self.color_weights,
+            &self.brick_colors,
+            &self.brick_points,
+            &self.peddle_width,
+            &self.max_peddle_step,
+            &self.charge_launch,
+            &self.ball_collisions,
+            &self.special_bricks,
+            &self.frozen_bricks,
+            &self.collision_padding,
+            &self.speed_limits,
+            &self.walls,
+            &self.field,
+            &self.peddle_side,
+            &self.grace,
+            &self.practice,
+            &self.extra_life_every,
+            &self.time_attack_secs,
+            &self.descend_secs,
+            &self.effects,
+            &self.easy_finish,
+            &self.easy_finish_bricks,
+            &self.boss,
+            &self.assist,
+            &self.assist_strength,
+            &self.spin_decay,
+        ];
+        // FNV-1a, stable across builds unlike the std hasher
+        format!("{:?}", options)
+            .bytes()
+            .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+            })
+    }
+
+    /// An `InvalidConfig` error explaining what is wrong with `what`.
+    fn invalid(what: &str, message: String) -> GameError {
+        GameError::InvalidConfig(format!("Invalid {}: {}", what, message))
+    }
+
+    fn get_min_peddle_width() -> Unit {
+        2
+    }
+
+    /// Checks that a game of `size` is large enough for the bricks and peddle.
+    pub fn check_size(&self, size: (Unit, Unit)) -> Result<(), GameError> {
+        let min_size = self.min_size();
+        match too_small_message(size, min_size) {
+            Some(_) => Err(GameError::TooSmall { size, min_size }),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks that the options make a playable game of `size`, which is
+    /// already fitted to the brick width.
+    pub fn check(&self, size: (Unit, Unit)) -> Result<(), GameError> {
+        self.grid_dimensions(size)?;
+        self.check_peddle_width(size.0)?;
+        self.check_title()?;
+        let padding = self.collision_padding;
+        if padding.side < 0f32 || padding.face < 0f32 || padding.face > 1f32 {
+            return Err(GameError::InvalidConfig(
+                "The side padding must not be negative and the face padding between 0 and 1"
+                    .to_string(),
+            ));
+        }
+        let playfield = (
+            coords::inner_width(size.0),
+            coords::bottom(size.1) + 1 - coords::top(),
+        );
+        if self.ball_size == 0 || self.ball_size > playfield.0.min(playfield.1) {
+            return Err(GameError::InvalidConfig(format!(
+                "The ball size must be at least 1 and fit the {}x{} playfield",
+                playfield.0, playfield.1
+            )));
+        }
+        if !(0f32..=1f32).contains(&self.spin_decay) {
+            return Err(GameError::InvalidConfig(
+                "The spin decay must be between 0 and 1".to_string(),
+            ));
+        }
+        let limits = self.speed_limits;
+        if limits.min <= 0f32 || limits.min > limits.max {
+            return Err(GameError::InvalidConfig(
+                "The minimum speed must be above 0 and not above the maximum speed".to_string(),
+            ));
+        }
+        self.check_peddle_side()?;
+        Ok(())
+    }
+
+    /// A top peddle only mirrors the basic field, the funnel, the boss and
+    /// descending bricks still expect the peddle at the bottom.
+    fn check_peddle_side(&self) -> Result<(), GameError> {
+        let invalid = |message: &str| Self::invalid("peddle side", message.to_string());
+        if self.peddle_side == PeddleSide::Bottom {
+            return Ok(());
+        }
+        if self.field != FieldShape::Rectangle {
+            return Err(invalid("the top does not work with the funnel field"));
+        }
+        if self.boss {
+            return Err(invalid("the top does not work with --boss"));
+        }
+        if self.descend_secs > 0 {
+            return Err(invalid("the top does not work with --descend"));
+        }
+        Ok(())
+    }
+
+    /// Smallest game size fitting the configured brick grid, or two columns
+    /// and a row of bricks when the grid follows the game size.
+    pub fn min_size(&self) -> (Unit, Unit) {
+        let cell_width = self.brick_width.max(1);
+        let gap = self.brick_gap;
+        let (cols, rows) = match self.level {
+            Some(ref level) => (level.width(), level.height()),
+            None => (self.cols.unwrap_or(2), self.rows.unwrap_or(1)),
+        };
+        let span_x = (cols * (cell_width + gap)).saturating_sub(gap);
+        let span_y = (rows * (1 + gap)).saturating_sub(gap);
+        // Widths are rounded down to whole bricks, so round the minimum up
+        let width = (span_x + 2 * cell_width).max(Self::get_min_width());
+        let width = (width + cell_width - 1) / cell_width * cell_width;
+        let height = Self::get_grid_top() + span_y + Self::get_rows_below_grid();
+        let height = height.max(Self::get_min_height());
+        let height = match self.grid_share {
+            Some(share) if share > 0f32 => height.max((span_y as f32 / share).ceil() as Unit),
+            _ => height,
+        };
+        (width, height)
+    }
+
+    /// Smallest supported game width, leaves room for at least two brick columns.
+    pub fn get_min_width() -> Unit {
+        4 * Cell::get_default_width()
+    }
+
+    /// Smallest supported game height, leaves room for several brick rows.
+    pub fn get_min_height() -> Unit {
+        20
+    }
+
+    fn get_grid_top() -> Unit {
+        4
+    }
+
+    /// Lowest row bricks may occupy, leaving room for the ball above the peddle.
+    fn get_grid_bottom(height: Unit) -> Unit {
+        height.saturating_sub(Self::get_rows_below_grid())
+    }
+
+    /// Rows kept free of bricks at the bottom of the game.
+    fn get_rows_below_grid() -> Unit {
+        6
+    }
+}
+
+/// The state of a game in plain values, independent of the terminal it is
+/// drawn to. Taken with `BreakoutGame::snapshot` and loaded with `restore`.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+struct GameSnapshot {
+    /// Seed of the level, drives the brick layout and power-up drops
+    seed: u64,
+    /// How far the power-up drops got in the sequence of the seed
+    rng_draws: u64,
+    /// The layout read with `--level-stdin`, which new rounds start from
+    level: Option<Level>,
+    score: u32,
+    lives: u32,
+    balls: Vec<BallSnapshot>,
+    /// Player one's peddle first
+    peddles: Vec<PeddleSnapshot>,
+    bricks: Vec<BrickSnapshot>,
+    boss: Option<BossSnapshot>,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+struct BallSnapshot {
+    pos: (f32, f32),
+    vel: (f32, f32),
+    /// Offset from the holding peddle while the ball is held by it
+    stuck_offset: Option<f32>,
+    holder: usize,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+struct PeddleSnapshot {
+    x: f32,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+struct BrickSnapshot {
+    pos: (Unit, Unit),
+    width: Unit,
+    kind: BrickKind,
+    hits_left: u8,
+    /// `None` for bricks colored by their kind
+    color: Option<NamedColor>,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+struct BossSnapshot {
+    pos: (Unit, Unit),
+    width: Unit,
+    height: Unit,
+    hp: u32,
+    max_hp: u32,
+}
+
+/// A game written to disk with the save key, resumed with `--load`.
+#[derive(Serialize, Deserialize)]
+struct SavedGame {
+    /// Format of the file, saves from other versions are not loaded
+    version: u32,
+    /// Size of the game the positions in `game` are relative to
+    size: (Unit, Unit),
+    game: GameSnapshot,
+}
+
+impl SavedGame {
+    pub fn get_version() -> u32 {
+        2
+    }
+
+    pub fn get_default_path() -> PathBuf {
+        let home = env::var("HOME").unwrap_or_else(|_| ".".to_string());
+        Path::new(&home).join(".rbreakout_save")
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let text = serde_json::to_string(self)?;
+        std::fs::write(path, text)
+    }
+
+    /// Reads a saved game, explaining what is wrong with files that cannot be
+    /// resumed as `InvalidData` errors.
+    pub fn load(path: &Path) -> io::Result<SavedGame> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+        let text = std::fs::read_to_string(path)?;
+        let value: serde_json::Value =
+            serde_json::from_str(&text).map_err(|_| invalid("not a saved game".to_string()))?;
+        let version = value.get("version").and_then(serde_json::Value::as_u64);
+        if version != Some(u64::from(Self::get_version())) {
+            return Err(invalid(match version {
+                Some(version) => format!(
+                    "saved in format version {}, this version reads {}",
+                    version,
+                    Self::get_version()
+                ),
+                None => "not a saved game".to_string(),
+            }));
+        }
+        serde_json::from_value(value).map_err(|e| invalid(format!("damaged save: {}", e)))
+    }
+}
+
+/// Summary of a finished game written with `--export-result`, for scripts and
+/// leaderboards.
+#[derive(PartialEq, Debug, Serialize, Deserialize)]
+struct GameResult {
+    seed: u64,
+    /// The layout read with `--level-stdin`, none when the bricks were
+    /// generated from the seed
+    level: Option<Level>,
+    won: bool,
+    score: u32,
+    time_millis: u64,
+    lives: u32,
+    /// Breakable bricks left on the field
+    bricks_remaining: usize,
+    /// The bricks as the game ended
+    bricks: Vec<BrickSnapshot>,
+}
+
+impl GameResult {
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let text = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, text)
+    }
+}
+
+/// Outcome of a headless demo session written with `--bench` and `--record`.
+/// The simulation only depends on the seed, the options and the frame count,
+/// so `--verify` replaying it with the same options has to end the same way
+/// unless the physics changed.
+#[derive(PartialEq, Debug, Serialize, Deserialize)]
+struct Recording {
+    seed: u64,
+    width: Unit,
+    height: Unit,
+    /// Frames simulated, fewer than asked for if the game ended early
+    frames: u64,
+    score: u32,
+    /// Breakable bricks left on the field
+    bricks_remaining: usize,
+    /// `GameConfig::fingerprint` of the options it was recorded with
+    options: u64,
+}
+
+impl Recording {
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let text = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, text)
+    }
+
+    pub fn load(path: &Path) -> io::Result<Recording> {
+        let text = std::fs::read_to_string(path)?;
+        serde_json::from_str(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Replays the session with `config`, which has to hold the options it
+    /// was recorded with, and fails with what diverged from the recording.
+    pub fn verify(&self, mut config: GameConfig) -> Result<(), GameError> {
+        if config.fingerprint() != self.options {
+            return Err(GameConfig::invalid(
+                "replay",
+                "the options differ from the ones the recording was made with".to_string(),
+            ));
+        }
+        config.seed = Some(self.seed);
+        let mut game = BreakoutGame::new(io::empty(), NullScreen, self.width, self.height, config);
+        let (frames, _) = game.simulate(self.frames);
+        let replayed = game.recording(frames);
+        let mut differences = vec![];
+        if replayed.frames != self.frames {
+            differences.push(format!(
+                "ended after {} frames instead of {}",
+                replayed.frames, self.frames
+            ));
+        }
+        if replayed.score != self.score {
+            differences.push(format!(
+                "scored {} instead of {}",
+                replayed.score, self.score
+            ));
+        }
+        if replayed.bricks_remaining != self.bricks_remaining {
+            differences.push(format!(
+                "left {} bricks instead of {}",
+                replayed.bricks_remaining, self.bricks_remaining
+            ));
+        }
+        if differences.is_empty() {
+            Ok(())
+        } else {
+            Err(GameError::ReplayDiverged(differences.join(", ")))
+        }
+    }
+}
+
+/// The random source for events during play, kept apart from the layout
+/// generator. It counts its draws so saved games continue the same sequence.
+struct GameRng {
+    rng: StdRng,
+    /// Words drawn since seeding, every draw takes one
+    draws: u64,
+}
+
+impl GameRng {
+    /// Seeded with `seed` and advanced past `draws` words.
+    pub fn new(seed: u64, draws: u64) -> GameRng {
+        let mut rng = GameRng {
+            rng: StdRng::from_seed(&[seed as usize, 1][..]),
+            draws: 0,
+        };
+        for _ in 0..draws {
+            rng.next_u64();
+        }
+        rng
+    }
+}
+
+impl Rng for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        self.next_u64() as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.draws += 1;
+        self.rng.next_u64()
+    }
+}
+
+struct BreakoutGame<R, W> {
+    stdin: R,
+    stdout: W,
+    /// Set once the player asked to quit with Ctrl-C, `INTERRUPTED` unless a
+    /// test hands in its own flag
+    interrupt: &'static AtomicBool,
+
+    /// Balls in play, the round is lost once all of them are gone
+    balls: Vec<Ball>,
+    /// Player one's peddle first, followed by player two's in co-op
+    peddles: Vec<Peddle>,
+    cells: Vec<Cell>,
+    /// Looks up the bricks near a ball, rebuilt with `index_bricks` whenever
+    /// bricks move, appear or are removed
+    brick_index: BrickIndex,
+    boss: Option<Boss>,
+    power_ups: Vec<PowerUp>,
+    particles: Vec<Particle>,
+    projectiles: Vec<Projectile>,
+    /// Messages waiting to be shown, the front one is on screen
+    toasts: VecDeque<Toast>,
+
+    // Remaining catches and frames of the sticky peddle power-up
+    sticky_catches: u32,
+    sticky_ticks: u32,
+    /// Launch being charged with `--charge-launch`
+    launch_charge: Option<LaunchCharge>,
+    /// Frames the safety net below the peddle stays up, 0 when there is none
+    safety_net_ticks: u32,
+    /// Frames left in which a lost ball is launched again instead of lost
+    mercy_ticks: u32,
+    /// Set when a life was lost, the mercy starts once the new ball is launched
+    mercy_pending: bool,
+    /// Nudges left for the current life
+    nudges_left: u32,
+    /// Frames the laser stays on, and frames until it can fire again
+    laser_ticks: u32,
+    laser_cooldown: u32,
+    /// Frames left of the screen shake, 0 when the playfield is at rest
+    shake_ticks: u32,
+    /// Frames until the bricks move down a row with `--descend`
+    descend_ticks: u32,
+    /// Ball speed picked with the speed keys, kept when a new game starts
+    speed_multiplier: f32,
+
+    /// Seed of the current game, drives the brick layout and power-up drops
+    seed: u64,
+    rng: GameRng,
+
+    height: Unit,
+    width: Unit,
+    config: GameConfig,
+    state: GameState,
+
+    // Debug controls for inspecting the physics
+    time_scale: f32,
+    paused: bool,
+    /// Set while paused because the terminal lost focus
+    autopaused: bool,
+    single_step: bool,
+    /// Set while the terminal is smaller than the game, which is then not drawn
+    too_small: bool,
+    /// Frames presented so far, drives border animations
+    frame: u64,
+    frame_timer: FrameTimer,
+    frame_label: String,
+
+    stopwatch: Stopwatch,
+    /// Play time left in time attack mode, counted down every tick
+    time_left: Option<Duration>,
+    time_label: String,
+    /// Ball count last drawn next to the lives, repainted when it changes
+    balls_label: String,
+    /// Breakable bricks the game started with, the base of the progress bar
+    initial_breakable: usize,
+    /// Progress bar last drawn, repainted when it changes
+    progress_label: String,
+    /// Charge meter last drawn, the border is repainted once it goes away
+    charge_label: String,
+    /// Nudges left last drawn left of the ball count, repainted when it changes
+    nudges_label: String,
+    records: Records,
+    score: u32,
+    /// Balls left including the one in play, the game ends when the last is lost
+    lives: u32,
+    /// Score at which the last extra life was granted
+    last_life_threshold: u32,
+    stats: GameStats,
+    frame_log: Option<FrameLog>,
+}
+
+impl<R: KeySource, W: Screen> BreakoutGame<R, W> {
+    pub fn new(
+        stdin: R,
+        stdout: W,
+        width: Unit,
+        height: Unit,
+        mut config: GameConfig,
+    ) -> BreakoutGame<R, W> {
+        let frame_log = config.log_file.take().map(FrameLog::new);
+        let seed = Self::choose_seed(&config);
+        let (ball, peddles, mut cells) = Self::get_start_values(width, height, &config, seed);
+        let boss = Self::place_boss(&mut cells, width, &config);
+        let time_left = Self::time_limit(&config);
+        let speed_multiplier = config.speed_limits.clamp(1.0);
+        let initial_breakable = cells.iter().filter(|cell| cell.is_breakable()).count();
+        let nudges_left = config.nudges;
+        let records = match config.records_path {
+            Some(ref path) => Records::load(path),
+            None => Records::new(),
+        };
+        BreakoutGame {
+            width,
+            height,
+            config,
+            state: GameState::Playing,
+            stdin,
+            stdout,
+            interrupt: &INTERRUPTED,
+            balls: vec![ball],
+            peddles,
+            brick_index: BrickIndex::new(&cells, (width, height)),
+            cells,
+            boss,
+            power_ups: vec![],
+            particles: vec![],
+            projectiles: vec![],
+            toasts: VecDeque::new(),
+            sticky_catches: 0,
+            sticky_ticks: 0,
+            launch_charge: None,
+            safety_net_ticks: 0,
+            mercy_ticks: 0,
+            mercy_pending: false,
+            nudges_left,
+            laser_ticks: 0,
+            laser_cooldown: 0,
+            shake_ticks: 0,
+            descend_ticks: 0,
+            speed_multiplier,
+            seed,
+            rng: GameRng::new(seed, 0),
+            time_scale: 1.0,
+            paused: false,
+            autopaused: false,
+            single_step: false,
+            too_small: false,
+            frame: 0,
+            frame_timer: FrameTimer::new(),
+            frame_label: String::new(),
+            stopwatch: Stopwatch::new(),
+            time_left,
+            time_label: String::new(),
+            balls_label: String::new(),
+            initial_breakable,
+            progress_label: String::new(),
+            charge_label: String::new(),
+            nudges_label: String::new(),
+            records,
+            score: 0,
+            lives: Self::get_start_lives(),
+            last_life_threshold: 0,
+            stats: GameStats::default(),
+            frame_log,
+        }
+    }
+
+    /// The configured seed, or a fresh random one.
+    fn choose_seed(config: &GameConfig) -> u64 {
+        config
+            .seed
+            .unwrap_or_else(|| u64::from(rand::random::<u32>()))
+    }
+
+    /// Time to clear the field in, `None` without time attack.
+    fn time_limit(config: &GameConfig) -> Option<Duration> {
+        match config.time_attack_secs {
+            0 => None,
+            secs => Some(Duration::from_secs(u64::from(secs))),
+        }
+    }
+
+    pub fn get_start_values(
+        width: Unit,
+        height: Unit,
+        config: &GameConfig,
+        seed: u64,
+    ) -> (Ball, Vec<Peddle>, Vec<Cell>) {
+        let peddles = Self::start_peddles(width, height, config);
+        let cells = Self::generate_cell_grid((width, height), config, seed);
+        let ball_pos = Self::start_pos(&peddles[0], &cells, config);
+        (Self::new_ball(ball_pos, config), peddles, cells)
+    }
+
+    /// One peddle per player, each in the middle of their share of the width.
+    fn start_peddles(width: Unit, height: Unit, config: &GameConfig) -> Vec<Peddle> {
+        let players = if config.coop_bindings.is_some() { 2 } else { 1 };
+        (0..players)
+            .map(|player| {
+                let mut peddle = Peddle {
+                    game_x: 0f32,
+                    target_x: 0f32,
+                    y: config.peddle_side.peddle_row(height),
+                    graphic: graphics::PEDDLE_SEGMENT.repeat(config.peddle_width),
+                    color: config.peddle_color,
+                    bounce_ticks: 0,
+                    flash_ticks: 0,
+                };
+                // Every player starts in the middle of their share of the width
+                let center = width * (2 * player + 1) / (2 * players);
+                peddle.jump_to((center - peddle.get_width() / 2) as f32, width);
+                peddle
+            })
+            .collect()
+    }
+
+    /// Where the ball starts: centered in front of `peddle`, the configured gap
+    /// away from it but always clear of the nearest brick.
+    fn start_pos(peddle: &Peddle, cells: &[Cell], config: &GameConfig) -> (f32, f32) {
+        let gap = config
+            .start_gap
+            .unwrap_or_else(|| config.difficulty.get_start_gap());
+        let y = match config.peddle_side {
+            PeddleSide::Bottom => {
+                let below_bricks = cells.iter().map(|cell| cell.y() + 2).max().unwrap_or(0);
+                let highest = peddle.y().saturating_sub(config.ball_size);
+                peddle
+                    .y()
+                    .saturating_sub(gap)
+                    .max(below_bricks)
+                    .min(highest)
+            }
+            PeddleSide::Top => {
+                let above_bricks = cells.iter().map(|cell| cell.y()).min();
+                let above_bricks =
+                    above_bricks.map_or(Unit::MAX, |y| y.saturating_sub(1 + config.ball_size));
+                let lowest = peddle.y() + 1;
+                (peddle.y() + gap + 1)
+                    .saturating_sub(config.ball_size)
+                    .min(above_bricks)
+                    .max(lowest)
+            }
+        };
+        let x = (peddle.x() + peddle.get_width() / 2).saturating_sub(config.ball_size / 2);
+        (x as f32, y as f32)
+    }
+
+    /// Puts a boss at the top center of the grid when enabled, removing the
+    /// bricks underneath it and its health bar.
+    fn place_boss(cells: &mut Vec<Cell>, width: Unit, config: &GameConfig) -> Option<Boss> {
+        if !config.boss {
+            return None;
+        }
+        let boss_width =
+            (config.brick_width * Boss::get_brick_span()).min(coords::inner_width(width));
+        let pos = (
+            (width + 1).saturating_sub(boss_width) / 2,
+            GameConfig::get_grid_top() + 2,
+        );
+        let boss = Boss::new(pos, boss_width, Boss::get_default_height());
+        cells
+            .retain(|cell| (0..cell.get_width()).all(|dx| !boss.covers((cell.x() + dx, cell.y()))));
+        Some(boss)
+    }
+
+    /// A ball at `ball_pos`, falling towards the peddle.
+    fn new_ball(ball_pos: (f32, f32), config: &GameConfig) -> Ball {
+        let speed_factor = config.difficulty.get_speed_factor();
+        let towards = config.peddle_side.towards_peddle();
+
+        Ball {
+            game_pos: ball_pos,
+            vel: (0.3 * speed_factor, 0.3 * speed_factor * towards),
+            speed: Ball::get_speed() * speed_factor,
+            gravity: if config.gravity {
+                Self::get_gravity()
+            } else {
+                0f32
+            },
+            stuck_offset: None,
+            holder: 0,
+            trail: VecDeque::with_capacity(config.trail),
+            trail_length: config.trail,
+            shallow_ticks: 0,
+            assist: if config.assist {
+                config.assist_strength
+            } else {
+                0f32
+            },
+            spin_decay: config.spin_decay * FRAME_MILLIS as f32 / 1000f32,
+            shot_share: std::f32::consts::FRAC_1_SQRT_2,
+            size: config.ball_size,
+            padding: config.collision_padding,
+            color: config.ball_color,
+            walls: config.peddle_side.orient(config.walls),
+            field: config.field,
+            side: config.peddle_side,
+            grace: config.grace,
+            bounces: 0,
+            fire_ticks: 0,
+        }
+    }
+
+    /// Replaces all balls with a single one held by player one's peddle, ready
+    /// to launch.
+    fn respawn_ball(&mut self) {
+        let peddle = &self.peddles[0];
+        let x = (peddle.x() + peddle.get_width() / 2) as f32;
+        let mut ball = Self::new_ball((x, peddle.y() as f32), &self.config);
+        ball.stick_to(&self.peddles, 0);
+        self.balls = vec![ball];
+        self.post_toast("New ball");
+    }
+
+    pub fn reset_game(&mut self) {
+        self.seed = Self::choose_seed(&self.config);
+        self.rng = GameRng::new(self.seed, 0);
+        let (ball, peddles, mut cells) =
+            Self::get_start_values(self.width, self.height, &self.config, self.seed);
+        self.boss = Self::place_boss(&mut cells, self.width, &self.config);
+        self.balls = vec![ball];
+        self.peddles = peddles;
+        self.cells = cells;
+        self.index_bricks();
+        self.initial_breakable = self.remaining_breakable();
+        self.reset_round();
+        self.stopwatch = Stopwatch::new();
+        if self.state == GameState::Playing {
+            self.stopwatch.start(Instant::now());
+        }
+        self.time_left = Self::time_limit(&self.config);
+        self.score = 0;
+        self.lives = Self::get_start_lives();
+        self.last_life_threshold = 0;
+        self.stats = GameStats::default();
+        self.redraw_scene();
+    }
+
+    /// The state of the current game, see `restore`.
+    pub fn snapshot(&self) -> GameSnapshot {
+        GameSnapshot {
+            seed: self.seed,
+            rng_draws: self.rng.draws,
+            level: self.config.level.clone(),
+            score: self.score,
+            lives: self.lives,
+            balls: self
+                .balls
+                .iter()
+                .map(|ball| BallSnapshot {
+                    pos: ball.game_pos,
+                    vel: ball.vel,
+                    stuck_offset: ball.stuck_offset,
+                    holder: ball.holder,
+                })
+                .collect(),
+            peddles: self
+                .peddles
+                .iter()
+                .map(|peddle| PeddleSnapshot { x: peddle.game_x })
+                .collect(),
+            bricks: self
+                .cells
+                .iter()
+                .map(|cell| BrickSnapshot {
+                    pos: cell.pos,
+                    width: cell.width,
+                    kind: cell.kind,
+                    hits_left: cell.hits_left,
+                    color: cell.named_color,
+                })
+                .collect(),
+            boss: self.boss.as_ref().map(|boss| BossSnapshot {
+                pos: boss.pos,
+                width: boss.width,
+                height: boss.height,
+                hp: boss.hp,
+                max_hp: boss.max_hp,
+            }),
+        }
+    }
+
+    /// Continues the game from `snapshot`. Balls take their remaining
+    /// properties from the configuration, short lived effects are dropped.
+    pub fn restore(&mut self, snapshot: &GameSnapshot) {
+        self.seed = snapshot.seed;
+        self.rng = GameRng::new(self.seed, snapshot.rng_draws);
+        self.config.level = snapshot.level.clone();
+        self.score = snapshot.score;
+        self.lives = snapshot.lives;
+        self.last_life_threshold = match self.config.extra_life_every {
+            0 => 0,
+            every => self.score / every * every,
+        };
+        self.balls = snapshot
+            .balls
+            .iter()
+            .map(|saved| {
+                let mut ball = Self::new_ball(saved.pos, &self.config);
+                ball.vel = saved.vel;
+                ball.stuck_offset = saved.stuck_offset;
+                ball.holder = saved.holder.min(self.peddles.len() - 1);
+                ball
+            })
+            .collect();
+        for (peddle, saved) in self.peddles.iter_mut().zip(&snapshot.peddles) {
+            peddle.jump_to(saved.x, self.width);
+        }
+        let damage_colors = Rc::new(self.config.damage_colors.clone());
+        self.cells = snapshot
+            .bricks
+            .iter()
+            .map(|saved| {
+                let color = saved.color.unwrap_or(NamedColor::Red);
+                let mut cell = Cell::colored(saved.pos, saved.width, saved.kind, color);
+                cell.hits_left = saved.hits_left;
+                cell.damage_colors = Some(Rc::clone(&damage_colors));
+                if cell.is_cracked() {
+                    cell.thaw_ticks = Cell::get_thaw_ticks();
+                }
+                cell
+            })
+            .collect();
+        self.index_bricks();
+        // Snapshots do not keep the starting layout, so the progress bar
+        // counts from the current game's start or the restored bricks
+        self.initial_breakable = self.initial_breakable.max(self.remaining_breakable());
+        self.boss = snapshot.boss.as_ref().map(|saved| {
+            let mut boss = Boss::new(saved.pos, saved.width, saved.height);
+            boss.hp = saved.hp;
+            boss.max_hp = saved.max_hp;
+            boss
+        });
+        self.reset_round();
+        self.redraw_scene();
+    }
+
+    /// A new game counts down first, unless the intro is skipped.
+    fn first_state(config: &GameConfig) -> GameState {
+        if config.skip_intro {
+            GameState::Playing
+        } else {
+            GameState::Countdown(Self::get_countdown_ticks())
+        }
+    }
+
+    /// Starts the round over on the same bricks: the peddles go back to the
+    /// start and a fresh ball drops from above them. Score, lives and the
+    /// bricks are kept.
+    fn soft_reset(&mut self) {
+        self.peddles = Self::start_peddles(self.width, self.height, &self.config);
+        let ball_pos = Self::start_pos(&self.peddles[0], &self.cells, &self.config);
+        self.balls = vec![Self::new_ball(ball_pos, &self.config)];
+        self.reset_round();
+        self.redraw_scene();
+    }
+
+    /// Ends the power-ups, mercy, nudges, shaking and pausing of the round,
+    /// for a new game, a restarted round and a restored snapshot.
+    fn reset_round(&mut self) {
+        self.power_ups.clear();
+        self.particles.clear();
+        self.projectiles.clear();
+        self.toasts.clear();
+        self.sticky_catches = 0;
+        self.sticky_ticks = 0;
+        self.launch_charge = None;
+        self.safety_net_ticks = 0;
+        self.mercy_ticks = 0;
+        self.mercy_pending = false;
+        self.nudges_left = self.config.nudges;
+        self.laser_ticks = 0;
+        self.laser_cooldown = 0;
+        self.descend_ticks = self.descend_interval();
+        self.shake_ticks = 0;
+        self.stdout.set_offset(0);
+        self.state = Self::first_state(&self.config);
+        self.paused = false;
+        self.autopaused = false;
+    }
+
+    /// Spares the player from losing the next balls for `--mercy-secs` once
+    /// the new ball is launched, see `start_mercy`.
+    fn grant_mercy(&mut self) {
+        self.mercy_pending = self.config.mercy_secs > 0;
+    }
+
+    /// Starts a granted mercy as soon as a ball is in play, with the peddles
+    /// blinking meanwhile.
+    fn start_mercy(&mut self) {
+        let launched = self.balls.iter().any(|ball| ball.stuck_offset.is_none());
+        if !self.mercy_pending || !launched {
+            return;
+        }
+        self.mercy_pending = false;
+        self.mercy_ticks = self.config.mercy_secs * (1000 / FRAME_MILLIS) as u32;
+        for peddle in &mut self.peddles {
+            peddle.flash_ticks = self.mercy_ticks;
+        }
+    }
+
+    /// Bounces `ball` off the bricks and the boss it touches, looking up the
+    /// bricks near it in `bricks`. Destroyed bricks are added to `to_kill` and
+    /// skipped until they are removed. Returns the number of boss hits.
+    fn hit_bricks(
+        ball: &mut Ball,
+        cells: &mut [Cell],
+        bricks: &mut BrickIndex,
+        boss: &Option<Boss>,
+        to_kill: &mut Vec<usize>,
+    ) -> u32 {
+        for &index in bricks.near(ball) {
+            let cell = &mut cells[index];
+            if cell.is_destroyed() {
+                continue;
+            }
+            if let Some(hit_dir) = ball.collides_with(cell) {
+                // A fireball burns through every brick but the solid ones
+                if ball.fire_ticks > 0 && cell.is_breakable() {
+                    cell.shatter();
+                    to_kill.push(index);
+                    continue;
+                }
+                if cell.hit() {
+                    to_kill.push(index);
+                }
+                ball.change_direction(hit_dir);
+            }
+        }
+        match *boss {
+            Some(ref boss) => match ball.collides_with(boss) {
+                Some(hit_dir) => {
+                    ball.change_direction(hit_dir);
+                    1
+                }
+                None => 0,
+            },
+            None => 0,
+        }
+    }
+
+    /// Replaces the bricks with a layout from a new seed drawn from the
+    /// gameplay rng, so `--seed` games swap to the same layouts. The score,
+    /// lives, balls and peddles stay as they are, no brick appears on a ball.
+    /// A level read with `--level-stdin` has only the one layout.
+    fn regenerate_level(&mut self) {
+        if self.config.level.is_some() {
+            self.post_toast("The level has a fixed layout");
+            return;
+        }
+        let mut seed = self.seed;
+        while seed == self.seed {
+            seed = u64::from(self.rng.gen::<u32>());
+        }
+        self.seed = seed;
+        let mut cells = Self::generate_cell_grid((self.width, self.height), &self.config, seed);
+        let balls = &self.balls;
+        cells.retain(|cell| !balls.iter().any(|ball| ball.overlaps(cell)));
+        self.boss = Self::place_boss(&mut cells, self.width, &self.config);
+        self.cells = cells;
+        self.index_bricks();
+        self.initial_breakable = self.remaining_breakable();
+        self.redraw_scene();
+        self.post_toast("New layout");
+    }
+
+    /// Rebuilds the brick index after bricks moved, appeared or were removed.
+    fn index_bricks(&mut self) {
+        self.brick_index.rebuild(&self.cells);
+    }
+
+    /// Repaints everything that is not redrawn every frame.
+    fn redraw_scene(&mut self) {
+        self.stdout.clear_all();
+        self.draw_game_borders();
+        self.draw_time_scale();
+        self.draw_lives();
+        self.frame_label.clear();
+        self.time_label.clear();
+        self.balls_label.clear();
+        self.progress_label.clear();
+        self.charge_label.clear();
+        self.nudges_label.clear();
+        for cell in &mut self.cells {
+            cell.dirty = true;
+        }
+        self.draw_dirty_bricks();
+        self.redraw_boss();
+        self.stdout.flush().unwrap();
+    }
+
+    /// Plays until the player quits, failing only when the terminal cannot be
+    /// set up or restored.
+    pub fn run(&mut self) -> Result<(), GameError> {
+        self.stdout
+            .enter()
+            .map_err(|e| GameError::Io("set up the terminal".to_string(), e))?;
+        self.reset_game(); // Display dummy game scene
+
+        if !self.config.skip_intro && !self.start_screen() {
+            return self.leave();
+        }
+
+        self.reset_game();
+        // Replaying after the loaded game ends starts a fresh one
+        if let Some(saved) = self.config.saved_game.take() {
+            self.restore(&saved);
+        }
+        loop {
+            if !self.update() {
+                break;
+            }
+            if self.check_terminal_size() {
+                thread::sleep(Duration::from_millis(FRAME_MILLIS));
+                continue;
+            }
+
+            let result = match self.state {
+                GameState::Countdown(_) => {
+                    self.state = self.state.next();
+                    self.draw_countdown();
+                    if self.state == GameState::Playing {
+                        self.stopwatch.start(Instant::now());
+                        self.post_toast("Go!");
+                    }
+                    TickResult::Running
+                }
+                GameState::Playing => {
+                    let advance = !self.paused || self.single_step;
+                    self.single_step = false;
+                    if advance {
+                        self.tick()
+                    } else {
+                        TickResult::Running
+                    }
+                }
+            };
+            if result != TickResult::Running {
+                self.stopwatch.stop(Instant::now());
+                self.stop_shake();
+            }
+            self.draw_play_time();
+
+            if result != TickResult::Running {
+                if let Err(e) = self.export_result(result == TickResult::Won) {
+                    self.leave()?;
+                    return Err(GameError::Io("export the result".to_string(), e));
+                }
+            }
+            let replay = match result {
+                TickResult::Running => None,
+                TickResult::BallLost | TickResult::TimeUp | TickResult::Overrun => {
+                    Some(self.game_over_screen())
+                }
+                TickResult::Won => Some(self.game_won_screen()),
+            };
+            match replay {
+                Some(true) => self.reset_game(),
+                Some(false) => break,
+                None => {}
+            }
+
+            self.present_frame();
+        }
+
+        self.leave()
+    }
+
+    /// Restores the terminal when the game ends.
+    fn leave(&mut self) -> Result<(), GameError> {
+        self.stdout
+            .leave()
+            .map_err(|e| GameError::Io("restore the terminal".to_string(), e))
+    }
+
+    /// Advances the simulation by one frame: moves the balls and resolves brick hits.
+    fn tick(&mut self) -> TickResult {
+        if let Some(ref mut log) = self.frame_log {
+            log.record(&self.balls, &self.peddles, self.cells.len());
+        }
+        if let Some(time_left) = self.time_left {
+            let frame_micros = FRAME_MILLIS as f32 * 1000f32 * self.time_scale;
+            let time_left = time_left.saturating_sub(Duration::from_micros(frame_micros as u64));
+            self.time_left = Some(time_left);
+            if time_left == Duration::ZERO {
+                return TickResult::TimeUp;
+            }
+        }
+        if self.config.descend_secs > 0 {
+            self.descend_ticks = self.descend_ticks.saturating_sub(1);
+            if self.descend_ticks == 0 {
+                self.descend_ticks = self.descend_interval();
+                if self.descend() {
+                    return TickResult::Overrun;
+                }
+            }
+        }
+        let game_size = (self.width, self.height);
+        let ball_time_scale = self.ball_time_scale();
+        for peddle in &mut self.peddles {
+            peddle.bounce_ticks = peddle.bounce_ticks.saturating_sub(1);
+            peddle.flash_ticks = peddle.flash_ticks.saturating_sub(1);
+        }
+        self.advance_charge();
+        self.start_mercy();
+        self.mercy_ticks = self.mercy_ticks.saturating_sub(1);
+        if self.config.ball_collisions {
+            self.collide_balls();
+        }
+        let mut lost = vec![];
+        let mut to_kill = vec![];
+        let mut boss_hits = 0;
+        // Balls that moved in substeps and already hit their bricks this frame
+        let mut swept = vec![false; self.balls.len()];
+        for (index, swept) in swept.iter_mut().enumerate() {
+            let target = if self.config.assist {
+                self.nearest_brick_center(self.balls[index].game_pos)
+            } else {
+                None
+            };
+            // A fast ball moves in substeps and hits the bricks after each one.
+            // Its first bounce off a brick or the peddle ends the checks for
+            // those for the frame, so it does not bounce again on the way out.
+            let substeps = self.balls[index].substeps(ball_time_scale);
+            *swept = substeps > 1;
+            let mut bounced = false;
+            let mut hit_peddle = false;
+            for _ in 0..substeps {
+                let ball = &mut self.balls[index];
+                let time_scale = ball_time_scale / substeps as f32;
+                let peddles = if hit_peddle {
+                    &[][..]
+                } else {
+                    &self.peddles[..]
+                };
+                match ball.update(game_size, peddles, target, time_scale) {
+                    BallUpdate::Lost if self.mercy_ticks > 0 => {
+                        let peddle = &self.peddles[0];
+                        ball.game_pos.0 = (peddle.x() + peddle.get_width() / 2) as f32;
+                        ball.stick_to(&self.peddles, 0);
+                        ball.launch(&self.peddles);
+                        break;
+                    }
+                    BallUpdate::Lost if self.safety_net_ticks > 0 => {
+                        ball.bounce_off_floor(self.peddles[0].y());
+                        self.safety_net_ticks = 0;
+                        self.draw_safety_net();
+                    }
+                    BallUpdate::Lost => {
+                        lost.push(index);
+                        break;
+                    }
+                    BallUpdate::HitPeddle(peddle_index) => {
+                        hit_peddle = true;
+                        self.stats.record_peddle_hit();
+                        if self.config.effects {
+                            self.peddles[peddle_index].bounce();
+                        }
+                        if self.sticky_catches > 0 && self.sticky_ticks > 0 {
+                            ball.stick_to(&self.peddles, peddle_index);
+                            self.sticky_catches -= 1;
+                        }
+                    }
+                    BallUpdate::Moving => {}
+                }
+                if *swept && !bounced {
+                    let ball = &mut self.balls[index];
+                    let bounces = ball.bounces;
+                    boss_hits += Self::hit_bricks(
+                        ball,
+                        &mut self.cells,
+                        &mut self.brick_index,
+                        &self.boss,
+                        &mut to_kill,
+                    );
+                    bounced = ball.bounces > bounces;
+                }
+            }
+        }
+        for index in lost.into_iter().rev() {
+            self.balls.remove(index);
+            swept.remove(index);
+            self.stats.record_ball_lost();
+        }
+        if self.balls.is_empty() {
+            if !self.config.practice {
+                if self.lives <= 1 {
+                    return TickResult::BallLost;
+                }
+                self.lives -= 1;
+                self.draw_lives();
+                self.grant_mercy();
+                self.nudges_left = self.config.nudges;
+            }
+            self.respawn_ball();
+        }
+        for peddle in &mut self.peddles {
+            peddle.ease(self.time_scale);
+        }
+        for ball in &mut self.balls {
+            ball.follow(&self.peddles);
+        }
+        self.sticky_ticks = self.sticky_ticks.saturating_sub(1);
+        for cell in &mut self.cells {
+            cell.refreeze();
+        }
+        if self.safety_net_ticks == 1 {
+            self.safety_net_ticks = 0;
+            self.draw_safety_net();
+        }
+        self.safety_net_ticks = self.safety_net_ticks.saturating_sub(1);
+        self.update_power_ups();
+        self.update_particles();
+        self.laser_ticks = self.laser_ticks.saturating_sub(1);
+        self.laser_cooldown = self.laser_cooldown.saturating_sub(1);
+        let side = self.config.peddle_side;
+        for projectile in &mut self.projectiles {
+            projectile.rise(side, self.time_scale);
+        }
+        let bottom = coords::bottom(self.height);
+        self.projectiles.retain(|projectile| match side {
+            PeddleSide::Bottom => projectile.y() >= coords::top(),
+            PeddleSide::Top => projectile.y() <= bottom,
+        });
+
+        for (index, ball) in self.balls.iter_mut().enumerate() {
+            if !swept.get(index).cloned().unwrap_or(false) {
+                boss_hits += Self::hit_bricks(
+                    ball,
+                    &mut self.cells,
+                    &mut self.brick_index,
+                    &self.boss,
+                    &mut to_kill,
+                );
+            }
+            // Counted per frame, however many substeps the ball moved in
+            if ball.stuck_offset.is_none() {
+                ball.check_shallow_angle();
+            }
+            self.stats.bounces += ball.bounces;
+            ball.bounces = 0;
+            ball.fire_ticks = ball.fire_ticks.saturating_sub(1);
+        }
+        let cells = &mut self.cells;
+        let boss = &self.boss;
+        self.projectiles.retain(|projectile| {
+            // Bricks the balls destroyed this frame are only removed below
+            let live = |cell: &Cell| !cell.is_destroyed() && projectile.hits(cell);
+            if let Some(index) = cells.iter().position(live) {
+                if cells[index].hit() {
+                    to_kill.push(index);
+                }
+                return false;
+            }
+            if boss.as_ref().map_or(false, |boss| projectile.hits(boss)) {
+                boss_hits += 1;
+                return false;
+            }
+            true
+        });
+        // Two balls can destroy the same brick in one frame
+        to_kill.sort();
+        to_kill.dedup();
+        let hit_count = to_kill.len();
+        let mut to_kill = self.with_explosions(to_kill);
+        let chain_bonus = (to_kill.len() - hit_count) as u32 * Self::get_chain_bonus();
+        if chain_bonus > 0 {
+            self.add_score(chain_bonus);
+            self.post_toast(&format!("Chain +{}", chain_bonus));
+            self.shake();
+        }
+        let combo = self.stats.combo;
+        // Remove from the back so the remaining indices stay valid
+        to_kill.sort();
+        let removed = !to_kill.is_empty();
+        for i in to_kill.into_iter().rev() {
+            self.cells[i].clear(&mut self.stdout);
+            let cell = self.cells.remove(i);
+            let points = self.config.brick_points.points(&cell);
+            self.add_score(points);
+            self.stats.record_brick(cell.named_color);
+            if cell.kind == BrickKind::MultiBall {
+                let pos = ((cell.x() + cell.get_width() / 2) as f32, cell.y() as f32);
+                let ball = self.balls[0].spawn_at(pos);
+                self.balls.push(ball);
+                self.post_toast("Multi-ball!");
+            }
+            self.maybe_drop_power_up(&cell);
+            if self.config.effects {
+                self.scatter_particles(&cell);
+            }
+        }
+        if removed {
+            self.index_bricks();
+        }
+        let threshold = self.config.fireball_combo;
+        if threshold > 0 && combo < threshold && self.stats.combo >= threshold {
+            self.ignite_balls();
+        }
+        self.draw_dirty_bricks();
+        if boss_hits > 0 && self.hit_boss(boss_hits) {
+            return TickResult::Won;
+        }
+        if self.boss.is_none() && self.remaining_breakable() == 0 {
+            return TickResult::Won;
+        }
+
+        TickResult::Running
+    }
+
+    /// Turns every ball into a fireball for a while, once a combo reached
+    /// `--fireball` bricks.
+    fn ignite_balls(&mut self) {
+        for ball in &mut self.balls {
+            ball.fire_ticks = Ball::get_fire_ticks();
+        }
+        self.post_toast("Fireball!");
+        self.shake();
+    }
+
+    /// Frames between two moves of the bricks with `--descend`.
+    fn descend_interval(&self) -> u32 {
+        self.config.descend_secs * (1000 / FRAME_MILLIS) as u32
+    }
+
+    /// Moves every brick and the boss down a row and returns true once a
+    /// breakable brick or the boss reached the peddle row. Unbreakable bricks
+    /// reaching it are removed, the player could never clear them.
+    fn descend(&mut self) -> bool {
+        for cell in &mut self.cells {
+            cell.clear(&mut self.stdout);
+            cell.pos.1 += 1;
+            cell.dirty = true;
+        }
+        if let Some(ref mut boss) = self.boss {
+            boss.clear(&mut self.stdout);
+            // The health bar sits on the row above the boss
+            self.stdout.draw_text(
+                (boss.x(), boss.y() - 1),
+                &color::Reset,
+                &" ".repeat(boss.get_width()),
+            );
+            boss.pos.1 += 1;
+        }
+        let peddle_row = self.peddles[0].y();
+        // Not drawn at their new row yet, so there is nothing to clear
+        self.cells
+            .retain(|cell| cell.is_breakable() || cell.y() < peddle_row);
+        self.index_bricks();
+        self.draw_dirty_bricks();
+        self.redraw_boss();
+        let lowest = self.cells.iter().map(|cell| cell.y());
+        let lowest = lowest.chain(
+            self.boss
+                .iter()
+                .map(|boss| boss.y() + boss.get_height() - 1),
+        );
+        lowest.max().map_or(false, |y| y >= peddle_row)
+    }
+
+    /// Time scale the balls move at, set with the speed keys and slowed down
+    /// for the last few bricks with the easy finish.
+    fn ball_time_scale(&self) -> f32 {
+        let finishing = self.config.easy_finish
+            && self.boss.is_none()
+            && self.remaining_breakable() <= self.config.easy_finish_bricks;
+        let time_scale = self.time_scale * self.speed_multiplier;
+        if finishing {
+            time_scale * Self::get_easy_finish_slowdown()
+        } else {
+            time_scale
+        }
+    }
+
+    /// Changes the ball speed by `steps` speed steps within the configured
+    /// limits and shows the new speed.
+    fn change_speed(&mut self, steps: f32) {
+        let multiplier = self.speed_multiplier + steps * Self::get_speed_step();
+        self.speed_multiplier = self.config.speed_limits.clamp(multiplier);
+        self.post_toast(&format!("Speed x{:.2}", self.speed_multiplier));
+    }
+
+    /// Damages the boss `hits` times and returns true once it is destroyed.
+    fn hit_boss(&mut self, hits: u32) -> bool {
+        let destroyed = match self.boss {
+            Some(ref mut boss) => (0..hits).any(|_| boss.hit()),
+            None => return false,
+        };
+        self.add_score(hits * ScoreTable::get_base_points());
+        self.shake();
+        if destroyed {
+            self.stats.record_brick(None);
+        }
+        self.redraw_boss();
+        destroyed
+    }
+
+    /// Adds `points` to the score, granting an extra life for every threshold
+    /// crossed until the lives are maxed out.
+    fn add_score(&mut self, points: u32) {
+        self.score += points;
+        let every = self.config.extra_life_every;
+        if every == 0 {
+            return;
+        }
+        while self.score >= self.last_life_threshold + every {
+            self.last_life_threshold += every;
+            if self.lives < Self::get_max_lives() {
+                self.lives += 1;
+                self.draw_lives();
+                self.post_toast("Extra life!");
+            }
+        }
+    }
+
+    /// Adds the breakable neighbors of every exploding brick in `destroyed`,
+    /// following chains of explosive bricks. Each index appears only once.
+    fn with_explosions(&self, mut destroyed: Vec<usize>) -> Vec<usize> {
+        let gap = self.config.brick_gap;
+        let mut exploding: Vec<usize> = destroyed
+            .iter()
+            .cloned()
+            .filter(|&index| self.cells[index].kind == BrickKind::Explosive)
+            .collect();
+        while let Some(center) = exploding.pop() {
+            for (index, cell) in self.cells.iter().enumerate() {
+                if !cell.is_breakable()
+                    || destroyed.contains(&index)
+                    || !cell.is_neighbor(&self.cells[center], gap)
+                {
+                    continue;
+                }
+                destroyed.push(index);
+                if cell.kind == BrickKind::Explosive {
+                    exploding.push(index);
+                }
+            }
+        }
+        destroyed
+    }
+
+    /// Gives a destroyed brick a chance to release a power-up capsule.
+    fn maybe_drop_power_up(&mut self, cell: &Cell) {
+        if self.rng.gen_range(0, Self::get_power_up_drop_odds()) == 0 {
+            let mut kinds = vec![PowerUpKind::Sticky, PowerUpKind::Laser];
+            if self.config.safety_net_secs > 0 {
+                kinds.push(PowerUpKind::SafetyNet);
+            }
+            let kind = kinds[self.rng.gen_range(0, kinds.len())];
+            self.power_ups.push(PowerUp {
+                game_pos: ((cell.x() + cell.get_width() / 2) as f32, cell.y() as f32),
+                kind,
+            });
+        }
+    }
+
+    /// Sends debris flying from the center of a destroyed brick.
+    fn scatter_particles(&mut self, cell: &Cell) {
+        // Not drawn from the gameplay rng so effects do not change the game
+        let mut rng = rand::thread_rng();
+        let center = ((cell.x() + cell.get_width() / 2) as f32, cell.y() as f32);
+        for _ in 0..Particle::get_count_per_brick() {
+            self.particles.push(Particle::scatter(center, &mut rng));
+        }
+    }
+
+    fn update_particles(&mut self) {
+        let game_size = (self.width, self.height);
+        let time_scale = self.time_scale;
+        for particle in &mut self.particles {
+            particle.advance(time_scale);
+        }
+        self.particles
+            .retain(|particle| particle.is_visible(game_size));
+    }
+
+    /// Moves falling power-ups, activating the ones caught by a peddle and
+    /// dropping the ones that reached the peddles' edge.
+    fn update_power_ups(&mut self) {
+        let mut caught = vec![];
+        let side = self.config.peddle_side;
+        let edge = side.edge_row(self.height);
+        let peddles = &self.peddles;
+        let time_scale = self.time_scale;
+        self.power_ups.retain(|power_up| {
+            if peddles.iter().any(|peddle| power_up.is_caught_by(peddle)) {
+                caught.push(power_up.kind);
+                return false;
+            }
+            match side {
+                PeddleSide::Bottom => power_up.y() < edge,
+                PeddleSide::Top => power_up.y() > edge,
+            }
+        });
+        for power_up in &mut self.power_ups {
+            power_up.fall(side, time_scale);
+        }
+        for kind in caught {
+            self.activate_power_up(kind);
+        }
+    }
+
+    fn activate_power_up(&mut self, kind: PowerUpKind) {
+        match kind {
+            PowerUpKind::Sticky => {
+                self.sticky_catches = Self::get_sticky_catches();
+                self.sticky_ticks = Self::get_sticky_ticks();
+                self.post_toast("Sticky peddle!");
+            }
+            PowerUpKind::SafetyNet => {
+                self.safety_net_ticks = self.config.safety_net_secs * (1000 / FRAME_MILLIS) as u32;
+                self.post_toast("Safety net!");
+                self.shake();
+            }
+            PowerUpKind::Laser => {
+                self.laser_ticks = Self::get_laser_ticks();
+                self.post_toast("Laser!");
+            }
+        }
+    }
+
+    /// Bounces every pair of touching balls off each other. There are only a
+    /// handful of balls, so checking all pairs is cheap.
+    fn collide_balls(&mut self) {
+        for second in 1..self.balls.len() {
+            let (before, after) = self.balls.split_at_mut(second);
+            for first in before.iter_mut() {
+                first.collide_with(&mut after[0]);
+            }
+        }
+    }
+
+    fn holds_ball(&self, player: usize) -> bool {
+        self.balls
+            .iter()
+            .any(|ball| ball.holder == player && ball.stuck_offset.is_some())
+    }
+
+    /// Starts charging a launch for `player`, or keeps charging it while the
+    /// key repeats. Only one player charges at a time.
+    fn charge_launch(&mut self, player: usize) {
+        match self.launch_charge {
+            Some(ref mut charge) if charge.player == player => charge.idle_ticks = 0,
+            Some(_) => {}
+            None => self.launch_charge = Some(LaunchCharge::new(player)),
+        }
+    }
+
+    /// Counts the frames of a charging launch and launches the balls of the
+    /// player once the launch key has been let go.
+    fn advance_charge(&mut self) {
+        let released = match self.launch_charge {
+            Some(ref mut charge) => {
+                charge.ticks += 1;
+                charge.idle_ticks += 1;
+                charge.idle_ticks > Self::get_charge_release_ticks()
+            }
+            None => false,
+        };
+        if !released {
+            return;
+        }
+        let charge = self.launch_charge.take().expect("a charge was released");
+        let boost = self.charge_boost(charge.held_ticks());
+        for ball in &mut self.balls {
+            if ball.holder == charge.player {
+                ball.launch_boosted(&self.peddles, boost);
+            }
+        }
+    }
+
+    /// Share of a full charge after holding the key for `held_ticks` frames.
+    fn charge_share(&self, held_ticks: u32) -> f32 {
+        held_ticks.min(Self::get_full_charge_ticks()) as f32 / Self::get_full_charge_ticks() as f32
+    }
+
+    /// Speed factor of a launch charged for `held_ticks` frames, from 1 up to
+    /// the maximum boost at a full charge.
+    fn charge_boost(&self, held_ticks: u32) -> f32 {
+        1f32 + self.charge_share(held_ticks) * (Self::get_max_charge_boost() - 1f32)
+    }
+
+    fn get_max_charge_boost() -> f32 {
+        1.5
+    }
+
+    /// Frames of holding the launch key for a full charge.
+    fn get_full_charge_ticks() -> u32 {
+        3 * (1000 / FRAME_MILLIS) as u32 / 2
+    }
+
+    /// Frames without a repeat after which the launch key counts as let go,
+    /// longer than the usual delay before a held key starts repeating.
+    fn get_charge_release_ticks() -> u32 {
+        600 / FRAME_MILLIS as u32
+    }
+
+    /// Shoots a projectile towards the bricks from the middle of `player`'s
+    /// peddle while the laser is on and has cooled down from the last shot.
+    fn fire_laser(&mut self, player: usize) {
+        if self.laser_ticks == 0 || self.laser_cooldown > 0 {
+            return;
+        }
+        let peddle = &self.peddles[player];
+        let x = (peddle.x() + peddle.get_width() / 2) as f32;
+        let y = peddle.y() as f32 - self.config.peddle_side.towards_peddle();
+        self.projectiles.push(Projectile { game_pos: (x, y) });
+        self.laser_cooldown = Self::get_laser_cooldown_ticks();
+    }
+
+    /// Starts shaking the screen to stress a big hit, with `--effects` only.
+    fn shake(&mut self) {
+        if self.config.effects {
+            self.shake_ticks = Self::get_shake_ticks();
+        }
+    }
+
+    /// Moves the playfield to the offset of the current shake frame,
+    /// repainting it whenever it moved. The playfield only moves into spare
+    /// terminal columns, a game filling the terminal does not shake.
+    fn shake_frame(&mut self) {
+        let spare_columns = match self.stdout.size() {
+            Some((columns, _)) => columns.saturating_sub(self.width),
+            None => Unit::MAX,
+        };
+        let offset = Self::get_shake_offset(self.shake_ticks).min(spare_columns);
+        self.shake_ticks = self.shake_ticks.saturating_sub(1);
+        if offset != self.stdout.offset() {
+            self.stdout.set_offset(offset);
+            self.redraw_scene();
+        }
+    }
+
+    /// Puts the playfield back in place right away, e.g. before a dialog.
+    fn stop_shake(&mut self) {
+        self.shake_ticks = 0;
+        self.shake_frame();
+    }
+
+    /// Queues `text` to be shown at the top of the playfield once the toasts
+    /// before it are gone. A message already waiting is not queued twice.
+    fn post_toast(&mut self, text: &str) {
+        if self.toasts.iter().any(|toast| toast.text == text) {
+            return;
+        }
+        self.toasts.push_back(Toast {
+            text: text.to_string(),
+            ticks_left: Self::get_toast_ticks(),
+        });
+    }
+
+    /// Draws the current toast for one more frame. A toast that has been shown
+    /// for its whole duration is blanked, repainting the bricks underneath,
+    /// and the next one takes its place.
+    fn draw_toast(&mut self) {
+        if self
+            .toasts
+            .front()
+            .map_or(false, |toast| toast.ticks_left == 0)
+        {
+            let toast = self.toasts.pop_front().unwrap();
+            let pos = self.toast_pos(&toast.text);
+            let width = display_width(&toast.text);
+            self.stdout
+                .draw_text(pos, &color::Reset, &" ".repeat(width));
+            for x in pos.0..pos.0 + width {
+                self.restore_bricks_at((x, pos.1));
+            }
+        }
+        if let Some(toast) = self.toasts.front_mut() {
+            toast.ticks_left -= 1;
+            let text = toast.text.clone();
+            let pos = self.toast_pos(&text);
+            self.stdout.draw_text(pos, &color::Yellow, &text);
+        }
+    }
+
+    /// Where `text` starts when centered in the top row of the playfield.
+    fn toast_pos(&self, text: &str) -> (Unit, Unit) {
+        (self.width / 2 - display_width(text) / 2, coords::top())
+    }
+
+    /// Repaints bricks and field walls underneath `pos` after something was
+    /// drawn on top of them.
+    fn restore_bricks_at(&mut self, pos: (Unit, Unit)) {
+        if self.config.field.is_wall(pos, (self.width, self.height)) {
+            let color = self.config.theme.border_color(self.frame);
+            self.stdout
+                .draw_text(pos, color.as_ref(), graphics::BORDER_FILL);
+        }
+        for index in 0..self.cells.len() {
+            let cell = &self.cells[index];
+            if cell.y() == pos.1 && pos.0 >= cell.x() && pos.0 < cell.x() + cell.get_width() {
+                self.redraw_brick(index);
+            }
+        }
+        if self.boss.as_ref().map_or(false, |boss| boss.covers(pos)) {
+            self.redraw_boss();
+        }
+    }
+
+    /// Repaints a single brick, e.g. after it was damaged, without touching the
+    /// rest of the grid.
+    fn redraw_brick(&mut self, index: usize) {
+        self.cells[index].write(&mut self.stdout);
+    }
+
+    /// Draws the bricks that changed since they were last drawn, e.g. new or
+    /// damaged ones, and marks them clean.
+    fn draw_dirty_bricks(&mut self) {
+        for cell in self.cells.iter_mut().filter(|cell| cell.dirty) {
+            cell.write(&mut self.stdout);
+            cell.dirty = false;
+        }
+    }
+
+    fn redraw_boss(&mut self) {
+        if let Some(ref boss) = self.boss {
+            boss.write(&mut self.stdout);
+            boss.write_health_bar(&mut self.stdout);
+        }
+    }
+
+    /// All bricks still on the field, in the order they are stored internally.
+    pub fn bricks(&self) -> impl Iterator<Item = &Cell> {
+        self.cells.iter()
+    }
+
+    /// Number of bricks on the field, including indestructible ones.
+    pub fn brick_count(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Counters of the current game, reset when a new game starts.
+    pub fn stats(&self) -> &GameStats {
+        &self.stats
+    }
+
+    /// Center of the breakable brick closest to `(x, y)`, the aim assist target.
+    fn nearest_brick_center(&self, (x, y): (f32, f32)) -> Option<(f32, f32)> {
+        self.bricks()
+            .filter(|cell| cell.is_breakable())
+            .map(|cell| {
+                (
+                    cell.x() as f32 + cell.get_width() as f32 / 2f32,
+                    cell.y() as f32,
+                )
+            })
+            .min_by(|a, b| {
+                let distance_a = (a.0 - x).powi(2) + (a.1 - y).powi(2);
+                let distance_b = (b.0 - x).powi(2) + (b.1 - y).powi(2);
+                distance_a.partial_cmp(&distance_b).unwrap()
+            })
+    }
+
+    /// Number of bricks that still have to be destroyed to win the game.
+    pub fn remaining_breakable(&self) -> usize {
+        self.bricks().filter(|cell| cell.is_breakable()).count()
+    }
+
+    /// Draws the moving objects, waits one frame and erases them again.
+    fn present_frame(&mut self) {
+        if self.shake_ticks > 0 || self.stdout.offset() != 0 {
+            self.shake_frame();
+        }
+        for particle in &self.particles {
+            particle.write(&mut self.stdout);
+        }
+        self.frame += 1;
+        if self.config.theme.border_changes(self.frame) {
+            self.repaint_borders();
+        }
+        self.draw_charge();
+        self.draw_ball_count();
+        self.draw_nudges();
+        self.draw_progress();
+        for power_up in &self.power_ups {
+            power_up.write(&mut self.stdout);
+        }
+        for projectile in &self.projectiles {
+            projectile.write(&mut self.stdout);
+        }
+        let mut trail = vec![];
+        for ball in &mut self.balls {
+            ball.record_trail();
+            trail.extend(ball.trail_cells());
+        }
+        for &(pos, glyph) in &trail {
+            self.stdout.draw_text(pos, &color::LightBlack, glyph);
+        }
+        let prediction = self.predicted_dots();
+        for &pos in &prediction {
+            self.stdout
+                .draw_text(pos, &color::LightBlack, graphics::PREDICTION);
+        }
+        let guides = self.guide_cells();
+        for &pos in &guides {
+            self.stdout
+                .draw_text(pos, &color::LightBlack, graphics::GUIDE);
+        }
+        for ball in &self.balls {
+            ball.write(&mut self.stdout);
+        }
+        for peddle in &self.peddles {
+            peddle.write(&mut self.stdout);
+        }
+        if self.safety_net_ticks > 0 {
+            self.draw_safety_net();
+        }
+        self.draw_toast();
+
+        if self.config.debug {
+            self.draw_frame_time();
+        }
+
+        self.stdout.flush().unwrap();
+        thread::sleep(Duration::from_millis(FRAME_MILLIS));
+
+        for ball in &self.balls {
+            ball.clear(&mut self.stdout);
+        }
+        for peddle in &self.peddles {
+            peddle.clear(&mut self.stdout);
+        }
+        for (pos, _) in trail {
+            self.stdout.draw_text(pos, &color::Reset, " ");
+            self.restore_bricks_at(pos);
+        }
+        for pos in prediction {
+            self.stdout.draw_text(pos, &color::Reset, " ");
+            self.restore_bricks_at(pos);
+        }
+        for pos in guides {
+            self.stdout.draw_text(pos, &color::Reset, " ");
+            self.restore_bricks_at(pos);
+        }
+        for index in 0..self.power_ups.len() {
+            self.power_ups[index].clear(&mut self.stdout);
+            let pos = self.power_ups[index].get_pos();
+            self.restore_bricks_at(pos);
+        }
+        for index in 0..self.particles.len() {
+            self.particles[index].clear(&mut self.stdout);
+            let pos = self.particles[index].get_pos();
+            self.restore_bricks_at(pos);
+        }
+        for index in 0..self.projectiles.len() {
+            self.projectiles[index].clear(&mut self.stdout);
+            let pos = self.projectiles[index].get_pos();
+            self.restore_bricks_at(pos);
+        }
+    }
+
+    /// Every other cell of the predicted paths of the moving balls, empty
+    /// unless the prediction is enabled.
+    fn predicted_dots(&self) -> Vec<(Unit, Unit)> {
+        if !self.config.predict {
+            return vec![];
+        }
+        let game_size = (self.width, self.height);
+        self.balls
+            .iter()
+            .filter(|ball| ball.stuck_offset.is_none())
+            .flat_map(|ball| ball.predict_path(game_size, Ball::get_prediction_frames()))
+            .step_by(2)
+            .collect()
+    }
+
+    /// Cells of the guides along the edge behind the peddles, spanning each
+    /// peddle and a column to either side. Empty unless the guides are enabled
+    /// and while the safety net takes up the row.
+    fn guide_cells(&self) -> Vec<(Unit, Unit)> {
+        if !self.config.guides || self.safety_net_ticks > 0 {
+            return vec![];
+        }
+        let row = self.config.peddle_side.edge_row(self.height);
+        let reach = Self::get_guide_reach();
+        let right = coords::right(self.width);
+        self.peddles
+            .iter()
+            .flat_map(|peddle| {
+                let first = peddle.x().saturating_sub(reach).max(coords::left());
+                let last = (peddle.x() + peddle.get_width() - 1 + reach).min(right);
+                (first..=last).map(move |x| (x, row))
+            })
+            .collect()
+    }
+
+    /// Columns the guides reach past either end of the peddle.
+    fn get_guide_reach() -> Unit {
+        1
+    }
+
+    /// Draws the safety net line behind the peddle, or blanks it once the net is gone.
+    fn draw_safety_net(&mut self) {
+        let (glyph, color): (&str, &dyn color::Color) = if self.safety_net_ticks > 0 {
+            (graphics::SAFETY_NET, &color::Green)
+        } else {
+            (" ", &color::Reset)
+        };
+        let line = glyph.repeat(coords::inner_width(self.width));
+        let row = self.config.peddle_side.edge_row(self.height);
+        self.stdout.draw_text((coords::left(), row), color, &line);
+    }
+
+    fn read_key(&mut self) -> Option<Key> {
+        loop {
+            match self.stdin.next_input() {
+                Some(Input::Focus(focused)) => self.focus_changed(focused),
+                // Raw mode delivers Ctrl-C as a key instead of a signal
+                Some(Input::Key(Key::Ctrl('c'))) => {
+                    self.interrupt.store(true, Ordering::SeqCst);
+                    return Some(Key::Ctrl('c'));
+                }
+                Some(Input::Key(key)) => return Some(key),
+                None => return None,
+            }
+        }
+    }
+
+    fn interrupted(&self) -> bool {
+        self.interrupt.load(Ordering::SeqCst)
+    }
+
+    /// Pauses the game when the terminal loses focus with `--autopause`, and
+    /// resumes it once focus is back unless the player took over the pause.
+    fn focus_changed(&mut self, focused: bool) {
+        if !self.config.autopause {
+            return;
+        }
+        if !focused && !self.paused {
+            self.toggle_pause();
+            self.autopaused = true;
+        } else if focused && self.autopaused {
+            self.autopaused = false;
+            if self.paused {
+                self.toggle_pause();
+            }
+        }
+    }
+
+    /// Handles every key that arrived since the last frame, returns false once
+    /// the game should quit. However many moves are queued, a peddle travels
+    /// at most `max_peddle_step` columns per frame, so key repeat cannot make
+    /// it warp across the field.
+    fn update(&mut self) -> bool {
+        let mut start = self.peddle_targets();
+        loop {
+            let key = self.read_key();
+            let (_, action) = self.key_action(key);
+            if !self.handle_key(key) {
+                return false;
+            }
+            match action {
+                Some(Action::Left) | Some(Action::Right) => self.cap_peddle_travel(&start),
+                // These can put the peddles somewhere new, later moves count from there
+                Some(Action::Reset) | Some(Action::SoftReset) | None => {
+                    start = self.peddle_targets()
+                }
+                _ => {}
+            }
+            if key.is_none() {
+                return true;
+            }
+        }
+    }
+
+    fn peddle_targets(&self) -> Vec<f32> {
+        self.peddles.iter().map(|peddle| peddle.target_x).collect()
+    }
+
+    /// Keeps every peddle within `max_peddle_step` columns of where it was
+    /// headed at `start`.
+    fn cap_peddle_travel(&mut self, start: &[f32]) {
+        let max_step = self.config.max_peddle_step as f32;
+        for (peddle, &start) in self.peddles.iter_mut().zip(start) {
+            peddle.target_x = peddle.target_x.max(start - max_step).min(start + max_step);
+        }
+    }
+
+    /// Applies a single key press, returns false if it quits the game.
+    fn handle_key(&mut self, key: Option<Key>) -> bool {
+        if self.interrupted() {
+            return false;
+        }
+        let (player, action) = self.key_action(key);
+        if let GameState::Countdown(_) = self.state {
+            return action != Some(Action::Quit);
+        }
+        if self.too_small {
+            return action != Some(Action::Quit);
+        }
+
+        match action {
+            Some(Action::Quit) => return false,
+            Some(Action::Reset) => self.reset_game(),
+            Some(Action::SoftReset) => self.soft_reset(),
+            Some(Action::Regenerate) => self.regenerate_level(),
+            Some(Action::Save) => self.save_game(),
+            Some(Action::SpeedUp) => self.change_speed(1f32),
+            Some(Action::SlowDown) => self.change_speed(-1f32),
+            Some(Action::Launch) => {
+                if self.config.charge_launch && self.holds_ball(player) {
+                    self.charge_launch(player);
+                } else {
+                    for ball in &mut self.balls {
+                        if ball.holder == player {
+                            ball.launch(&self.peddles);
+                        }
+                    }
+                }
+                self.fire_laser(player);
+            }
+            Some(Action::Respawn) => self.respawn_ball(),
+            Some(Action::Nudge) => self.nudge_balls(player),
+            Some(Action::Left) => self.peddles[player].move_in_dir(Direction::Left, self.width),
+            Some(Action::Right) => self.peddles[player].move_in_dir(Direction::Right, self.width),
+            None => self.handle_debug_key(key),
+        }
+
+        true
+    }
+
+    /// Writes the game to the save file, telling the player how it went.
+    fn save_game(&mut self) {
+        let saved = SavedGame {
+            version: SavedGame::get_version(),
+            size: (self.width, self.height),
+            game: self.snapshot(),
+        };
+        match saved.save(&self.config.save_path) {
+            Ok(()) => self.post_toast("Game saved"),
+            Err(_) => self.post_toast("Saving failed"),
+        }
+    }
+
+    /// Outcome of a headless session that ran for `frames` frames.
+    pub fn recording(&self, frames: u64) -> Recording {
+        Recording {
+            seed: self.seed,
+            width: self.width,
+            height: self.height,
+            frames,
+            score: self.score,
+            bricks_remaining: self.remaining_breakable(),
+            options: self.config.fingerprint(),
+        }
+    }
+
+    /// Summary of the game so far, `won` tells how it ended.
+    pub fn result(&self, won: bool) -> GameResult {
+        GameResult {
+            seed: self.seed,
+            level: self.config.level.clone(),
+            won,
+            score: self.score,
+            time_millis: self.stopwatch.elapsed(Instant::now()).as_millis() as u64,
+            lives: self.lives,
+            bricks_remaining: self.remaining_breakable(),
+            bricks: self.snapshot().bricks,
+        }
+    }
+
+    /// Writes the result of the finished game if `--export-result` was given.
+    fn export_result(&self, won: bool) -> io::Result<()> {
+        match self.config.result_path {
+            Some(ref path) => self.result(won).save(path),
+            None => Ok(()),
+        }
+    }
+
+    /// The player pressing `key` and the action it triggers. Player one's keys
+    /// take precedence, player two can only move and launch.
+    fn key_action(&self, key: Option<Key>) -> (usize, Option<Action>) {
+        let key = match key {
+            Some(key) => key,
+            None => return (0, None),
+        };
+        if let Some(action) = self.config.key_bindings.action(key) {
+            return (0, Some(action));
+        }
+        let coop_action = self
+            .config
+            .coop_bindings
+            .as_ref()
+            .and_then(|bindings| bindings.action(key));
+        match coop_action {
+            Some(Action::Left) | Some(Action::Right) | Some(Action::Launch) => (1, coop_action),
+            _ => (0, None),
+        }
+    }
+
+    fn handle_debug_key(&mut self, key: Option<Key>) {
+        match key {
+            // Hidden debug controls
+            Some(Key::Char('[')) => self.set_time_scale(self.time_scale / 2f32),
+            Some(Key::Char(']')) => self.set_time_scale(self.time_scale * 2f32),
+            Some(Key::Char('p')) => {
+                self.autopaused = false;
+                self.toggle_pause();
+            }
+            Some(Key::Char('.')) => self.single_step = true,
+            _ => {}
+        }
+    }
+
+    /// Shows a notice instead of the game while the terminal is too small to
+    /// hold it, and repaints the game once it fits again. Returns true while
+    /// the terminal is too small.
+    fn check_terminal_size(&mut self) -> bool {
+        // The game fits if the last terminal cell shows its last position or
+        // one beyond it
+        let fits = match self.stdout.size() {
+            Some((width, height)) => {
+                match coords::from_terminal((width as u16, height as u16), 0) {
+                    Some(last) => last.0 >= self.width && last.1 >= self.height,
+                    None => false,
+                }
+            }
+            None => true,
+        };
+        if fits != self.too_small {
+            return self.too_small;
+        }
+
+        self.too_small = !fits;
+        if self.too_small {
+            self.stopwatch.stop(Instant::now());
+            self.stdout.clear_all();
+            let notice = format!("Terminal too small, needs {}x{}", self.width, self.height);
+            self.stdout.draw_text((1, 1), &color::Red, &notice);
+        } else {
+            self.redraw_scene();
+            if self.state == GameState::Playing && !self.paused {
+                self.stopwatch.start(Instant::now());
+            }
+        }
+        self.stdout.flush().unwrap();
+        self.too_small
+    }
+
+    fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        if self.paused {
+            self.stopwatch.stop(Instant::now());
+        } else {
+            self.stopwatch.start(Instant::now());
+        }
+    }
+
+    /// Shows the elapsed play time in the top right border, or the time left in
+    /// time attack mode, only rewriting it on change.
+    fn draw_play_time(&mut self) {
+        let time = match self.time_left {
+            Some(time_left) => time_left,
+            None => self.stopwatch.elapsed(Instant::now()),
+        };
+        let label = format!(" {} ", format_duration(time));
+        if label != self.time_label {
+            let pos = (
+                coords::right(self.width) - label.len(),
+                coords::top_border(),
+            );
+            let color: &dyn color::Color = match self.time_left {
+                Some(time_left) if time_left < Duration::from_secs(Self::get_hurry_secs()) => {
+                    &color::Red
+                }
+                _ => &color::Blue,
+            };
+            self.stdout.draw_text(pos, color, &label);
+            self.time_label = label;
+        }
+    }
+
+    /// Shows the smoothed frame time in the bottom border, only rewriting it on change.
+    fn draw_frame_time(&mut self) {
+        self.frame_timer.tick();
+        let label = format!(
+            "{}{:3} bricks ",
+            self.frame_timer.label(),
+            self.brick_count()
+        );
+        if label != self.frame_label {
+            let pos = (coords::left() + 1, coords::bottom_border(self.height));
+            self.stdout.draw_text(pos, &color::Blue, &label);
+            self.frame_label = label;
+        }
+    }
+
+    /// Shows the lives left in the bottom right border.
+    fn draw_lives(&mut self) {
+        let label = self.lives_label();
+        let x = coords::right(self.width) - display_width(&label);
+        self.stdout.draw_text(
+            (x, coords::bottom_border(self.height)),
+            &color::Blue,
+            &label,
+        );
+    }
+
+    /// The lives as a number, or with `--reserve-balls` as one ball each
+    /// stacked against the corner. The balls are padded to the most lives a
+    /// player can have, so lost ones are blanked when the label is redrawn.
+    fn lives_label(&self) -> String {
+        if !self.config.reserve_balls {
+            return format!(" lives {} ", self.lives);
+        }
+        let max = Self::get_max_lives().max(self.lives) as usize;
+        format!(
+            " {}{} ",
+            " ".repeat(max - self.lives as usize),
+            graphics::BALL_GRAPHIC.repeat(self.lives as usize)
+        )
+    }
+
+    /// Shows the number of balls in play left of the lives, only rewriting it
+    /// on change.
+    fn draw_ball_count(&mut self) {
+        let label = self.ball_count_label();
+        if label != self.balls_label {
+            let lives_width = display_width(&self.lives_label());
+            let x = coords::right(self.width) - lives_width - label.len();
+            self.stdout.draw_text(
+                (x, coords::bottom_border(self.height)),
+                &color::Blue,
+                &label,
+            );
+            self.balls_label = label;
+        }
+    }
+
+    fn ball_count_label(&self) -> String {
+        format!(" balls {:2} ", self.balls.len())
+    }
+
+    /// Shows the nudges left for this life left of the ball count, only
+    /// rewriting it on change. Nothing is shown with nudging turned off.
+    fn draw_nudges(&mut self) {
+        if self.config.nudges == 0 {
+            return;
+        }
+        let label = format!(" nudges {} ", self.nudges_left);
+        if label != self.nudges_label {
+            let right_width =
+                display_width(&self.lives_label()) + display_width(&self.ball_count_label());
+            let x = coords::right(self.width) - right_width - display_width(&label);
+            self.stdout.draw_text(
+                (x, coords::bottom_border(self.height)),
+                &color::Blue,
+                &label,
+            );
+            self.nudges_label = label;
+        }
+    }
+
+    /// Nudges the free balls towards the middle of `player`'s peddle, using up
+    /// one of the nudges of this life. Nothing happens without nudges left or
+    /// free balls to nudge.
+    fn nudge_balls(&mut self, player: usize) {
+        let free = self.balls.iter().any(|ball| ball.stuck_offset.is_none());
+        if self.nudges_left == 0 || !free {
+            return;
+        }
+        self.nudges_left -= 1;
+        let peddle = &self.peddles[player];
+        let center = peddle.x() as f32 + peddle.get_width() as f32 / 2f32;
+        for ball in &mut self.balls {
+            ball.nudge(center);
+        }
+        self.shake();
+    }
+
+    /// Share of the breakable bricks destroyed so far, from 0 to 1.
+    pub fn progress(&self) -> f32 {
+        if self.initial_breakable == 0 {
+            return 0f32;
+        }
+        let destroyed = self
+            .initial_breakable
+            .saturating_sub(self.remaining_breakable());
+        destroyed as f32 / self.initial_breakable as f32
+    }
+
+    /// Shows the progress bar in the middle of the top border with `--progress`,
+    /// only rewriting it on change.
+    fn draw_progress(&mut self) {
+        if !self.config.progress {
+            return;
+        }
+        let progress = self.progress();
+        let width = Self::get_progress_bar_width();
+        let filled = (progress * width as f32).floor() as Unit;
+        let label = format!(
+            " {}{} {:3}% ",
+            graphics::PROGRESS_FULL.repeat(filled),
+            graphics::PROGRESS_EMPTY.repeat(width - filled),
+            (progress * 100f32).floor() as u32
+        );
+        if label != self.progress_label {
+            let x = self.width / 2 - display_width(&label) / 2;
+            self.stdout
+                .draw_text((x, coords::top_border()), &color::Blue, &label);
+            self.progress_label = label;
+        }
+    }
+
+    fn get_progress_bar_width() -> Unit {
+        10
+    }
+
+    /// Repaints the borders along with the labels living in them.
+    fn repaint_borders(&mut self) {
+        self.draw_game_borders();
+        self.draw_time_scale();
+        self.draw_lives();
+        self.time_label.clear();
+        self.frame_label.clear();
+        self.balls_label.clear();
+        self.progress_label.clear();
+        self.charge_label.clear();
+        self.nudges_label.clear();
+    }
+
+    /// Shows the launch charge in the middle of the bottom border while the
+    /// launch key is held, only rewriting it on change.
+    fn draw_charge(&mut self) {
+        let label = match self.launch_charge {
+            Some(charge) => {
+                let width = Self::get_charge_meter_width();
+                let filled =
+                    (self.charge_share(charge.held_ticks()) * width as f32).round() as Unit;
+                format!(
+                    " charge {}{} ",
+                    graphics::PROGRESS_FULL.repeat(filled),
+                    graphics::PROGRESS_EMPTY.repeat(width - filled)
+                )
+            }
+            None => String::new(),
+        };
+        if label == self.charge_label {
+            return;
+        }
+        if label.is_empty() {
+            self.repaint_borders();
+            return;
+        }
+        let x = self.width / 2 - display_width(&label) / 2;
+        self.stdout.draw_text(
+            (x, coords::bottom_border(self.height)),
+            &color::Blue,
+            &label,
+        );
+        self.charge_label = label;
+    }
+
+    fn get_charge_meter_width() -> Unit {
+        5
+    }
+
+    /// Shows the remaining seconds in the center, erasing it once play starts.
+    fn draw_countdown(&mut self) {
+        let text = match self.state {
+            GameState::Countdown(ticks) => {
+                let ticks_per_second = (1000 / FRAME_MILLIS) as u32;
+                ((ticks + ticks_per_second - 1) / ticks_per_second).to_string()
+            }
+            GameState::Playing => " ".to_string(),
+        };
+        let pos = (self.width / 2, self.height / 2);
+        self.stdout.draw_text(pos, &color::Yellow, &text);
+    }
+
+    fn set_time_scale(&mut self, time_scale: f32) {
+        self.time_scale = time_scale
+            .max(Self::get_min_time_scale())
+            .min(Self::get_max_time_scale());
+        self.draw_time_scale();
+    }
+
+    /// Shows the time scale in the top border while it differs from 1.0.
+    fn draw_time_scale(&mut self) {
+        let label = format!(" x{:.3} ", self.time_scale);
+        let text = if (self.time_scale - 1f32).abs() > f32::EPSILON {
+            label
+        } else {
+            graphics::BORDER_HORIZONTAL.repeat(label.chars().count())
+        };
+        self.stdout.draw_text(
+            (coords::left() + 1, coords::top_border()),
+            &color::Blue,
+            &text,
+        );
+    }
+
+    /// Runs the start menu, returns false if the player chose to quit.
+    /// The attract mode starts after a few seconds without input.
+    fn start_screen(&mut self) -> bool {
+        let mut menu = Menu::new();
+        let idle = Duration::from_secs(Self::get_demo_idle_secs());
+        let mut last_input = Instant::now();
+        self.draw_menu(&menu);
+        loop {
+            let key = self.read_key();
+            if self.interrupted() {
+                return false;
+            }
+            match key {
+                Some(key) => {
+                    last_input = Instant::now();
+                    match menu.handle_key(key, &mut self.config) {
+                        Some(MenuAction::Start) => return true,
+                        Some(MenuAction::Quit) => return false,
+                        Some(MenuAction::Scores) => {
+                            self.scoreboard_screen();
+                            // Repaint the scene the scoreboard was drawn over
+                            self.reset_game();
+                            self.draw_menu(&menu);
+                        }
+                        None => self.draw_menu(&menu),
+                    }
+                }
+                None if last_input.elapsed() >= idle => {
+                    self.run_demo();
+                    self.draw_menu(&menu);
+                    last_input = Instant::now();
+                }
+                None => {}
+            }
+            thread::sleep(Duration::from_millis(FRAME_MILLIS));
+        }
+    }
+
+    fn draw_menu(&mut self, menu: &Menu) {
+        let mut lines = Menu::header_lines(&self.config.title);
+        lines.extend(menu.lines(&self.config));
+        lines.extend(Menu::controls_lines(&self.config.key_bindings));
+        lines.push(graphics::MENU_FOOTER.to_string());
+        self.draw_dialog(&lines.join("\n"), Box::new(color::Blue));
+    }
+
+    /// Attract mode: the peddle plays by itself until any key is pressed.
+    fn run_demo(&mut self) {
+        self.reset_game();
+        while self.read_key().is_none() && !self.interrupted() {
+            match self.autoplay_tick() {
+                TickResult::Running => self.present_frame(),
+                _ => self.reset_game(),
+            }
+        }
+        self.reset_game();
+    }
+
+    /// Advances one frame with the peddle steered by the demo AI.
+    fn autoplay_tick(&mut self) -> TickResult {
+        // Go for the ball closest to dropping out
+        let lowest = self
+            .balls
+            .iter()
+            .max_by(|a, b| a.game_pos.1.partial_cmp(&b.game_pos.1).unwrap())
+            .map_or(self.peddles[0].x() as f32, |ball| ball.game_pos.0);
+        let width = self.width;
+        for peddle in &mut self.peddles {
+            let dx = peddle.steer_towards(lowest);
+            peddle.move_by(dx, width);
+        }
+        self.tick()
+    }
+
+    /// Plays up to `frames` frames with the demo AI as fast as possible,
+    /// without rendering or waiting. Stops early once the game ends.
+    pub fn simulate(&mut self, frames: u64) -> (u64, TickResult) {
+        self.reset_game();
+        for frame in 0..frames {
+            let result = self.autoplay_tick();
+            if result != TickResult::Running {
+                return (frame + 1, result);
+            }
+        }
+        (frames, TickResult::Running)
+    }
+
+    fn game_over_screen(&mut self) -> bool {
+        if self.submit_score() {
+            self.save_records();
+        }
+        self.stats_screen();
+        let time = format!(
+            "time {}",
+            format_duration(self.stopwatch.elapsed(Instant::now()))
+        );
+        let dialog = insert_dialog_line(graphics::GAME_OVER, &time);
+        self.replay_dialog(&dialog, Box::new(color::Red))
+    }
+
+    fn game_won_screen(&mut self) -> bool {
+        let time = self.stopwatch.elapsed(Instant::now());
+        let new_best = self.records.submit_time(time);
+        let new_score = self.submit_score();
+        if new_best || new_score {
+            self.save_records();
+        }
+        self.stats_screen();
+        let best = self.records.best_time.unwrap_or(time);
+        let dialog = insert_dialog_line(
+            &insert_dialog_line(
+                graphics::GAME_WIN,
+                &format!("time {}", format_duration(time)),
+            ),
+            &format!("best {}", format_duration(best)),
+        );
+        self.replay_dialog(&dialog, Box::new(color::Green))
+    }
+
+    /// Shows the summary of the finished game until a key is pressed.
+    fn stats_screen(&mut self) {
+        let time = self.stopwatch.elapsed(Instant::now());
+        let mut lines: Vec<String> = graphics::STATS.lines().map(String::from).collect();
+        let inner_width = display_width(&lines[0]) - 2;
+        // Rows go between the title and the blank line above the continue hint
+        for (index, (label, value)) in self
+            .stats()
+            .summary(self.score, time)
+            .into_iter()
+            .enumerate()
+        {
+            let text = format!("{:<9}{:>9}", label, value);
+            lines.insert(2 + index, dialog_line(inner_width, &text));
+        }
+        let panel = lines.join("\n");
+        self.draw_dialog(&panel, Box::new(color::Blue));
+        loop {
+            if self.read_key().is_some() || self.interrupted() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(FRAME_MILLIS));
+        }
+        self.clear_dialog(&panel);
+    }
+
+    /// Adds the final score to the scoreboard, returns true if it made it.
+    fn submit_score(&mut self) -> bool {
+        self.records.submit_score(self.score, unix_now()).is_some()
+    }
+
+    fn save_records(&self) {
+        if let Some(ref path) = self.config.records_path {
+            // Failing to persist the records should not end the game
+            let _ = self.records.save(path);
+        }
+    }
+
+    /// Lists the high scores until Escape is pressed.
+    fn scoreboard_screen(&mut self) {
+        self.draw_dialog(&self.scoreboard_lines().join("\n"), Box::new(color::Blue));
+        loop {
+            let key = self.read_key();
+            if self.interrupted() || key == Some(Key::Esc) {
+                return;
+            }
+            thread::sleep(Duration::from_millis(FRAME_MILLIS));
+        }
+    }
+
+    fn scoreboard_lines(&self) -> Vec<String> {
+        let mut lines = vec![graphics::SCOREBOARD_HEADER.to_string()];
+        if self.records.scores.is_empty() {
+            lines.push(menu_line("    No scores yet"));
+        }
+        for (rank, entry) in self.records.scores.iter().enumerate() {
+            lines.push(menu_line(&format!(
+                "  {:>2}.  {:>6}   {}",
+                rank + 1,
+                entry.score,
+                format_date(entry.date)
+            )));
+        }
+        lines.push(menu_line(""));
+        lines.push(menu_line(&format!("       {:<6}{}", "esc", "back")));
+        lines.push(graphics::MENU_FOOTER.to_string());
+        lines
+    }
+
+    /// Asks whether to play again, answered with the reset or quit key.
+    fn replay_dialog(&mut self, graphics: &str, color: Box<dyn color::Color>) -> bool {
+        let bindings = &self.config.key_bindings;
+        let yes = bindings.key_for(Action::Reset).unwrap_or('r');
+        let no = bindings.key_for(Action::Quit).unwrap_or('q');
+        self.yes_no_dialog(graphics, color, yes, no)
+    }
+
+    fn yes_no_dialog(
+        &mut self,
+        graphics: &str,
+        color: Box<dyn color::Color>,
+        yes: char,
+        no: char,
+    ) -> bool {
+        self.draw_dialog(graphics, color);
+        self.wait_for_answer(yes, no)
+    }
+
+    fn draw_dialog(&mut self, graphics: &str, color: Box<dyn color::Color>) {
+        for (index, l) in graphics.lines().enumerate() {
+            let pos = (
+                self.width / 2 - display_width(l) / 2,
+                self.height / 2 + index,
+            );
+            self.stdout.draw_text(pos, color.as_ref(), l);
+        }
+        self.stdout.flush().unwrap();
+    }
+
+    /// Blanks a dialog previously drawn with `draw_dialog`.
+    fn clear_dialog(&mut self, graphics: &str) {
+        for (index, l) in graphics.lines().enumerate() {
+            let pos = (
+                self.width / 2 - display_width(l) / 2,
+                self.height / 2 + index,
+            );
+            self.stdout
+                .draw_text(pos, &color::Reset, &" ".repeat(display_width(l)));
+            for x in pos.0..pos.0 + display_width(l) {
+                self.restore_bricks_at((x, pos.1));
+            }
+        }
+    }
+
+    /// Blocks until `yes` or `no` is pressed.
+    fn wait_for_answer(&mut self, yes: char, no: char) -> bool {
+        loop {
+            let key = self.read_key();
+            if self.interrupted() {
+                return false;
+            }
+            match key {
+                Some(Key::Char(key)) if key == yes => return true,
+                Some(Key::Char(key)) if key == no => return false,
+                _ => {}
+            }
+            thread::sleep(Duration::from_millis(FRAME_MILLIS));
+        }
+    }
+
+    fn get_demo_idle_secs() -> u64 {
+        5
+    }
+
+    /// Length of the countdown before each round, three seconds.
+    fn get_countdown_ticks() -> u32 {
+        3 * (1000 / FRAME_MILLIS) as u32
+    }
+
+    /// The time attack timer turns red for the last ten seconds.
+    fn get_hurry_secs() -> u64 {
+        10
+    }
+
+    fn get_start_lives() -> u32 {
+        1
+    }
+
+    fn get_max_lives() -> u32 {
+        5
+    }
+
+    /// Frames a screen shake lasts.
+    fn get_shake_ticks() -> u32 {
+        4
+    }
+
+    /// Columns the playfield is shifted by with `ticks_left` frames of shaking
+    /// to go, moving back a column every other frame so that it is only
+    /// repainted every other frame.
+    fn get_shake_offset(ticks_left: u32) -> Unit {
+        (ticks_left / 2 + ticks_left % 2) as Unit
+    }
+
+    /// How long a toast stays on screen, one and a half seconds.
+    fn get_toast_ticks() -> u32 {
+        3 * (1000 / FRAME_MILLIS) as u32 / 2
+    }
+
+    /// Downward acceleration of the ball per frame in gravity mode.
+    fn get_gravity() -> f32 {
+        let acceleration_per_second = 0.25;
+        acceleration_per_second * FRAME_MILLIS as f32 / 1000f32
+    }
+
+    /// One in this many destroyed bricks drops a power-up.
+    fn get_power_up_drop_odds() -> u32 {
+        8
+    }
+
+    /// How long the laser stays on, ten seconds.
+    fn get_laser_ticks() -> u32 {
+        10 * (1000 / FRAME_MILLIS) as u32
+    }
+
+    /// Frames between two laser shots.
+    fn get_laser_cooldown_ticks() -> u32 {
+        8
+    }
+
+    fn get_sticky_catches() -> u32 {
+        3
+    }
+
+    /// Extra points for every brick taken out by an explosion.
+    fn get_chain_bonus() -> u32 {
+        5
+    }
+
+    /// The sticky peddle wears off after twenty seconds.
+    fn get_sticky_ticks() -> u32 {
+        20 * (1000 / FRAME_MILLIS) as u32
+    }
+
+    /// Speed of the ball relative to normal with the easy finish.
+    fn get_easy_finish_slowdown() -> f32 {
+        0.5
+    }
+
+    fn get_speed_step() -> f32 {
+        0.25
+    }
+
+    fn get_min_time_scale() -> f32 {
+        0.125
+    }
+
+    fn get_max_time_scale() -> f32 {
+        4.0
+    }
+
+    fn draw_game_borders(&mut self) {
+        let horizontal_border = graphics::BORDER_HORIZONTAL.repeat(coords::inner_width(self.width));
+        let left = coords::left_border();
+        let right = coords::right_border(self.width);
+
+        let border_color = self.config.theme.border_color(self.frame);
+        self.stdout.set_fg(border_color.as_ref());
+        write!(
+            self.stdout,
+            "{}{}{}{}",
+            self.stdout.goto((left, coords::top_border())),
+            graphics::TOP_LEFT_BORDER,
+            horizontal_border,
+            graphics::TOP_RIGHT_BORDER
+        )
+        .unwrap();
+        for y in coords::top()..=coords::bottom(self.height) {
+            write!(
+                self.stdout,
+                "{}{}",
+                self.stdout.goto((left, y)),
+                graphics::BORDER_VERTICAL
+            )
+            .unwrap();
+            write!(
+                self.stdout,
+                "{}{}",
+                self.stdout.goto((right, y)),
+                graphics::BORDER_VERTICAL
+            )
+            .unwrap();
+        }
+        write!(
+            self.stdout,
+            "{}{}{}{}",
+            self.stdout.goto((left, coords::bottom_border(self.height))),
+            graphics::BOTTOM_LEFT_BORDER,
+            horizontal_border,
+            graphics::BOTTOM_RIGHT_BORDER
+        )
+        .unwrap();
+        for pos in self.config.field.wall_cells((self.width, self.height)) {
+            write!(
+                self.stdout,
+                "{}{}",
+                self.stdout.goto(pos),
+                graphics::BORDER_FILL
+            )
+            .unwrap();
+        }
+    }
+
+    fn generate_cell_grid(game_size: (Unit, Unit), config: &GameConfig, seed: u64) -> Vec<Cell> {
+        let cell_width = config.brick_width;
+        let cell_margin = config.brick_gap;
+        let (num_cells_horizontally, num_cells_vertically) = config
+            .grid_dimensions(game_size)
+            .expect("brick grid is validated before the game starts");
+
+        let mut rng = StdRng::from_seed(&[seed as usize][..]);
+
+        // Center the grid between the side borders, an odd spare column goes
+        // to the right margin
+        let span_x =
+            (num_cells_horizontally * (cell_width + cell_margin)).saturating_sub(cell_margin);
+        let grid_left =
+            coords::left() + coords::inner_width(game_size.0).saturating_sub(span_x) / 2;
+
+        let vec_capacity = num_cells_vertically * num_cells_horizontally;
+        let mut cells = Vec::with_capacity(vec_capacity);
+        for cy in 0..num_cells_vertically {
+            for cx in 0..num_cells_horizontally {
+                // Always draw from the weights so the kinds stay the same
+                let picked = config.color_weights.pick(&mut rng);
+                let brick_color = config.brick_colors.row_color(cy).unwrap_or(picked);
+                let kind = match config.level {
+                    Some(ref level) => match level.get(cx, cy) {
+                        Some(kind) => kind,
+                        None => continue,
                     },
-                });
+                    None => match (config.special_bricks, rng.gen_range(0, 20)) {
+                        (true, 0) => BrickKind::Solid,
+                        (true, 1..=3) => BrickKind::Tough,
+                        (true, 4) => BrickKind::Explosive,
+                        (true, 5) => BrickKind::MultiBall,
+                        (_, 6..=7) if config.frozen_bricks => BrickKind::Frozen,
+                        _ => BrickKind::Normal,
+                    },
+                };
+
+                let xpos = grid_left + cx * (cell_width + cell_margin);
+                let ypos = GameConfig::get_grid_top() + cy * (1 + cell_margin);
+                let ypos = config.peddle_side.mirror_row(ypos, game_size.1);
+                cells.push(Cell::colored((xpos, ypos), cell_width, kind, brick_color));
+            }
+        }
+
+        // Make sure there is always something to win the game with
+        if !cells.is_empty() && !cells.iter().any(|cell| cell.is_breakable()) {
+            let first = cells.remove(0);
+            let normal = Cell::colored(first.pos, first.width, BrickKind::Normal, NamedColor::Red);
+            cells.insert(0, normal);
+        }
+        let damage_colors = Rc::new(config.damage_colors.clone());
+        for cell in &mut cells {
+            cell.damage_colors = Some(Rc::clone(&damage_colors));
+        }
+        cells
+    }
+}
+
+/// Shrinks the requested game size to what the terminal can show, anything
+/// past its edges would garble the screen.
+fn fit_to_terminal(requested: (Unit, Unit), terminal: (Unit, Unit)) -> (Unit, Unit) {
+    (requested.0.min(terminal.0), requested.1.min(terminal.1))
+}
+
+/// Explains that a game of `size` is smaller than `min_size` and how to get a
+/// game that works, `None` if the size is fine.
+fn too_small_message(size: (Unit, Unit), min_size: (Unit, Unit)) -> Option<String> {
+    if size.0 >= min_size.0 && size.1 >= min_size.1 {
+        return None;
+    }
+    Some(format!(
+        "A game of {}x{} is too small, it needs at least {}x{}.\n\
+         Resize the terminal to at least {} columns and {} rows \
+         or start with --width {} --height {}",
+        size.0,
+        size.1,
+        min_size.0,
+        min_size.1,
+        min_size.0,
+        min_size.1,
+        size.0.max(min_size.0),
+        size.1.max(min_size.1)
+    ))
+}
+
+/// Checks that `--record` comes with `--bench` and `--verify` without it,
+/// instead of ignoring either.
+fn check_replay_options(bench: bool, record: bool, verify: bool) -> Result<(), GameError> {
+    if record && !bench {
+        Err(GameConfig::invalid(
+            "replay",
+            "--record only works with --bench".to_string(),
+        ))
+    } else if verify && bench {
+        Err(GameConfig::invalid(
+            "replay",
+            "--verify replays a recording and cannot be combined with --bench".to_string(),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Why the game could not be started. `main` prints it and exits with its
+/// exit code.
+#[derive(Debug)]
+enum GameError {
+    /// The game is smaller than the configured bricks and peddle need
+    TooSmall {
+        size: (Unit, Unit),
+        min_size: (Unit, Unit),
+    },
+    /// The terminal could not be asked for its size to scale the game to
+    TerminalSizeUnavailable(io::Error),
+    /// Reading or opening something failed, with what was attempted
+    Io(String, io::Error),
+    /// Options that do not make a playable game, with the explanation
+    InvalidConfig(String),
+    /// Replaying a recording with `--verify` ended differently, with how
+    ReplayDiverged(String),
+}
+
+impl GameError {
+    /// 1 keeps the code the game exited with when too small, 2 is what the
+    /// argument parser exits with for bad arguments.
+    pub fn exit_code(&self) -> u8 {
+        match *self {
+            GameError::TooSmall { .. } => 1,
+            GameError::InvalidConfig(_) => 2,
+            GameError::Io(..) => 3,
+            GameError::TerminalSizeUnavailable(_) => 4,
+            GameError::ReplayDiverged(_) => 5,
+        }
+    }
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GameError::TooSmall { size, min_size } => {
+                let message = too_small_message(size, min_size).unwrap_or_default();
+                write!(f, "{}", message)
+            }
+            GameError::TerminalSizeUnavailable(ref e) => {
+                write!(f, "Failed to get terminal size with error: {}", e)
+            }
+            GameError::Io(ref what, ref e) => write!(f, "Failed to {} with error: {}", what, e),
+            GameError::InvalidConfig(ref message) => write!(f, "{}", message),
+            GameError::ReplayDiverged(ref differences) => {
+                write!(
+                    f,
+                    "The replay diverged from the recording: it {}",
+                    differences
+                )
+            }
+        }
+    }
+}
+
+fn init(width: Unit, height: Unit, config: GameConfig) -> Result<(), GameError> {
+    let stdout = stdout();
+    let terminal =
+        Terminal::new(stdout.lock(), StdoutTty, !config.no_color).report_focus(config.autopause);
+    let input = InputThread::stdin();
+    install_sigint_handler();
+    let mut game = BreakoutGame::new(input, terminal, width, height, config);
+    game.run()
+}
+
+/// Runs the simulation headless for `frames` frames and prints the timing
+/// and the final state, which is written to `record_path` if given.
+fn bench(
+    width: Unit,
+    height: Unit,
+    mut config: GameConfig,
+    frames: u64,
+    record_path: Option<PathBuf>,
+) -> Result<(), GameError> {
+    // A fixed seed keeps runs comparable
+    config.seed = Some(config.seed.unwrap_or(0));
+    let mut game = BreakoutGame::new(io::empty(), NullScreen, width, height, config);
+    let start = Instant::now();
+    let (frames_run, result) = game.simulate(frames);
+    let elapsed = start.elapsed();
+
+    let millis = elapsed.as_secs_f64() * 1000f64;
+    println!("frames      {}", frames_run);
+    println!(
+        "time        {:.3} ms ({:.3} us/frame)",
+        millis,
+        millis * 1000f64 / frames_run.max(1) as f64
+    );
+    println!("result      {:?}", result);
+    println!("bricks      {}", game.brick_count());
+    println!("score       {}", game.score);
+    if let Some(path) = record_path {
+        let recording = game.recording(frames_run);
+        recording
+            .save(&path)
+            .map_err(|e| GameError::Io("write the recording".to_string(), e))?;
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match start() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
+
+/// Parses the options and plays or benchmarks the game they describe.
+fn start() -> Result<(), GameError> {
+    // Store default game size
+    let mut width = Cell::get_default_width() * 13;
+    let mut height = 30;
+
+    let mut auto_scale_to_terminal = false;
+    let mut level_from_stdin = false;
+    let mut bench_frames: Option<u64> = None;
+    let mut record_path: Option<String> = None;
+    let mut verify_path: Option<String> = None;
+    let mut invert_controls = false;
+    let mut load_path: Option<String> = None;
+    let mut log_path: Option<String> = None;
+    let mut result_path: Option<String> = None;
+    let mut config = GameConfig::default();
+
+    {
+        // this block limits scope of borrows by ap.refer() method
+
+        let mut ap = ArgumentParser::new();
+        ap.set_description("A simple breakout clone built with rust, playable in the terminal");
+
+        ap.refer(&mut width).add_option(
+            &["-w", "--width"],
+            Store,
+            "Preferable game width of at least 32,
+                 gets scaled according to the in-game cell width",
+        );
+
+        ap.refer(&mut height).add_option(
+            &["-h", "--height"],
+            Store,
+            "Preferable game height of at least 20",
+        );
+
+        ap.refer(&mut auto_scale_to_terminal).add_option(
+            &["-f", "--fill"],
+            StoreTrue,
+            "Fill game to current terminal size",
+        );
+
+        ap.refer(&mut config.debug).add_option(
+            &["--debug"],
+            StoreTrue,
+            "Show frame time and FPS overlay",
+        );
+
+        ap.refer(&mut config.gravity).add_option(
+            &["--gravity"],
+            StoreTrue,
+            "Harder mode where the ball accelerates downwards",
+        );
+
+        ap.refer(&mut config.difficulty).add_option(
+            &["--difficulty"],
+            Store,
+            "Ball speed: easy, normal or hard",
+        );
+
+        ap.refer(&mut config.start_gap).add_option(
+            &["--start-gap"],
+            StoreOption,
+            "Rows between the peddle and the starting ball. Defaults to 10 on easy, \
+             8 on normal and 6 on hard",
+        );
+
+        ap.refer(&mut config.seed).add_option(
+            &["--seed"],
+            StoreOption,
+            "Seed for the brick layout",
+        );
+
+        ap.refer(&mut config.trail).add_option(
+            &["--trail"],
+            Store,
+            "Length of the trail behind the ball, defaults to 0 which turns it off",
+        );
+
+        ap.refer(&mut config.no_color).add_option(
+            &["--no-color"],
+            StoreTrue,
+            "Draw without colors, for monochrome terminals",
+        );
+
+        ap.refer(&mut config.reserve_balls).add_option(
+            &["--reserve-balls"],
+            StoreTrue,
+            "Show the lives as a row of balls in the bottom border instead of a number",
+        );
+
+        ap.refer(&mut config.autopause).add_option(
+            &["--autopause"],
+            StoreTrue,
+            "Pause while the terminal is out of focus, for terminals reporting focus changes",
+        );
+
+        ap.refer(&mut config.skip_intro).add_option(
+            &["--skip-intro"],
+            StoreTrue,
+            "Start playing right away, without the start screen and countdown",
+        );
+
+        ap.refer(&mut config.title).add_option(
+            &["--title"],
+            Store,
+            "Heading of the start screen, at most 26 columns wide",
+        );
+
+        ap.refer(&mut level_from_stdin).add_option(
+            &["--level-stdin"],
+            StoreTrue,
+            "Read the brick layout from standard input",
+        );
+
+        ap.refer(&mut config.assist).add_option(
+            &["--assist"],
+            StoreTrue,
+            "Aim assist, curves the ball towards the nearest brick",
+        );
+
+        ap.refer(&mut config.assist_strength).add_option(
+            &["--assist-strength"],
+            Store,
+            "Strength of the aim assist, defaults to 0.05",
+        );
+
+        ap.refer(&mut config.spin_decay).add_option(
+            &["--spin-decay"],
+            Store,
+            "Share of the ball's sideways spin lost per second, between 0 and 1. \
+             Steadies long rallies, defaults to 0 which keeps the spin",
+        );
+
+        ap.refer(&mut config.safety_net_secs).add_option(
+            &["--safety-net-secs"],
+            Store,
+            "Seconds a safety net power-up lasts, 0 disables it. Defaults to 15",
+        );
+
+        ap.refer(&mut config.mercy_secs).add_option(
+            &["--mercy-secs"],
+            Store,
+            "Seconds after launching the ball that replaces a lost life in which lost \
+             balls are launched again from the peddle, which blinks meanwhile. Defaults \
+             to 0, which turns it off",
+        );
+
+        ap.refer(&mut config.fireball_combo).add_option(
+            &["--fireball"],
+            Store,
+            "Turn the balls into fireballs burning through bricks for three seconds \
+             once a combo reaches this many bricks. Defaults to 0, which turns it off",
+        );
+
+        ap.refer(&mut config.nudges).add_option(
+            &["--nudges"],
+            Store,
+            "Times per life x nudges the ball towards the peddle. Defaults to 0, \
+             which turns nudging off",
+        );
+
+        ap.refer(&mut config.ball_size).add_option(
+            &["--ball-size"],
+            Store,
+            "Side length of the ball in cells, defaults to 1",
+        );
+
+        ap.refer(&mut bench_frames)
+            .add_option(
+                &["--bench"],
+                StoreOption,
+                "Simulate FRAMES frames without drawing and print the timing",
+            )
+            .metavar("FRAMES");
+
+        ap.refer(&mut record_path).add_option(
+            &["--record"],
+            StoreOption,
+            "With --bench, write the seed, size, options and outcome of the \
+             simulation to the given file for --verify",
+        );
+
+        ap.refer(&mut verify_path).add_option(
+            &["--verify"],
+            StoreOption,
+            "Replay a simulation written with --record, given the same options, \
+             and exit with 5 if the score or the bricks left differ. \
+             Cannot be combined with --bench",
+        );
+
+        ap.refer(&mut config.color_weights).add_option(
+            &["--color-weights"],
+            Store,
+            "Relative odds of brick colors, e.g. red=3,blue=1. \
+             Colors: red, green, blue, magenta, cyan and yellow",
+        );
+
+        ap.refer(&mut config.brick_colors).add_option(
+            &["--colors"],
+            Store,
+            "Brick coloring: random, or rows for one color per row from the top, \
+             optionally with the colors like rows=red,blue. Pair with --brick-points \
+             to make rows worth different points",
+        );
+
+        ap.refer(&mut config.brick_points).add_option(
+            &["--brick-points"],
+            Store,
+            "Points per destroyed brick by color or kind, e.g. red=20,tough=30. \
+             Kinds: normal, tough, explosive, multiball and frozen",
+        );
+
+        ap.refer(&mut config.ball_color).add_option(
+            &["--ball-color"],
+            Store,
+            "Color of the ball, one of the brick colors. Defaults to red",
+        );
+
+        ap.refer(&mut config.peddle_color).add_option(
+            &["--peddle-color"],
+            Store,
+            "Color of the peddle, one of the brick colors. Defaults to red",
+        );
+
+        ap.refer(&mut config.peddle_width).add_option(
+            &["--peddle-width"],
+            Store,
+            "Columns the peddle spans, at least 2. Defaults to 12",
+        );
+
+        ap.refer(&mut config.max_peddle_step).add_option(
+            &["--max-peddle-step"],
+            Store,
+            "Most columns the peddle moves per frame when keys pile up, defaults to 6",
+        );
+
+        ap.refer(&mut config.charge_launch).add_option(
+            &["--charge-launch"],
+            StoreTrue,
+            "Hold the launch key to charge a held ball, launching it up to 1.5 times \
+             as fast once the key is let go",
+        );
+
+        ap.refer(&mut config.ball_collisions).add_option(
+            &["--ball-collisions"],
+            StoreTrue,
+            "Let the balls of a multi-ball bounce off each other instead of passing through",
+        );
+
+        ap.refer(&mut config.theme).add_option(
+            &["--theme"],
+            Store,
+            "Border style: classic or rainbow",
+        );
+
+        ap.refer(&mut config.damage_colors).add_option(
+            &["--damage-colors"],
+            Store,
+            "Colors of damaged bricks by hits left, starting at one hit left, or none to \
+             keep the brick colors. Defaults to red,yellow,green",
+        );
+
+        ap.refer(&mut config.special_bricks).add_option(
+            &["--special-bricks"],
+            StoreTrue,
+            "Mix tough, solid, explosive and multiball bricks into random layouts",
+        );
+
+        ap.refer(&mut config.frozen_bricks).add_option(
+            &["--frozen-bricks"],
+            StoreTrue,
+            "Mix frozen bricks into random layouts, they need a second hit soon after \
+             the first",
+        );
+
+        ap.refer(&mut config.collision_padding.side).add_option(
+            &["--side-padding"],
+            Store,
+            "Width of the brick edges counting as side hits, defaults to 0.3",
+        );
+
+        ap.refer(&mut config.collision_padding.face).add_option(
+            &["--face-padding"],
+            Store,
+            "Part of a brick counting as its top face, defaults to 0.5",
+        );
+
+        ap.refer(&mut config.speed_limits.min).add_option(
+            &["--min-speed"],
+            Store,
+            "Slowest ball speed the - key goes down to, as a multiple of the regular \
+             speed. Defaults to 0.5",
+        );
+
+        ap.refer(&mut config.speed_limits.max).add_option(
+            &["--max-speed"],
+            Store,
+            "Fastest ball speed the + key goes up to, defaults to 2",
+        );
+
+        ap.refer(&mut config.walls).add_option(
+            &["--walls"],
+            Store,
+            "Sides bouncing the ball back, e.g. left,right,top,bottom or none. \
+             A ball reaching any other side is lost. Defaults to left,right,top",
+        );
+
+        ap.refer(&mut config.field).add_option(
+            &["--field"],
+            Store,
+            "Shape of the playfield: rectangle or funnel, which narrows towards the peddle",
+        );
+
+        ap.refer(&mut config.peddle_side).add_option(
+            &["--peddle-side"],
+            Store,
+            "Edge the peddle guards: bottom or top. The top flips the field, \
+             the bricks sit near the bottom and the ball is lost through the top, \
+             --walls trades its top and bottom along with it. Defaults to bottom",
+        );
+
+        ap.refer(&mut config.grace).add_option(
+            &["--grace"],
+            StoreTrue,
+            "Still bounce a ball that slipped one row below the peddle if the peddle is under it",
+        );
+
+        ap.refer(&mut load_path).add_option(
+            &["--load"],
+            StoreOption,
+            "Resume a game saved with ctrl-s from the given file, saving goes back to it. \
+             Games are saved to ~/.rbreakout_save otherwise",
+        );
+
+        ap.refer(&mut log_path).add_option(
+            &["--log"],
+            StoreOption,
+            "Append the ball, peddle and brick count of every frame to the given file",
+        );
+
+        ap.refer(&mut result_path).add_option(
+            &["--export-result"],
+            StoreOption,
+            "Write the seed, level, score, time, lives and remaining bricks of every \
+             finished game to the given file as JSON, ending the game if it cannot be \
+             written",
+        );
+
+        ap.refer(&mut config.practice).add_option(
+            &["--practice"],
+            StoreTrue,
+            "Practice mode, the game never ends and g puts a new ball on the peddle",
+        );
+
+        ap.refer(&mut config.extra_life_every).add_option(
+            &["--extra-life-every"],
+            Store,
+            "Points between extra lives, 0 disables them. Defaults to 500",
+        );
+
+        ap.refer(&mut config.time_attack_secs).add_option(
+            &["--time-attack"],
+            Store,
+            "Seconds to clear the field in, the game is over once they run out",
+        );
+
+        ap.refer(&mut config.descend_secs).add_option(
+            &["--descend"],
+            Store,
+            "Move all bricks down a row every given number of seconds, \
+             the game is over once they reach the peddle",
+        );
+
+        ap.refer(&mut config.key_bindings).add_option(
+            &["--layout"],
+            Store,
+            "Control layout: vim, wasd or arrows. Defaults to both vim and wasd keys",
+        );
+
+        ap.refer(&mut config.coop_bindings).add_option(
+            &["--coop"],
+            StoreOption,
+            "Add a second peddle for a second player using the given layout, \
+             e.g. --layout vim --coop arrows",
+        );
+
+        ap.refer(&mut invert_controls).add_option(
+            &["--invert-controls"],
+            StoreTrue,
+            "Swap the keys moving the peddle left and right",
+        );
+
+        ap.refer(&mut config.easy_finish).add_option(
+            &["--easy-finish"],
+            StoreTrue,
+            "Slow the ball down to half speed once few bricks are left",
+        );
+
+        ap.refer(&mut config.easy_finish_bricks).add_option(
+            &["--easy-finish-bricks"],
+            Store,
+            "Number of bricks left at which --easy-finish slows the ball. Defaults to 1",
+        );
+
+        ap.refer(&mut config.effects).add_option(
+            &["--effects"],
+            StoreTrue,
+            "Debris flying off destroyed bricks, the screen shaking on big hits and the \
+             peddle dipping when the ball bounces off it",
+        );
+
+        ap.refer(&mut config.predict).add_option(
+            &["--predict"],
+            StoreTrue,
+            "Show the path the ball takes off the walls",
+        );
+
+        ap.refer(&mut config.guides).add_option(
+            &["--guides"],
+            StoreTrue,
+            "Mark the span the peddle catches the ball in with a faint line \
+             under it",
+        );
+
+        ap.refer(&mut config.progress).add_option(
+            &["--progress"],
+            StoreTrue,
+            "Show the share of destroyed bricks as a bar in the top border",
+        );
+
+        ap.refer(&mut config.boss).add_option(
+            &["--boss"],
+            StoreTrue,
+            "Add a boss brick that takes many hits, destroying it clears the level",
+        );
+
+        ap.refer(&mut config.rows)
+            .add_option(&["--rows"], StoreOption, "Number of brick rows");
+
+        ap.refer(&mut config.cols)
+            .add_option(&["--cols"], StoreOption, "Number of brick columns");
+
+        ap.refer(&mut config.brick_width).add_option(
+            &["--brick-width"],
+            Store,
+            "Width of a single brick",
+        );
+
+        ap.refer(&mut config.brick_gap).add_option(
+            &["--brick-gap"],
+            Store,
+            "Empty cells between neighbouring bricks",
+        );
+
+        ap.refer(&mut config.grid_share).add_option(
+            &["--grid-share"],
+            StoreOption,
+            "Share of the game height the bricks may take up, between 0 and 1. \
+             Defaults to a third unless --rows is given",
+        );
+
+        ap.parse_args_or_exit();
+    }
+
+    if config.practice {
+        config.key_bindings.bind(Action::Respawn, Key::Char('g'));
+    }
+    if let Some(path) = log_path {
+        match std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+        {
+            Ok(file) => config.log_file = Some(file),
+            Err(e) => return Err(GameError::Io("open the log file".to_string(), e)),
+        }
+    }
+    config.result_path = result_path.map(PathBuf::from);
+    let mut saved_size = None;
+    if let Some(path) = load_path {
+        let saved = SavedGame::load(Path::new(&path))
+            .map_err(|e| GameError::Io("load the saved game".to_string(), e))?;
+        width = saved.size.0;
+        height = saved.size.1;
+        saved_size = Some(saved.size);
+        config.saved_game = Some(saved.game);
+        config.save_path = PathBuf::from(path);
+    }
+    check_replay_options(
+        bench_frames.is_some(),
+        record_path.is_some(),
+        verify_path.is_some(),
+    )?;
+    let recording = match verify_path {
+        Some(path) => match Recording::load(Path::new(&path)) {
+            Ok(recording) => {
+                width = recording.width;
+                height = recording.height;
+                Some(recording)
+            }
+            Err(e) => return Err(GameError::Io("read the recording".to_string(), e)),
+        },
+        None => None,
+    };
+    // Replays run headless like benchmarks
+    let headless = bench_frames.is_some() || recording.is_some();
+    // Only played games count towards the personal records
+    if !headless {
+        config.records_path = Some(Records::get_default_path());
+    }
+    if invert_controls {
+        config.key_bindings.invert();
+    }
+    if let Some(ref coop_bindings) = config.coop_bindings {
+        if config.key_bindings.conflicts_with(coop_bindings) {
+            return Err(GameError::InvalidConfig(
+                "The co-op layout moves with keys the first player already uses".to_string(),
+            ));
+        }
+    }
+
+    if level_from_stdin {
+        let mut text = String::new();
+        if let Err(e) = io::stdin().read_to_string(&mut text) {
+            return Err(GameError::Io(
+                "read the level from standard input".to_string(),
+                e,
+            ));
+        }
+        // An empty pipe falls back to a random layout
+        if !text.trim().is_empty() {
+            match Level::parse(&text) {
+                Ok(level) => config.level = Some(level),
+                Err(e) => return Err(GameError::InvalidConfig(format!("Invalid level: {}", e))),
+            }
+        }
+        // Benchmarks read no keys and may run without a terminal
+        let reattached = if headless {
+            Ok(())
+        } else {
+            reattach_stdin_to_tty()
+        };
+        if let Err(e) = reattached {
+            return Err(GameError::Io("open the terminal for input".to_string(), e));
+        }
+    }
+
+    if auto_scale_to_terminal {
+        let size = termion::terminal_size().map_err(GameError::TerminalSizeUnavailable)?;
+        width = size.0 as usize;
+        height = size.1 as usize;
+    } else if !headless {
+        if let Ok((columns, rows)) = termion::terminal_size() {
+            let fitted = fit_to_terminal((width, height), (columns as Unit, rows as Unit));
+            if fitted != (width, height) {
+                eprintln!(
+                    "A game size of {}x{} does not fit the terminal, using {}x{}",
+                    width, height, fitted.0, fitted.1
+                );
+                width = fitted.0;
+                height = fitted.1;
+            }
+        }
+    }
+
+    config.check_size((width, height))?;
+
+    width = (width / config.brick_width.max(1)) * config.brick_width.max(1);
+    if let Some(size) = saved_size {
+        if size != (width, height) {
+            return Err(GameError::InvalidConfig(format!(
+                "The saved game is {}x{} and does not fit a {}x{} game",
+                size.0, size.1, width, height
+            )));
+        }
+    }
+    config.check((width, height))?;
+    if let Some(recording) = recording {
+        recording.verify(config)?;
+        eprintln!("The replay matches the recording");
+        return Ok(());
+    }
+    match bench_frames {
+        Some(frames) => bench(
+            width,
+            height,
+            config,
+            frames,
+            record_path.map(PathBuf::from),
+        )?,
+        None => init(width, height, config)?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Keeps everything drawn to it, to check the output of the drawing code.
+    struct CaptureScreen {
+        output: Vec<u8>,
+        color: bool,
+        /// Reported terminal size, none by default
+        size: Option<(Unit, Unit)>,
+        offset: Unit,
+    }
+
+    impl CaptureScreen {
+        fn new(color: bool) -> CaptureScreen {
+            CaptureScreen {
+                output: vec![],
+                color,
+                size: None,
+                offset: 0,
+            }
+        }
+
+        fn text(&self) -> String {
+            String::from_utf8_lossy(&self.output).into_owned()
+        }
+    }
+
+    impl Screen for CaptureScreen {
+        fn enter(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn leave(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn uses_color(&self) -> bool {
+            self.color
+        }
+
+        fn size(&self) -> Option<(Unit, Unit)> {
+            self.size
+        }
+
+        fn offset(&self) -> Unit {
+            self.offset
+        }
+
+        fn set_offset(&mut self, columns: Unit) {
+            self.offset = columns;
+        }
+    }
+
+    impl Write for CaptureScreen {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.output.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Stand-in for the terminal device, tracks whether raw mode is on.
+    #[derive(Default)]
+    struct FakeTty {
+        raw: std::rc::Rc<std::cell::Cell<bool>>,
+    }
+
+    /// Turns raw mode of its `FakeTty` off again when dropped.
+    struct FakeRawMode(std::rc::Rc<std::cell::Cell<bool>>);
+
+    impl Drop for FakeRawMode {
+        fn drop(&mut self) {
+            self.0.set(false);
+        }
+    }
+
+    impl Tty for FakeTty {
+        type RawMode = FakeRawMode;
+
+        fn raw_mode(&mut self) -> io::Result<FakeRawMode> {
+            self.raw.set(true);
+            Ok(FakeRawMode(self.raw.clone()))
+        }
+
+        fn size(&self) -> Option<(Unit, Unit)> {
+            Some((80, 24))
+        }
+    }
+
+    /// A terminal drawing into memory on a fake device.
+    fn fake_terminal(color: bool) -> Terminal<Vec<u8>, FakeTty> {
+        Terminal::new(Vec::new(), FakeTty::default(), color)
+    }
+
+    /// A peddle in the bottom row of a 104x30 game with its left end at `x`.
+    fn peddle_at(x: f32) -> Peddle {
+        Peddle {
+            game_x: x,
+            target_x: x,
+            y: 27,
+            graphic: graphics::PEDDLE_GRAPHIC.to_string(),
+            color: NamedColor::Red,
+            bounce_ticks: 0,
+            flash_ticks: 0,
+        }
+    }
+
+    /// A ball at `pos` moving with `vel`.
+    fn ball_at(pos: (f32, f32), vel: (f32, f32)) -> Ball {
+        Ball {
+            game_pos: pos,
+            vel,
+            gravity: 0.0,
+            speed: Ball::get_speed(),
+            stuck_offset: None,
+            holder: 0,
+            trail: VecDeque::new(),
+            trail_length: 0,
+            shallow_ticks: 0,
+            assist: 0.0,
+            spin_decay: 0.0,
+            shot_share: std::f32::consts::FRAC_1_SQRT_2,
+            size: 1,
+            padding: CollisionPadding::default(),
+            color: NamedColor::Red,
+            walls: Walls::default(),
+            field: FieldShape::Rectangle,
+            side: PeddleSide::Bottom,
+            grace: false,
+            bounces: 0,
+            fire_ticks: 0,
+        }
+    }
+
+    /// Moves `ball` one frame in a 104x30 game, with the peddle out of its way.
+    fn update_in_open_field(ball: &mut Ball, time_scale: f32) -> BallUpdate {
+        ball.update((104, 30), &[peddle_at(2.0)], None, time_scale)
+    }
+
+    #[test]
+    fn demo_ai_steers_towards_the_ball_at_most_the_peddle_speed() {
+        let peddle = peddle_at(40.0);
+        let center = (40 + peddle.get_width() / 2) as f32;
+        let speed = Peddle::get_speed() as isize;
+        assert_eq!(peddle.steer_towards(center), 0);
+        assert_eq!(peddle.steer_towards(center + 2f32), 1);
+        assert_eq!(peddle.steer_towards(center - 2f32), -1);
+        assert_eq!(peddle.steer_towards(center + 50f32), speed);
+        assert_eq!(peddle.steer_towards(center - 50f32), -speed);
+    }
+
+    #[test]
+    fn time_scale_multiplies_the_distance_moved_per_frame() {
+        let start = (50f32, 10f32);
+        let vel = (0.2, 0.3);
+        for &time_scale in &[0.5f32, 1f32, 2f32] {
+            let mut ball = ball_at(start, vel);
+            update_in_open_field(&mut ball, time_scale);
+            assert!((ball.game_pos.0 - start.0 - vel.0 * time_scale).abs() < 1e-4);
+            assert!((ball.game_pos.1 - start.1 - vel.1 * time_scale).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn clearing_a_wide_peddle_blanks_its_display_width() {
+        let mut peddle = peddle_at(10.0);
+        peddle.graphic = "＝＝＝＝".to_string();
+        assert_eq!(peddle.get_graphics().chars().count(), 4);
+        assert_eq!(peddle.get_width(), 8);
+        let mut screen = CaptureScreen::new(false);
+        peddle.clear(&mut screen);
+        assert_eq!(
+            screen.text(),
+            format!("{}{}", cursor::Goto(10, 27), " ".repeat(8))
+        );
+
+        let peddle = peddle_at(40.0);
+        assert_eq!(peddle.get_width(), display_width(graphics::PEDDLE_GRAPHIC));
+    }
+
+    #[test]
+    fn brick_gap_spaces_the_bricks_apart() {
+        let config = GameConfig {
+            cols: Some(4),
+            rows: Some(2),
+            brick_gap: 1,
+            ..GameConfig::default()
+        };
+        let cells = BreakoutGame::<std::io::Empty, CaptureScreen>::generate_cell_grid(
+            (100, 30),
+            &config,
+            0,
+        );
+        assert_eq!(cells.len(), 8);
+        let first = cells[0].x();
+        for (index, cell) in cells.iter().enumerate() {
+            let (column, row) = (index % 4, index / 4);
+            assert_eq!(cell.x(), first + column * (Cell::get_default_width() + 1));
+            assert_eq!(cell.y(), GameConfig::get_grid_top() + row * 2);
+        }
+    }
+
+    fn headless_game(
+        width: Unit,
+        height: Unit,
+        config: GameConfig,
+    ) -> BreakoutGame<std::io::Empty, CaptureScreen> {
+        BreakoutGame::new(
+            std::io::empty(),
+            CaptureScreen::new(false),
+            width,
+            height,
+            config,
+        )
+    }
+
+    /// A 104x30 game with only `cells` on the field and the ball at `pos`
+    /// moving with `vel`.
+    fn game_with_bricks(
+        cells: Vec<Cell>,
+        pos: (f32, f32),
+        vel: (f32, f32),
+    ) -> BreakoutGame<std::io::Empty, CaptureScreen> {
+        let mut game = headless_game(104, 30, GameConfig::default());
+        game.cells = cells;
+        game.index_bricks();
+        game.balls = vec![ball_at(pos, vel)];
+        game
+    }
+
+    #[test]
+    fn solid_bricks_bounce_the_ball_without_being_destroyed() {
+        let solid = Cell::new((40, 5), 8, Box::new(color::LightBlack), BrickKind::Solid);
+        let mut game = game_with_bricks(vec![solid], (44.0, 6.0), (0.0, -1.0));
+        for _ in 0..3 {
+            game.tick();
+            assert_eq!(game.cells.len(), 1);
+        }
+        assert!(game.balls[0].vel.1 > 0.0);
+        assert!(!game.cells[0].hit());
+    }
+
+    #[test]
+    fn game_is_won_with_only_solid_bricks_left() {
+        let cells = vec![
+            Cell::new((20, 5), 8, Box::new(color::LightBlack), BrickKind::Solid),
+            Cell::new((40, 5), 8, Box::new(color::Red), BrickKind::Normal),
+        ];
+        let mut game = game_with_bricks(cells, (44.0, 6.0), (0.0, -1.0));
+        assert_eq!(game.tick(), TickResult::Won);
+        assert_eq!(game.cells.len(), 1);
+    }
+
+    #[test]
+    fn win_fires_when_the_last_breakable_brick_is_cleared() {
+        let cells = vec![
+            Cell::new((20, 5), 8, Box::new(color::Red), BrickKind::Normal),
+            Cell::new((40, 5), 8, Box::new(color::LightBlack), BrickKind::Solid),
+            Cell::new((60, 5), 8, Box::new(color::Red), BrickKind::Normal),
+        ];
+        let mut game = game_with_bricks(cells, (24.0, 6.0), (0.0, -1.0));
+        assert_eq!(game.tick(), TickResult::Running);
+        assert_eq!(game.remaining_breakable(), 1);
+        game.balls = vec![ball_at((64.0, 6.0), (0.0, -1.0))];
+        assert_eq!(game.tick(), TickResult::Won);
+        assert_eq!(game.remaining_breakable(), 0);
+        assert_eq!(game.cells.len(), 1);
+    }
+
+    #[test]
+    fn minimum_size_generates_a_non_empty_grid_inside_the_borders() {
+        let config = GameConfig::default();
+        let size = (GameConfig::get_min_width(), GameConfig::get_min_height());
+        assert!(config.grid_dimensions(size).is_ok());
+        for seed in 0..10 {
+            let cells = BreakoutGame::<std::io::Empty, CaptureScreen>::generate_cell_grid(
+                size, &config, seed,
+            );
+            assert!(!cells.is_empty());
+            assert!(cells.iter().any(|cell| cell.is_breakable()));
+            for cell in &cells {
+                assert!(cell.x() > 1 && cell.x() + cell.get_width() <= size.0);
+                assert!(cell.y() > 1 && cell.y() < size.1 / 2);
+            }
+        }
+    }
+
+    #[test]
+    fn impossible_grid_is_an_error_instead_of_a_panic() {
+        let config = GameConfig::default();
+        assert!(config
+            .grid_dimensions((2 * Cell::get_default_width(), 20))
+            .is_err());
+        assert!(config.grid_dimensions((40, 4)).is_err());
+    }
+
+    #[test]
+    fn countdown_turns_into_playing_after_three_seconds_of_ticks() {
+        let mut game = headless_game(104, 30, GameConfig::default());
+        game.reset_game();
+        let ticks = BreakoutGame::<std::io::Empty, CaptureScreen>::get_countdown_ticks();
+        assert_eq!(ticks, 150);
+        assert_eq!(game.state, GameState::Countdown(ticks));
+        for _ in 1..ticks {
+            game.state = game.state.next();
+            assert_ne!(game.state, GameState::Playing);
+        }
+        game.state = game.state.next();
+        assert_eq!(game.state, GameState::Playing);
+        assert_eq!(game.state.next(), GameState::Playing);
+    }
+
+    #[test]
+    fn frame_timer_smooths_towards_recent_frame_times() {
+        let mut timer = FrameTimer::new();
+        assert_eq!(timer.fps(), 0.0);
+        timer.record(20.0);
+        assert_eq!(timer.fps(), 50.0);
+        timer.record(30.0);
+        assert!((timer.average_millis - 21.0).abs() < 1e-4);
+        for _ in 0..200 {
+            timer.record(10.0);
+        }
+        assert!((timer.fps() - 100.0).abs() < 0.1);
+        assert_eq!(timer.label(), "  10.0ms 100fps ");
+    }
+
+    #[test]
+    fn ball_bounces_horizontally_off_the_peddle_sides() {
+        let peddles = [peddle_at(40.0)];
+        let right_side = (peddles[0].x() + peddles[0].get_width()) as f32;
+        // Just inside either end of the peddle, moving more sideways than down
+        for &(pos, vel) in &[
+            ((40.1, 27.2), (0.8, 0.6)),
+            ((right_side - 0.1, 27.2), (-0.8, 0.6)),
+        ] {
+            let mut ball = ball_at(pos, vel);
+            assert_eq!(
+                ball.update((104, 30), &peddles, None, 1.0),
+                BallUpdate::Moving
+            );
+            assert_eq!(ball.vel.0, -vel.0);
+            assert_eq!(ball.vel.1, vel.1);
+        }
+    }
+
+    #[test]
+    fn gravity_increases_the_downward_velocity_over_several_ticks() {
+        let peddles = [peddle_at(0.0)];
+        let mut ball = ball_at((50.0, 10.0), (0.3, -0.5));
+        ball.gravity = BreakoutGame::<std::io::Empty, CaptureScreen>::get_gravity();
+        let mut previous = ball.vel.1;
+        for _ in 0..5 {
+            assert_eq!(
+                ball.update((104, 30), &peddles, None, 1.0),
+                BallUpdate::Moving
+            );
+            assert!(ball.vel.1 > previous);
+            previous = ball.vel.1;
+        }
+        assert_eq!(ball.vel.0, 0.3);
+
+        let mut weightless = ball_at((50.0, 10.0), (0.3, -0.5));
+        assert_eq!(
+            weightless.update((104, 30), &peddles, None, 1.0),
+            BallUpdate::Moving
+        );
+        assert_eq!(weightless.vel.1, -0.5);
+    }
+
+    #[test]
+    fn brick_graphics_span_the_configured_width_at_every_damage_level() {
+        for &width in &[1, 5, 8, 12] {
+            for &kind in &[BrickKind::Normal, BrickKind::Tough, BrickKind::Solid] {
+                let mut cell = Cell::new((10, 5), width, Box::new(color::Red), kind);
+                for _ in 0..=Cell::get_max_hits(kind) {
+                    let graphics = cell.get_graphics();
+                    assert_eq!(graphics.chars().count(), width);
+                    assert_eq!(display_width(&graphics), width);
+                    cell.hit();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn menu_navigation_selects_items_and_edits_the_config() {
+        let mut menu = Menu::new();
+        let mut config = GameConfig::default();
+        assert_eq!(menu.handle_key(Key::Char('k'), &mut config), None);
+        assert_eq!(menu.selected_item(), MenuItem::Quit);
+        menu.handle_key(Key::Down, &mut config);
+        menu.handle_key(Key::Char('j'), &mut config);
+        assert_eq!(menu.selected_item(), MenuItem::Difficulty);
+        menu.handle_key(Key::Right, &mut config);
+        assert_eq!(config.difficulty, Difficulty::Hard);
+        menu.handle_key(Key::Char('h'), &mut config);
+        menu.handle_key(Key::Char('h'), &mut config);
+        assert_eq!(config.difficulty, Difficulty::Easy);
+
+        menu.handle_key(Key::Down, &mut config);
+        assert_eq!(menu.selected_item(), MenuItem::Seed);
+        for digit in "42".chars() {
+            menu.handle_key(Key::Char(digit), &mut config);
+        }
+        assert_eq!(config.seed, Some(42));
+        menu.handle_key(Key::Backspace, &mut config);
+        assert_eq!(config.seed, Some(4));
+
+        menu.handle_key(Key::Up, &mut config);
+        menu.handle_key(Key::Up, &mut config);
+        assert_eq!(
+            menu.handle_key(Key::Char('\n'), &mut config),
+            Some(MenuAction::Start)
+        );
+        assert_eq!(
+            menu.handle_key(Key::Char('q'), &mut config),
+            Some(MenuAction::Quit)
+        );
+    }
+
+    #[test]
+    fn format_duration_pads_minutes_and_seconds() {
+        assert_eq!(format_duration(Duration::from_secs(0)), "00:00");
+        assert_eq!(format_duration(Duration::from_millis(59_999)), "00:59");
+        assert_eq!(format_duration(Duration::from_secs(61)), "01:01");
+        assert_eq!(format_duration(Duration::from_secs(100 * 60 + 5)), "100:05");
+    }
+
+    #[test]
+    fn stopwatch_leaves_out_paused_time() {
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let mut stopwatch = Stopwatch::new();
+        assert_eq!(stopwatch.elapsed(at(5)), Duration::from_secs(0));
+        stopwatch.start(at(0));
+        stopwatch.start(at(3));
+        assert_eq!(stopwatch.elapsed(at(10)), Duration::from_secs(10));
+        stopwatch.stop(at(10));
+        assert_eq!(stopwatch.elapsed(at(40)), Duration::from_secs(10));
+        stopwatch.start(at(40));
+        stopwatch.stop(at(45));
+        stopwatch.stop(at(50));
+        assert_eq!(stopwatch.elapsed(at(60)), Duration::from_secs(15));
+    }
+
+    #[test]
+    fn sticky_peddle_holds_the_ball_until_space() {
+        let mut game = headless_game(104, 30, GameConfig::default());
+        game.state = GameState::Playing;
+        game.sticky_catches = 2;
+        game.sticky_ticks = 500;
+        let pos = (
+            game.peddles[0].x() as f32 + 2.0,
+            (game.peddles[0].y() - 2) as f32,
+        );
+        game.balls = vec![ball_at(pos, (0.0, 1.0))];
+        for _ in 0..20 {
+            game.tick();
+        }
+        let offset = game.balls[0].stuck_offset.expect("the ball is caught");
+        let caught_at = game.peddles[0].x();
+        assert_eq!(game.sticky_catches, 1);
+
+        for _ in 0..5 {
+            game.peddles[0].move_in_dir(Direction::Right, game.width);
+            game.tick();
+            let peddle = &game.peddles[0];
+            assert_eq!(
+                game.balls[0].game_pos,
+                (peddle.x() as f32 + offset, (peddle.y() - 1) as f32)
+            );
+        }
+        assert!(game.peddles[0].x() > caught_at);
+
+        // The ball stays caught until space launches it
+        for _ in 0..20 {
+            game.tick();
+        }
+        assert!(game.balls[0].stuck_offset.is_some());
+        assert!(game.handle_key(Some(Key::Char(' '))));
+        assert_eq!(game.balls[0].stuck_offset, None);
+        assert!(game.balls[0].vel.1 < 0.0);
+        game.tick();
+        assert!(game.balls[0].game_pos.1 < (game.peddles[0].y() - 1) as f32);
+    }
+
+    #[test]
+    fn terminal_enter_and_leave_write_matching_sequences() {
+        let mut terminal = fake_terminal(true);
+        let raw = terminal.tty.raw.clone();
+        assert_eq!(terminal.size(), Some((80, 24)));
+        terminal.enter().unwrap();
+        assert!(raw.get());
+        assert_eq!(
+            String::from_utf8(terminal.output.clone()).unwrap(),
+            format!("{}{}", ToAlternateScreen, cursor::Hide)
+        );
+        terminal.output.clear();
+        terminal.leave().unwrap();
+        assert_eq!(
+            String::from_utf8(terminal.output.clone()).unwrap(),
+            format!(
+                "{}{}{}",
+                color::Fg(color::Reset),
+                cursor::Show,
+                ToMainScreen
+            )
+        );
+        assert!(!raw.get());
+    }
+
+    #[test]
+    fn dropping_an_entered_terminal_leaves_it() {
+        let mut output = Vec::new();
+        let tty = FakeTty::default();
+        let raw = tty.raw.clone();
+        {
+            let mut terminal = Terminal::new(&mut output, tty, false);
+            terminal.enter().unwrap();
+            assert!(raw.get());
+        }
+        assert!(!raw.get());
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.ends_with(&format!("{}{}", cursor::Show, ToMainScreen)));
+
+        // Never entered, so nothing to restore
+        let mut output = Vec::new();
+        drop(Terminal::new(&mut output, FakeTty::default(), false));
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn trail_keeps_exactly_the_most_recent_positions() {
+        let mut ball = ball_at((10.0, 10.0), (0.0, 1.0));
+        ball.trail_length = 3;
+        for x in 10..16 {
+            ball.game_pos = (x as f32, 10.0);
+            ball.record_trail();
+            ball.record_trail();
+        }
+        let trail: Vec<_> = ball.trail.iter().cloned().collect();
+        assert_eq!(trail, vec![(15, 10), (14, 10), (13, 10)]);
+        // The ball's own cell is not drawn as trail
+        let cells: Vec<_> = ball.trail_cells().into_iter().map(|(pos, _)| pos).collect();
+        assert_eq!(cells, vec![(14, 10), (13, 10)]);
+
+        let mut untraced = ball_at((10.0, 10.0), (0.0, 1.0));
+        untraced.trail_length = 0;
+        untraced.record_trail();
+        assert!(untraced.trail.is_empty());
+        // The trail is opt-in
+        let game = headless_game(104, 30, GameConfig::default());
+        assert_eq!(game.balls[0].trail_length, 0);
+    }
+
+    #[test]
+    fn too_horizontal_ball_gets_its_vertical_velocity_restored() {
+        let mut ball = ball_at((50.0, 10.0), (-0.9, -0.05));
+        let speed = (0.9f32 * 0.9 + 0.05 * 0.05).sqrt();
+        for _ in 0..Ball::get_shallow_tick_limit() {
+            ball.check_shallow_angle();
+        }
+        assert_eq!(ball.vel, (-0.9, -0.05));
+        ball.check_shallow_angle();
+        assert!((ball.vel.1 + speed * Ball::get_restored_vertical_ratio()).abs() < 1e-5);
+        assert!(ball.vel.0 < 0.0);
+        let restored_speed = (ball.vel.0 * ball.vel.0 + ball.vel.1 * ball.vel.1).sqrt();
+        assert!((restored_speed - speed).abs() < 1e-5);
+        assert_eq!(ball.shallow_ticks, 0);
+
+        // A steep enough ball resets the count
+        ball.shallow_ticks = 100;
+        ball.check_shallow_angle();
+        assert_eq!(ball.shallow_ticks, 0);
+    }
+
+    #[test]
+    fn brick_count_matches_the_generated_grid() {
+        for seed in 0..5 {
+            let config = GameConfig {
+                seed: Some(seed),
+                ..GameConfig::default()
+            };
+            let cells = BreakoutGame::<std::io::Empty, CaptureScreen>::generate_cell_grid(
+                (104, 30),
+                &config,
+                seed,
+            );
+            let game = headless_game(104, 30, config);
+            assert_eq!(game.brick_count(), cells.len());
+            let positions: Vec<_> = game.bricks().map(|cell| cell.get_pos()).collect();
+            let expected: Vec<_> = cells.iter().map(|cell| cell.get_pos()).collect();
+            assert_eq!(positions, expected);
+        }
+    }
+
+    #[test]
+    fn no_color_mode_draws_the_same_shapes_without_color_escapes() {
+        let draw = |color: bool| {
+            let mut game = BreakoutGame::new(
+                io::empty(),
+                CaptureScreen::new(color),
+                104,
+                30,
+                GameConfig {
+                    seed: Some(1),
+                    ..GameConfig::default()
+                },
+            );
+            game.reset_game();
+            game.present_frame();
+            game.draw_dialog(graphics::GAME_OVER, Box::new(color::Red));
+            game.stdout.text()
+        };
+        let colored = draw(true);
+        let plain = draw(false);
+        assert!(colored.contains("\x1b[38;5;"));
+        assert!(!plain.contains("\x1b[38;5;"));
+        assert!(!plain.contains("\x1b[48;5;"));
+        assert!(!plain.contains("\x1b[39m"));
+        for glyph in &[graphics::BRICK_FULL, graphics::BORDER_VERTICAL] {
+            assert_eq!(plain.matches(glyph).count(), colored.matches(glyph).count());
+        }
+    }
+
+    #[test]
+    fn level_parse_reads_brick_kinds_and_gaps() {
+        let level = Level::parse("#T.X\n #TX  \n\n\n").unwrap();
+        assert_eq!((level.width(), level.height()), (4, 2));
+        assert_eq!(level.get(0, 0), Some(BrickKind::Normal));
+        assert_eq!(level.get(1, 0), Some(BrickKind::Tough));
+        assert_eq!(level.get(2, 0), None);
+        assert_eq!(level.get(3, 0), Some(BrickKind::Solid));
+        assert_eq!(level.get(0, 1), None);
+        assert_eq!(level.get(1, 1), Some(BrickKind::Normal));
+        assert_eq!(level.get(2, 1), Some(BrickKind::Tough));
+        assert_eq!(level.get(3, 1), Some(BrickKind::Solid));
+        assert_eq!(level.get(9, 9), None);
+    }
+
+    #[test]
+    fn level_parse_rejects_unknown_characters_and_unwinnable_levels() {
+        assert_eq!(
+            Level::parse("##\n#?").err(),
+            Some("unexpected character '?' on line 2".to_string())
+        );
+        assert!(Level::parse("XX..\nX").is_err());
+        assert!(Level::parse("").is_err());
+    }
+
+    #[test]
+    fn move_in_dir_keeps_the_peddle_between_the_borders() {
+        for &width in &[32, 33, 51, 104, 105] {
+            let mut peddle = peddle_at(10.0);
+            let peddle_width = peddle.get_width();
+            peddle.move_in_dir(Direction::Right, width);
+            assert_eq!(peddle.target_x, 10.0 + Peddle::get_speed() as f32);
+            for _ in 0..width {
+                peddle.move_in_dir(Direction::Right, width);
+            }
+            assert_eq!(
+                peddle.target_x as Unit + peddle_width - 1,
+                coords::right(width)
+            );
+            for _ in 0..width {
+                peddle.move_in_dir(Direction::Left, width);
+            }
+            assert_eq!(peddle.target_x as Unit, coords::left());
+            peddle.move_in_dir(Direction::Up, width);
+            assert_eq!(peddle.target_x as Unit, coords::left());
+        }
+    }
+
+    #[test]
+    fn assist_curves_a_rising_ball_towards_the_nearest_brick() {
+        let config = GameConfig {
+            assist: true,
+            ..GameConfig::default()
+        };
+        let mut game = headless_game(104, 30, config);
+        game.cells = vec![
+            Cell::new((10, 5), 8, Box::new(color::LightBlack), BrickKind::Solid),
+            Cell::new((70, 5), 8, Box::new(color::Red), BrickKind::Normal),
+        ];
+        game.index_bricks();
+        assert!(game.balls[0].assist > 0.0);
+        game.balls[0].game_pos = (50.0, 20.0);
+        game.balls[0].vel = (0.0, -game.balls[0].speed);
+        game.balls[0].stuck_offset = None;
+        assert_eq!(game.nearest_brick_center((50.0, 20.0)), Some((74.0, 5.0)));
+        game.state = GameState::Playing;
+        game.tick();
+        let vel = game.balls[0].vel;
+        assert!(vel.0 > 0.0);
+        assert!(((vel.0 * vel.0 + vel.1 * vel.1).sqrt() - game.balls[0].speed).abs() < 1e-5);
+
+        // Falling balls are left alone
+        let mut falling = ball_at((50.0, 20.0), (0.0, 1.0));
+        falling.assist = game.balls[0].assist;
+        falling.apply_assist((74.0, 5.0), 1.0);
+        assert_eq!(falling.vel, (0.0, 1.0));
+    }
+
+    /// A 104x30 game drawing to a `CaptureScreen` with colors.
+    fn captured_game(config: GameConfig) -> BreakoutGame<std::io::Empty, CaptureScreen> {
+        BreakoutGame::new(io::empty(), CaptureScreen::new(true), 104, 30, config)
+    }
+
+    #[test]
+    fn damaging_a_brick_redraws_only_that_brick() {
+        let mut game = captured_game(GameConfig::default());
+        game.cells = vec![
+            Cell::new((20, 5), 8, Box::new(color::Red), BrickKind::Tough),
+            Cell::new((40, 5), 8, Box::new(color::Red), BrickKind::Tough),
+            Cell::new((60, 5), 8, Box::new(color::Red), BrickKind::Tough),
+        ];
+        game.index_bricks();
+        game.balls = vec![ball_at((44.0, 6.0), (0.0, -1.0))];
+        game.redraw_scene();
+        game.stdout.output.clear();
+        game.tick();
+        let text = game.stdout.text();
+        assert_eq!(text.matches(graphics::BRICK_DAMAGED).count(), 8);
+        assert!(!text.contains(graphics::BRICK_FULL));
+        assert!(text.contains(&format!("{}", cursor::Goto(40, 5))));
+    }
+
+    #[test]
+    fn leaving_after_drawing_restores_the_terminal() {
+        let mut terminal = fake_terminal(true);
+        terminal.enter().unwrap();
+        terminal.draw_text((5, 5), &color::Red, "quit");
+        terminal.leave().unwrap();
+        let output = String::from_utf8(terminal.output.clone()).unwrap();
+        let restore = format!(
+            "{}{}{}",
+            color::Fg(color::Reset),
+            cursor::Show,
+            ToMainScreen
+        );
+        assert!(output.ends_with(&restore));
+        assert!(terminal.raw_mode.is_none());
+    }
+
+    #[test]
+    fn safety_net_bounces_one_ball_and_is_used_up() {
+        let mut game = headless_game(104, 30, GameConfig::default());
+        game.safety_net_ticks = 100;
+        let bottom = 29.0;
+        game.balls = vec![ball_at((10.0, bottom), (0.0, 1.0))];
+        assert_eq!(game.tick(), TickResult::Running);
+        assert_eq!(game.safety_net_ticks, 0);
+        assert!(game.balls[0].vel.1 < 0.0);
+
+        game.balls = vec![ball_at((10.0, bottom), (0.0, 1.0))];
+        assert_eq!(game.tick(), TickResult::BallLost);
+    }
+
+    #[test]
+    fn controls_help_lists_the_configured_movement_keys_compactly() {
+        let mut bindings = KeyBindings::wasd();
+        bindings.bind(Action::Left, Key::Left);
+        let lines = Menu::controls_lines(&bindings);
+        let text = lines.join("\n");
+        assert!(text.contains("a/← d move"));
+        assert!(text.contains("q quit"));
+        assert!(!text.contains("move left"));
+        assert!(lines.len() < 2 + bindings.bindings.len());
+        for line in &lines {
+            assert_eq!(display_width(line), graphics::MENU_INNER_WIDTH + 2);
+        }
+    }
+
+    #[test]
+    fn scoreboard_lists_one_line_per_stored_score() {
+        let path = env::temp_dir().join(format!("rbreakout-scores-{}", std::process::id()));
+        let mut fixture = "best_time 95\nscore 300 0\nnot a record\n".to_string();
+        for score in 1..=3 {
+            fixture.push_str(&format!("score {} 86400\n", score * 100));
+        }
+        std::fs::write(&path, fixture).unwrap();
+        let mut game = headless_game(104, 30, GameConfig::default());
+        game.records = Records::load(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let lines = game.scoreboard_lines();
+        let entries: Vec<_> = lines.iter().filter(|line| line.contains("1970-")).collect();
+        assert_eq!(entries.len(), 4);
+        assert!(entries[0].contains(" 1.     300   1970-01-01"));
+        assert!(entries[1].contains(" 2.     300   1970-01-02"));
+        assert!(entries[3].contains(" 4.     100   1970-01-02"));
+        assert_eq!(lines.len(), 1 + 4 + 3);
+
+        for score in 1..=20 {
+            game.records.submit_score(score * 1000, 0);
+        }
+        assert_eq!(
+            game.scoreboard_lines().len(),
+            1 + Records::get_max_scores() + 3
+        );
+    }
+
+    #[test]
+    fn size_two_ball_collides_across_its_whole_footprint() {
+        let brick = Cell::new((40, 5), 8, Box::new(color::Red), BrickKind::Normal);
+        let sized = |size: Unit, pos: (f32, f32), vel: (f32, f32)| {
+            let mut ball = ball_at(pos, vel);
+            ball.size = size;
+            ball
+        };
+        // Bottom row of the ball resting on the brick's top
+        assert_eq!(
+            sized(2, (42.0, 4.0), (0.0, 1.0)).collides_with(&brick),
+            Some(Direction::Down)
+        );
+        assert_eq!(
+            sized(1, (42.0, 4.0), (0.0, 1.0)).collides_with(&brick),
+            None
+        );
+        assert_eq!(
+            sized(2, (42.0, 3.4), (0.0, 1.0)).collides_with(&brick),
+            None
+        );
+        // Right column of the ball touching the brick's left end
+        assert_eq!(
+            sized(2, (39.0, 5.0), (1.0, 0.2)).collides_with(&brick),
+            Some(Direction::Left)
+        );
+        assert_eq!(
+            sized(2, (38.5, 5.0), (1.0, 0.2)).collides_with(&brick),
+            None
+        );
+        // Left column only reaching past the brick's right end
+        assert_eq!(
+            sized(2, (48.5, 5.0), (1.0, 0.2)).collides_with(&brick),
+            None
+        );
+
+        let ball = sized(2, (10.0, 10.0), (0.0, 1.0));
+        assert_eq!((ball.get_width(), ball.get_height()), (2, 2));
+        assert_eq!(ball.get_extent(), 1.0);
+    }
+
+    #[test]
+    fn stats_count_bricks_and_peddle_hits_and_feed_the_summary() {
+        let mut game = headless_game(104, 30, GameConfig::default());
+        game.cells = vec![
+            Cell::new((40, 5), 8, Box::new(color::Red), BrickKind::Normal),
+            Cell::new((80, 5), 8, Box::new(color::Red), BrickKind::Normal),
+        ];
+        game.index_bricks();
+        game.balls = vec![ball_at((44.0, 6.0), (0.0, -1.0))];
+        game.tick();
+        let pos = (
+            (game.peddles[0].x() + 2) as f32,
+            (game.peddles[0].y() - 2) as f32,
+        );
+        game.balls = vec![ball_at(pos, (0.0, 1.0))];
+        for _ in 0..10 {
+            if game.stats.peddle_hits > 0 {
+                break;
+            }
+            game.tick();
+        }
+        let stats = &game.stats;
+        assert_eq!(stats.bricks_destroyed, 1);
+        assert_eq!(stats.peddle_hits, 1);
+        assert_eq!(stats.accuracy(), 100);
+
+        let summary = stats.summary(game.score, Duration::from_secs(75));
+        assert_eq!(summary[0], ("score", game.score.to_string()));
+        assert_eq!(summary[1], ("bricks", "1".to_string()));
+        assert_eq!(summary[2], ("time", "01:15".to_string()));
+        assert_eq!(summary[3], ("accuracy", "100%".to_string()));
+
+        game.reset_game();
+        assert_eq!(game.stats.bricks_destroyed, 0);
+        assert_eq!(game.stats.peddle_hits, 0);
+    }
+
+    #[test]
+    fn simulation_with_a_fixed_seed_is_deterministic() {
+        let run = || {
+            let config = GameConfig {
+                seed: Some(11),
+                ..GameConfig::default()
+            };
+            let mut game = BreakoutGame::new(io::empty(), NullScreen, 104, 30, config);
+            let outcome = game.simulate(2000);
+            assert_eq!(game.seed, 11);
+            (outcome, game.score, game.brick_count())
+        };
+        let first = run();
+        assert_eq!(first, run());
+        assert!(first.1 > 0);
+    }
+
+    #[test]
+    fn explosive_bricks_take_their_breakable_neighbors_with_them() {
+        let brick = |x, y, kind| Cell::new((x, y), 8, Box::new(color::Red), kind);
+        let cells = vec![
+            brick(24, 5, BrickKind::Normal),
+            brick(32, 5, BrickKind::Explosive),
+            brick(40, 5, BrickKind::Explosive),
+            brick(48, 5, BrickKind::Normal),
+            brick(56, 5, BrickKind::Normal),
+            brick(24, 6, BrickKind::Solid),
+        ];
+        let mut game = game_with_bricks(cells, (44.0, 6.0), (0.0, -1.0));
+        let mut chain = game.with_explosions(vec![2]);
+        chain.sort();
+        assert_eq!(chain, vec![0, 1, 2, 3]);
+
+        game.tick();
+        let left: Vec<_> = game.bricks().map(|cell| cell.get_pos()).collect();
+        assert_eq!(left, vec![(56, 5), (24, 6)]);
+        assert_eq!(game.stats.bricks_destroyed, 4);
+    }
+
+    #[test]
+    fn playfield_bounds_sit_inside_the_borders() {
+        for &width in &[32, 33, 104] {
+            assert_eq!(coords::left(), coords::left_border() + 1);
+            assert_eq!(coords::right(width), coords::right_border(width) - 1);
+            assert_eq!(
+                coords::right(width) + 1 - coords::left(),
+                coords::inner_width(width)
+            );
+        }
+        assert_eq!(coords::top(), coords::top_border() + 1);
+        assert_eq!(coords::bottom(30), coords::bottom_border(30) - 1);
+    }
+
+    #[test]
+    fn cursor_positions_match_game_positions() {
+        let cursor = format!("{}", coords::to_cursor((7, 9), 0));
+        assert_eq!(cursor, format!("{}", cursor::Goto(7, 9)));
+    }
+
+    #[test]
+    fn destroying_a_multiball_brick_adds_one_ball() {
+        let cells = vec![
+            Cell::new((40, 5), 8, Box::new(color::Cyan), BrickKind::MultiBall),
+            Cell::new((80, 5), 8, Box::new(color::Red), BrickKind::Normal),
+        ];
+        let mut game = game_with_bricks(cells, (44.0, 6.0), (0.0, -1.0));
+        game.tick();
+        assert_eq!(game.balls.len(), 2);
+        let spawned = &game.balls[1];
+        assert!(spawned.vel.1 < 0.0);
+        assert_eq!(spawned.speed, game.balls[0].speed);
+        assert_eq!(spawned.get_pos(), (44, 5));
+        assert_eq!(game.brick_count(), 1);
+    }
+
+    #[test]
+    fn weighted_colors_follow_the_requested_distribution() {
+        let weights: ColorWeights = "red=6, blue=3, green=1, yellow=0".parse().unwrap();
+        let mut rng = StdRng::from_seed(&[42usize][..]);
+        let samples = 10_000;
+        let mut counts = [0; 4];
+        for _ in 0..samples {
+            let index = match weights.pick(&mut rng) {
+                NamedColor::Red => 0,
+                NamedColor::Blue => 1,
+                NamedColor::Green => 2,
+                _ => 3,
+            };
+            counts[index] += 1;
+        }
+        let share = |count: u32| count as f32 / samples as f32;
+        assert!((share(counts[0]) - 0.6).abs() < 0.03);
+        assert!((share(counts[1]) - 0.3).abs() < 0.03);
+        assert!((share(counts[2]) - 0.1).abs() < 0.03);
+        assert_eq!(counts[3], 0);
+        assert!("red=0".parse::<ColorWeights>().is_err());
+        assert!("red".parse::<ColorWeights>().is_err());
+    }
+
+    #[test]
+    fn peddle_eases_towards_its_target_over_several_frames() {
+        let mut peddle = peddle_at(10.0);
+        peddle.move_by(20, 104);
+        assert_eq!(peddle.target_x, 30.0);
+        let mut previous = peddle.game_x;
+        let mut frames = 0;
+        while peddle.game_x != peddle.target_x {
+            peddle.ease(1.0);
+            assert!(peddle.game_x > previous && peddle.game_x <= 30.0);
+            assert_eq!(peddle.x(), peddle.game_x.round() as Unit);
+            previous = peddle.game_x;
+            frames += 1;
+            assert!(frames < 100);
+        }
+        assert!(frames > 1);
+
+        // A larger time scale covers more of the way per frame
+        let (mut slow, mut fast) = (peddle_at(10.0), peddle_at(10.0));
+        slow.move_by(20, 104);
+        fast.move_by(20, 104);
+        slow.ease(0.5);
+        fast.ease(1.0);
+        assert!(fast.game_x > slow.game_x);
+    }
+
+    #[test]
+    fn shrinking_the_terminal_shows_a_notice_instead_of_panicking() {
+        let keys = io::Cursor::new(b"l".to_vec());
+        let mut game = BreakoutGame::new(
+            keys,
+            CaptureScreen::new(true),
+            104,
+            30,
+            GameConfig::default(),
+        );
+        game.reset_game();
+        game.state = GameState::Playing;
+        assert!(!game.check_terminal_size());
+
+        game.stdout.size = Some((20, 5));
+        assert!(game.check_terminal_size());
+        assert!(game
+            .stdout
+            .text()
+            .contains("Terminal too small, needs 104x30"));
+        let peddle_x = game.peddles[0].target_x;
+        assert!(game.update());
+        assert_eq!(game.peddles[0].target_x, peddle_x);
+        game.stdin = io::Cursor::new(b"q".to_vec());
+        assert!(!game.update());
+
+        game.stdout.size = Some((104, 30));
+        game.stdout.output.clear();
+        assert!(!game.check_terminal_size());
+        assert!(game.stdout.text().contains(graphics::BRICK_FULL));
+    }
+
+    #[test]
+    fn rainbow_index_advances_every_ten_frames_and_wraps() {
+        assert_eq!(Theme::rainbow_index(0), 0);
+        assert_eq!(Theme::rainbow_index(9), 0);
+        assert_eq!(Theme::rainbow_index(10), 1);
+        assert_eq!(Theme::rainbow_index(59), 5);
+        assert_eq!(Theme::rainbow_index(60), 0);
+        assert!(Theme::Rainbow.border_changes(10));
+        assert!(!Theme::Rainbow.border_changes(11));
+        assert!(!Theme::Rainbow.border_changes(0));
+        assert!(!Theme::Classic.border_changes(10));
+    }
+
+    #[test]
+    fn wider_side_padding_registers_side_hits_further_in() {
+        let brick = Cell::new((40, 5), 8, Box::new(color::Red), BrickKind::Normal);
+        let with_side = |side: f32| {
+            let mut ball = ball_at((40.4, 5.2), (1.0, 0.5));
+            ball.padding = CollisionPadding { side, face: 0.5 };
+            ball.collides_with(&brick)
+        };
+        assert_eq!(with_side(0.3), Some(Direction::Down));
+        assert_eq!(with_side(0.5), Some(Direction::Left));
+
+        let with_face = |face: f32| {
+            let mut ball = ball_at((44.0, 5.6), (0.0, 1.0));
+            ball.padding = CollisionPadding { side: 0.3, face };
+            ball.collides_with(&brick)
+        };
+        assert_eq!(with_face(0.5), Some(Direction::Up));
+        assert_eq!(with_face(0.8), Some(Direction::Down));
+    }
+
+    #[test]
+    fn practice_mode_respawns_lost_balls_instead_of_ending_the_game() {
+        let mut config = GameConfig {
+            practice: true,
+            ..GameConfig::default()
+        };
+        config.key_bindings.bind(Action::Respawn, Key::Char('g'));
+        let keys = io::Cursor::new(b"g".to_vec());
+        let mut game = BreakoutGame::new(keys, CaptureScreen::new(false), 104, 30, config);
+        game.state = GameState::Playing;
+        let bottom = coords::bottom(30) as f32;
+        for _ in 0..3 {
+            game.balls = vec![ball_at((10.0, bottom), (0.0, 1.0))];
+            assert_eq!(game.tick(), TickResult::Running);
+            assert_eq!(game.balls.len(), 1);
+            assert!(game.balls[0].stuck_offset.is_some());
+        }
+        assert_eq!(game.stats.balls_lost, 3);
+
+        game.balls[0].launch(&game.peddles);
+        assert!(game.update());
+        assert!(game.balls[0].stuck_offset.is_some());
+    }
+
+    #[test]
+    fn layout_presets_bind_movement_quit_and_reset() {
+        let presets = [
+            ("vim", Key::Char('h'), Key::Char('l')),
+            ("wasd", Key::Char('a'), Key::Char('d')),
+            ("arrows", Key::Left, Key::Right),
+        ];
+        for &(name, left, right) in &presets {
+            let bindings: KeyBindings = name.parse().unwrap();
+            assert_eq!(bindings.action(left), Some(Action::Left));
+            assert_eq!(bindings.action(right), Some(Action::Right));
+            assert_eq!(bindings.action(Key::Char('q')), Some(Action::Quit));
+            assert_eq!(bindings.action(Key::Char('r')), Some(Action::Reset));
+            assert_eq!(bindings.action(Key::Char(' ')), Some(Action::Launch));
+        }
+        assert!("emacs".parse::<KeyBindings>().is_err());
+    }
+
+    #[test]
+    fn destroyed_bricks_scatter_particles_that_expire() {
+        let cells = vec![
+            Cell::new((40, 10), 8, Box::new(color::Red), BrickKind::Normal),
+            Cell::new((80, 5), 8, Box::new(color::Red), BrickKind::Normal),
+        ];
+        let mut game = game_with_bricks(cells, (44.0, 11.0), (0.0, -1.0));
+        game.config.effects = true;
+        game.tick();
+        assert_eq!(game.particles.len(), Particle::get_count_per_brick());
+        assert!(game
+            .particles
+            .iter()
+            .all(|particle| particle.ticks_left == Particle::get_lifetime()));
+        for _ in 0..Particle::get_lifetime() {
+            game.tick();
+        }
+        assert!(game.particles.is_empty());
+
+        let mut game = game_with_bricks(
+            vec![Cell::new(
+                (40, 10),
+                8,
+                Box::new(color::Red),
+                BrickKind::Normal,
+            )],
+            (44.0, 11.0),
+            (0.0, -1.0),
+        );
+        game.config.effects = false;
+        game.tick();
+        assert!(game.particles.is_empty());
+    }
+
+    #[test]
+    fn corner_hits_bounce_off_the_face_of_the_dominant_velocity() {
+        let brick = Cell::new((40, 5), 8, Box::new(color::Red), BrickKind::Normal);
+        let hit = |pos: (f32, f32), vel: (f32, f32)| ball_at(pos, vel).collides_with(&brick);
+        // Top left corner
+        assert_eq!(hit((40.1, 5.1), (1.0, 0.5)), Some(Direction::Left));
+        assert_eq!(hit((40.1, 5.1), (0.5, 1.0)), Some(Direction::Down));
+        // Ties go to the top or bottom
+        assert_eq!(hit((40.1, 5.1), (0.7, 0.7)), Some(Direction::Down));
+        // Bottom right corner
+        assert_eq!(hit((47.9, 5.8), (-1.0, -0.5)), Some(Direction::Left));
+        assert_eq!(hit((47.9, 5.8), (-0.5, -1.0)), Some(Direction::Up));
+        assert_eq!(hit((47.9, 5.8), (-0.7, -0.7)), Some(Direction::Up));
+    }
+
+    #[test]
+    fn boss_loses_health_per_hit_and_collides_across_its_footprint() {
+        let mut boss = Boss::new((40, 5), 24, 3);
+        assert_eq!(boss.health_bar(), graphics::BOSS_HEALTH_FULL.repeat(24));
+        for _ in 1..Boss::get_default_hp() {
+            assert!(!boss.hit());
+        }
+        assert_eq!(boss.hp, 1);
+        assert_eq!(
+            boss.health_bar()
+                .matches(graphics::BOSS_HEALTH_FULL)
+                .count(),
+            2
+        );
+        assert!(boss.hit());
+
+        // Every row of the footprint stops the ball, not just the top one
+        for &y in &[5.1, 6.0, 7.7] {
+            assert!(ball_at((40.1, y), (1.0, 0.2))
+                .collides_with(&boss)
+                .is_some());
+            assert!(ball_at((63.9, y), (-1.0, 0.2))
+                .collides_with(&boss)
+                .is_some());
+        }
+        assert!(ball_at((50.0, 8.5), (0.0, -1.0))
+            .collides_with(&boss)
+            .is_none());
+        assert!(boss.covers((40, 4)) && boss.covers((63, 7)));
+        assert!(!boss.covers((64, 6)) && !boss.covers((40, 8)));
+    }
+
+    #[test]
+    fn destroying_the_boss_wins_the_game() {
+        let mut game = game_with_bricks(vec![], (50.0, 8.6), (0.0, -1.0));
+        let mut boss = Boss::new((40, 5), 24, 3);
+        boss.hp = 1;
+        game.boss = Some(boss);
+        assert_eq!(game.tick(), TickResult::Won);
+    }
+
+    #[test]
+    fn toasts_show_for_their_duration_and_then_disappear() {
+        let mut game = captured_game(GameConfig::default());
+        game.post_toast("Multi-ball!");
+        game.post_toast("Multi-ball!");
+        assert_eq!(game.toasts.len(), 1);
+        let pos = game.toast_pos("Multi-ball!");
+        let shown = format!("{}Multi-ball!", coords::to_cursor(pos, 0));
+        for _ in 0..BreakoutGame::<io::Empty, CaptureScreen>::get_toast_ticks() {
+            game.stdout.output.clear();
+            game.draw_toast();
+            assert!(game.stdout.text().contains(&shown));
+        }
+        game.stdout.output.clear();
+        game.draw_toast();
+        let text = game.stdout.text();
+        assert!(!text.contains("Multi-ball!"));
+        assert!(text.contains(&format!("{}{}", coords::to_cursor(pos, 0), " ".repeat(11))));
+        assert!(game.toasts.is_empty());
+    }
+
+    #[test]
+    fn input_thread_decodes_escape_sequences_and_bursts_into_keys() {
+        let (sender, keys) = mpsc::channel();
+        forward_keys(io::Cursor::new(b"h\x1b[Dl\x1b[C \x03".to_vec()), &sender);
+        let decoded: Vec<Input> = keys.try_iter().collect();
+        assert_eq!(
+            decoded,
+            vec![
+                Input::Key(Key::Char('h')),
+                Input::Key(Key::Left),
+                Input::Key(Key::Char('l')),
+                Input::Key(Key::Right),
+                Input::Key(Key::Char(' ')),
+                Input::Key(Key::Ctrl('c')),
+            ]
+        );
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let mut input = InputThread::spawn(io::Cursor::new(b"q".to_vec()), stop.clone());
+        let key = input.keys.recv_timeout(Duration::from_secs(1)).ok();
+        assert_eq!(key, Some(Input::Key(Key::Char('q'))));
+        assert_eq!(input.next_input(), None);
+        drop(input);
+        assert!(stop.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn crossing_the_score_threshold_grants_one_life_each_time() {
+        let config = GameConfig {
+            extra_life_every: 500,
+            ..GameConfig::default()
+        };
+        let mut game = headless_game(104, 30, config);
+        game.add_score(490);
+        assert_eq!(game.lives, 1);
+        game.add_score(10);
+        assert_eq!(game.lives, 2);
+        game.add_score(490);
+        assert_eq!(game.lives, 2);
+        game.add_score(20);
+        assert_eq!(game.lives, 3);
+        for _ in 0..10 {
+            game.add_score(500);
+        }
+        assert_eq!(
+            game.lives,
+            BreakoutGame::<io::Empty, CaptureScreen>::get_max_lives()
+        );
+
+        game.lives = 2;
+        game.balls = vec![ball_at((10.0, coords::bottom(30) as f32), (0.0, 1.0))];
+        assert_eq!(game.tick(), TickResult::Running);
+        assert_eq!(game.lives, 1);
+        assert!(game.balls[0].stuck_offset.is_some());
+
+        game.reset_game();
+        assert_eq!((game.lives, game.last_life_threshold), (1, 0));
+    }
+
+    #[test]
+    fn ball_and_peddle_are_drawn_in_the_configured_colors() {
+        let config = GameConfig {
+            ball_color: "green".parse().unwrap(),
+            peddle_color: "cyan".parse().unwrap(),
+            ..GameConfig::default()
+        };
+        assert_eq!(config.ball_color, NamedColor::Green);
+        assert!("purple".parse::<NamedColor>().is_err());
+
+        let game = captured_game(config);
+        let fg = |shade: &dyn color::Color| format!("{}", color::Fg(shade));
+        assert_eq!(fg(game.balls[0].get_color()), fg(&color::Green));
+        assert_eq!(fg(game.peddles[0].get_color()), fg(&color::Cyan));
+        let spawned = game.balls[0].spawn_at((50.0, 10.0));
+        assert_eq!(spawned.color, NamedColor::Green);
+    }
+
+    #[test]
+    fn time_attack_counts_down_every_tick_and_ends_the_game_at_zero() {
+        let config = GameConfig {
+            time_attack_secs: 1,
+            ..GameConfig::default()
+        };
+        let mut game = headless_game(104, 30, config);
+        game.balls[0].stick_to(&game.peddles, 0);
+        let ticks = 1000 / FRAME_MILLIS;
+        for tick in 1..ticks {
+            assert_eq!(game.tick(), TickResult::Running);
+            let expected = Duration::from_millis(1000 - tick * FRAME_MILLIS);
+            assert_eq!(game.time_left, Some(expected));
+        }
+        assert_eq!(game.tick(), TickResult::TimeUp);
+        assert_eq!(game.time_left, Some(Duration::ZERO));
+
+        game.reset_game();
+        assert_eq!(game.time_left, Some(Duration::from_secs(1)));
+        assert_eq!(
+            headless_game(104, 30, GameConfig::default()).time_left,
+            None
+        );
+    }
+
+    #[test]
+    fn requested_size_is_shrunk_to_fit_the_terminal() {
+        assert_eq!(fit_to_terminal((104, 30), (200, 50)), (104, 30));
+        assert_eq!(fit_to_terminal((104, 30), (80, 24)), (80, 24));
+        assert_eq!(fit_to_terminal((104, 30), (80, 40)), (80, 30));
+        assert_eq!(fit_to_terminal((104, 30), (120, 24)), (104, 24));
+    }
+
+    #[test]
+    fn predicted_path_bounces_off_the_walls_and_stops_above_the_peddle() {
+        let path = ball_at((10.0, 10.0), (-1.0, -1.0)).predict_path((104, 30), 20);
+        assert_eq!(path.len(), 20);
+        assert_eq!(path[0], (9, 9));
+        assert_eq!(path[7], (2, 2));
+        assert_eq!(path[8], (3, 3));
+        assert_eq!(path[19], (14, 14));
+
+        let path = ball_at((100.0, 10.0), (1.0, 0.0)).predict_path((104, 30), 5);
+        assert_eq!(
+            path,
+            vec![(101, 10), (102, 10), (103, 10), (102, 10), (101, 10)]
+        );
+
+        let path = ball_at((50.0, 20.0), (0.0, 1.0)).predict_path((104, 30), 40);
+        let expected: Vec<_> = (21..28).map(|y| (50, y)).collect();
+        assert_eq!(path, expected);
+
+        // Slow balls take several frames per cell, each cell shows up once
+        let path = ball_at((50.0, 10.0), (0.0, 0.4)).predict_path((104, 30), 10);
+        assert_eq!(path, vec![(50, 11), (50, 12), (50, 13), (50, 14)]);
+    }
+
+    #[test]
+    fn destroyed_bricks_score_the_points_of_the_configured_table() {
+        let table: ScoreTable = "red=50, blue=5, tough=30".parse().unwrap();
+        let brick = |name: NamedColor, kind: BrickKind, x: Unit| {
+            let mut cell = Cell::new((x, 5), 8, name.to_color(), kind);
+            cell.named_color = Some(name);
+            cell
+        };
+        assert_eq!(
+            table.points(&brick(NamedColor::Red, BrickKind::Tough, 40)),
+            50
+        );
+        assert_eq!(
+            table.points(&brick(NamedColor::Green, BrickKind::Tough, 40)),
+            30
+        );
+        assert_eq!(
+            table.points(&brick(NamedColor::Green, BrickKind::Normal, 40)),
+            10
+        );
+        assert!("purple=3".parse::<ScoreTable>().is_err());
+        assert!("solid=3".parse::<ScoreTable>().is_err());
+        assert!("red".parse::<ScoreTable>().is_err());
+
+        for &(name, points) in &[(NamedColor::Red, 50), (NamedColor::Blue, 5)] {
+            let cells = vec![
+                brick(name, BrickKind::Normal, 40),
+                brick(NamedColor::Green, BrickKind::Normal, 80),
+            ];
+            let mut game = game_with_bricks(cells, (44.0, 6.0), (0.0, -1.0));
+            game.config.brick_points = "red=50, blue=5".parse().unwrap();
+            game.tick();
+            assert_eq!(game.brick_count(), 1);
+            assert_eq!(game.score, points);
+        }
+    }
+
+    #[test]
+    fn easy_finish_slows_the_ball_for_the_last_bricks_until_reset() {
+        let cells = vec![
+            Cell::new((20, 5), 8, Box::new(color::Red), BrickKind::Normal),
+            Cell::new((80, 5), 8, Box::new(color::Red), BrickKind::Normal),
+        ];
+        let vel = (0.2, 0.3);
+        let mut game = game_with_bricks(cells, (50.0, 15.0), vel);
+        game.config.easy_finish = true;
+        game.tick();
+        assert!((game.balls[0].game_pos.1 - 15.0 - vel.1).abs() < 1e-4);
+
+        game.cells.pop();
+        let before = game.balls[0].game_pos;
+        game.tick();
+        let slowdown = BreakoutGame::<io::Empty, CaptureScreen>::get_easy_finish_slowdown();
+        assert!((game.balls[0].game_pos.0 - before.0 - vel.0 * slowdown).abs() < 1e-4);
+        assert!((game.balls[0].game_pos.1 - before.1 - vel.1 * slowdown).abs() < 1e-4);
+
+        game.reset_game();
+        assert!(game.remaining_breakable() > 1);
+        assert_eq!(game.ball_time_scale(), 1.0);
+    }
+
+    #[test]
+    fn coop_peddles_each_bounce_the_ball_and_follow_their_own_keys() {
+        let config = GameConfig {
+            key_bindings: KeyBindings::vim(),
+            coop_bindings: Some(KeyBindings::arrows()),
+            ..GameConfig::default()
+        };
+        let keys = io::Cursor::new(b"l".to_vec());
+        let mut game = BreakoutGame::new(keys, CaptureScreen::new(false), 104, 30, config);
+        game.state = GameState::Playing;
+        assert_eq!(game.peddles.len(), 2);
+        assert!(game.peddles[0].x() + game.peddles[0].get_width() <= game.peddles[1].x());
+
+        for player in 0..2 {
+            let peddle = &game.peddles[player];
+            let pos = ((peddle.x() + 2) as f32, (peddle.y() - 2) as f32);
+            game.balls = vec![ball_at(pos, (0.0, 1.0))];
+            let hit = (0..10).find_map(|_| {
+                match game.balls[0].update((104, 30), &game.peddles, None, 1.0) {
+                    BallUpdate::HitPeddle(index) => Some(index),
+                    _ => None,
+                }
+            });
+            assert_eq!(hit, Some(player));
+            assert!(game.balls[0].vel.1 < 0.0);
+        }
+
+        let targets = (game.peddles[0].target_x, game.peddles[1].target_x);
+        assert!(game.update());
+        assert_eq!(
+            game.peddles[0].target_x,
+            targets.0 + Peddle::get_speed() as f32
+        );
+        assert_eq!(game.peddles[1].target_x, targets.1);
+        game.stdin = io::Cursor::new(b"\x1b[D".to_vec());
+        assert!(game.update());
+        assert_eq!(
+            game.peddles[1].target_x,
+            targets.1 - Peddle::get_speed() as f32
+        );
+
+        assert!(KeyBindings::default().conflicts_with(&KeyBindings::wasd()));
+        assert!(!KeyBindings::vim().conflicts_with(&KeyBindings::arrows()));
+    }
+
+    #[test]
+    fn closed_bottom_bounces_the_ball_and_open_sides_lose_it() {
+        let walls: Walls = "left, right, top, bottom".parse().unwrap();
+        let mut game = headless_game(104, 30, GameConfig::default());
+        let bottom = coords::bottom(30) as f32;
+        let mut ball = ball_at((10.0, bottom), (0.0, 1.0));
+        ball.walls = walls;
+        game.balls = vec![ball];
+        assert_eq!(game.tick(), TickResult::Running);
+        assert_eq!(game.balls.len(), 1);
+        assert!(game.balls[0].vel.1 < 0.0);
+        assert_eq!(game.stats.balls_lost, 0);
+
+        let mut escaping = ball_at((50.0, coords::top() as f32), (0.2, -1.0));
+        escaping.walls = "left,right".parse().unwrap();
+        assert_eq!(update_in_open_field(&mut escaping, 1.0), BallUpdate::Lost);
+        let mut bouncing = ball_at((50.0, coords::top() as f32), (0.2, -1.0));
+        assert_eq!(update_in_open_field(&mut bouncing, 1.0), BallUpdate::Moving);
+        assert!(bouncing.vel.1 > 0.0);
+
+        assert!(!"none".parse::<Walls>().unwrap().left);
+        assert!("ceiling".parse::<Walls>().is_err());
+    }
+
+    #[test]
+    fn render_pass_draws_only_dirty_bricks() {
+        let mut game = captured_game(GameConfig::default());
+        game.cells = vec![
+            Cell::new((20, 5), 8, Box::new(color::Red), BrickKind::Normal),
+            Cell::new((40, 5), 8, Box::new(color::Red), BrickKind::Tough),
+            Cell::new((60, 5), 8, Box::new(color::Red), BrickKind::Normal),
+        ];
+        game.index_bricks();
+        assert!(game.bricks().all(|cell| cell.dirty));
+        game.draw_dirty_bricks();
+        assert!(game.bricks().all(|cell| !cell.dirty));
+
+        game.stdout.output.clear();
+        assert!(!game.cells[1].hit());
+        assert!(game.cells[1].dirty);
+        game.draw_dirty_bricks();
+        let text = game.stdout.text();
+        assert!(text.contains(&format!("{}", cursor::Goto(40, 5))));
+        assert!(!text.contains(&format!("{}", cursor::Goto(20, 5))));
+        assert!(!text.contains(&format!("{}", cursor::Goto(60, 5))));
+        assert_eq!(text.matches(graphics::BRICK_DAMAGED).count(), 8);
+
+        game.stdout.output.clear();
+        game.draw_dirty_bricks();
+        assert!(game.stdout.output.is_empty());
+    }
+
+    #[test]
+    fn screen_shake_decays_back_to_rest() {
+        let mut game = captured_game(GameConfig::default());
+        game.shake();
+        assert_eq!(game.shake_ticks, 0);
+
+        game.config.effects = true;
+        game.shake();
+        let mut offsets = vec![];
+        let mut repaints = 0;
+        while game.shake_ticks > 0 || game.stdout.offset() != 0 {
+            game.stdout.output.clear();
+            game.shake_frame();
+            offsets.push(game.stdout.offset());
+            if !game.stdout.output.is_empty() {
+                repaints += 1;
+            }
+            if offsets.len() == 1 {
+                // The whole scene is repainted two columns further right
+                let corner = format!("{}", cursor::Goto(3, 1));
+                assert!(game.stdout.text().contains(&corner));
+            }
+        }
+        assert_eq!(offsets, vec![2, 2, 1, 1, 0]);
+        // Only frames moving the playfield repaint it
+        assert_eq!(repaints, 3);
+
+        game.shake();
+        game.shake_frame();
+        game.stop_shake();
+        assert_eq!(game.shake_ticks, 0);
+        assert_eq!(game.stdout.offset(), 0);
+
+        // A game filling the terminal has no room to shake into
+        game.stdout.size = Some((game.width + 1, game.height));
+        game.shake();
+        game.shake_frame();
+        assert_eq!(game.stdout.offset(), 1);
+        game.stdout.size = Some((game.width, game.height));
+        game.stop_shake();
+        game.shake();
+        while game.shake_ticks > 0 {
+            game.stdout.output.clear();
+            game.shake_frame();
+            assert_eq!(game.stdout.offset(), 0);
+            assert!(game.stdout.output.is_empty());
+        }
+    }
+
+    #[test]
+    fn snapshot_round_trips_through_serde_and_restore() {
+        let config = |seed| GameConfig {
+            seed: Some(seed),
+            boss: true,
+            ..GameConfig::default()
+        };
+        let mut game = headless_game(104, 30, config(7));
+        game.reset_game();
+        game.score = 120;
+        game.lives = 3;
+        game.cells[0].hit();
+        game.balls[0].vel = (-0.4, 0.25);
+        game.peddles[0].jump_to(30.0, 104);
+        game.boss.as_mut().unwrap().hit();
+        game.config.level = Some(Level::parse("#T\n.X").unwrap());
+        let snapshot = game.snapshot();
+
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let parsed: GameSnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, snapshot);
+
+        let mut restored = headless_game(104, 30, config(8));
+        restored.reset_game();
+        restored.laser_ticks = 50;
+        restored.projectiles.push(Projectile {
+            game_pos: (50.0, 10.0),
+        });
+        restored.restore(&parsed);
+        assert_eq!(restored.snapshot(), snapshot);
+        // Effects of the game played before are dropped
+        assert_eq!(restored.laser_ticks, 0);
+        assert!(restored.projectiles.is_empty());
+        assert_eq!(restored.seed, 7);
+        assert_eq!(restored.config.level, game.config.level);
+        assert_eq!((restored.score, restored.lives), (120, 3));
+        assert_eq!(restored.peddles[0].x(), 30);
+        let original: Vec<_> = game
+            .bricks()
+            .map(|cell| (cell.pos, cell.get_glyph()))
+            .collect();
+        let loaded: Vec<_> = restored
+            .bricks()
+            .map(|cell| (cell.pos, cell.get_glyph()))
+            .collect();
+        assert_eq!(loaded, original);
+    }
+
+    #[test]
+    fn saving_and_loading_resumes_the_same_game() {
+        let path = env::temp_dir().join(format!("rbreakout-save-{}", std::process::id()));
+        let config = GameConfig {
+            save_path: path.clone(),
+            ..GameConfig::default()
+        };
+        let keys = io::Cursor::new(b"\x13".to_vec());
+        let mut game = BreakoutGame::new(keys, CaptureScreen::new(false), 104, 30, config);
+        game.reset_game();
+        game.state = GameState::Playing;
+        game.balls[0].game_pos = (33.0, 17.5);
+        game.score = 70;
+        game.cells.truncate(5);
+        game.index_bricks();
+        for _ in 0..7 {
+            game.rng.gen_range(0, 10);
+        }
+        assert!(game.update());
+        assert_eq!(game.toasts[0].text, "Game saved");
+        assert_eq!(KeyBindings::wasd().action(Key::Char('s')), None);
+
+        let saved = SavedGame::load(&path).unwrap();
+        assert_eq!(saved.size, (104, 30));
+        let mut resumed = headless_game(104, 30, GameConfig::default());
+        resumed.reset_game();
+        resumed.restore(&saved.game);
+        assert_eq!(resumed.balls[0].game_pos, (33.0, 17.5));
+        assert_eq!(resumed.score, 70);
+        assert_eq!(resumed.brick_count(), 5);
+        assert_eq!(resumed.seed, game.seed);
+        // Power-ups drop as they would have in the saved game
+        assert_eq!(resumed.rng.draws, 7);
+        let upcoming: Vec<u32> = (0..5).map(|_| game.rng.gen_range(0, 100)).collect();
+        let resumed_upcoming: Vec<u32> = (0..5).map(|_| resumed.rng.gen_range(0, 100)).collect();
+        assert_eq!(resumed_upcoming, upcoming);
+
+        std::fs::write(&path, "{\"version\": 99}").unwrap();
+        let error = SavedGame::load(&path).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("version 99"));
+        std::fs::write(&path, "best_time 95").unwrap();
+        assert_eq!(
+            SavedGame::load(&path).err().unwrap().to_string(),
+            "not a saved game"
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn too_small_message_reports_the_size_and_what_would_fit() {
+        let config = GameConfig::default();
+        let min_size = config.min_size();
+        assert_eq!(
+            min_size,
+            (GameConfig::get_min_width(), GameConfig::get_min_height())
+        );
+        assert_eq!(too_small_message((104, 30), min_size), None);
+
+        let message = too_small_message((20, 30), min_size).unwrap();
+        assert!(message.contains("20x30"));
+        assert!(message.contains(&format!("{}x{}", min_size.0, min_size.1)));
+        assert!(message.contains(&format!("--width {} --height 30", min_size.0)));
+
+        let tall_grid = GameConfig {
+            rows: Some(20),
+            cols: Some(12),
+            ..GameConfig::default()
+        };
+        let (width, height) = tall_grid.min_size();
+        assert_eq!((width, height), (112, 30));
+        assert!(tall_grid.grid_dimensions((width, height)).is_ok());
+        assert!(tall_grid.grid_dimensions((width, height - 1)).is_err());
+        let message = too_small_message((104, 24), (width, height)).unwrap();
+        assert!(message.contains("104x24"));
+        assert!(message.contains("--width 112 --height 30"));
+    }
+
+    #[test]
+    fn each_third_of_the_peddle_sends_the_ball_its_own_way() {
+        let peddles = [peddle_at(40.0)];
+        let speed = ball_at((0.0, 0.0), (0.0, 0.0)).speed;
+        // The peddle is 12 wide, so its thirds start at 40, 44 and 48
+        for &(x, incoming, expected) in &[
+            (41.0, 0.3, (-1f32, -1f32)),
+            (45.0, 0.3, (0.5, -1.0)),
+            (45.0, -0.3, (-0.5, -1.0)),
+            (49.5, -0.3, (1.0, -1.0)),
+        ] {
+            let mut ball = ball_at((x, 27.2), (incoming, 0.5));
+            assert_eq!(
+                ball.update((104, 30), &peddles, None, 1.0),
+                BallUpdate::HitPeddle(0)
+            );
+            let norm = (expected.0 * expected.0 + expected.1 * expected.1).sqrt();
+            assert!(
+                (ball.vel.0 - expected.0 / norm * speed).abs() < 1e-5,
+                "x {}",
+                x
+            );
+            assert!(
+                (ball.vel.1 - expected.1 / norm * speed).abs() < 1e-5,
+                "x {}",
+                x
+            );
+        }
+    }
+
+    #[test]
+    fn frame_log_writes_a_line_per_simulated_frame() {
+        let path = env::temp_dir().join(format!("rbreakout-log-{}", std::process::id()));
+        let config = GameConfig {
+            seed: Some(3),
+            log_file: Some(File::create(&path).unwrap()),
+            ..GameConfig::default()
+        };
+        let mut game = headless_game(104, 30, config);
+        game.reset_game();
+        let bricks = game.brick_count();
+        let start = (
+            game.balls[0].game_pos,
+            game.balls[0].vel,
+            game.peddles[0].game_x,
+        );
+        assert_eq!(game.simulate(3), (3, TickResult::Running));
+        drop(game);
+
+        let log = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let lines: Vec<&str> = log.lines().collect();
+        assert_eq!(lines.len(), 3);
+        let ((x, y), (vx, vy), peddle_x) = start;
+        assert_eq!(
+            lines[0],
+            format!(
+                "frame 0 ball {:.2} {:.2} vel {:.3} {:.3} peddle {:.1} bricks {}",
+                x, y, vx, vy, peddle_x, bricks
+            )
+        );
+        for (frame, line) in lines.iter().enumerate() {
+            let fields: Vec<&str> = line.split(' ').collect();
+            assert_eq!(fields.len(), 12);
+            assert_eq!(fields[..2], ["frame", &frame.to_string()[..]]);
+            assert_eq!(
+                (fields[2], fields[5], fields[8], fields[10]),
+                ("ball", "vel", "peddle", "bricks")
+            );
+        }
+    }
+
+    #[test]
+    fn laser_shot_destroys_the_brick_above_and_disappears() {
+        let keys = io::Cursor::new(b" ".to_vec());
+        let mut game = BreakoutGame::new(
+            keys,
+            CaptureScreen::new(false),
+            104,
+            30,
+            GameConfig::default(),
+        );
+        game.reset_game();
+        game.state = GameState::Playing;
+        game.peddles[0].jump_to(40.0, 104);
+        let above = (40 + game.peddles[0].get_width() / 2) as Unit;
+        game.cells = vec![
+            Cell::new((above - 4, 10), 8, Box::new(color::Red), BrickKind::Normal),
+            Cell::new((above - 4, 5), 8, Box::new(color::Red), BrickKind::Normal),
+            Cell::new((80, 5), 8, Box::new(color::Red), BrickKind::Normal),
+        ];
+        game.index_bricks();
+        game.balls = vec![ball_at((90.0, 20.0), (0.0, 0.0))];
+        game.activate_power_up(PowerUpKind::Laser);
+        assert!(game.update());
+        assert_eq!(game.projectiles.len(), 1);
+        // The cooldown keeps a second press from firing right away
+        game.fire_laser(0);
+        assert_eq!(game.projectiles.len(), 1);
+
+        for _ in 0..30 {
+            if game.projectiles.is_empty() {
+                break;
+            }
+            game.tick();
+        }
+        assert!(game.projectiles.is_empty());
+        let left: Vec<_> = game.bricks().map(|cell| cell.pos).collect();
+        assert_eq!(left, vec![(above - 4, 5), (80, 5)]);
+    }
+
+    #[test]
+    fn ball_starts_centered_above_the_peddle() {
+        for &(width, difficulty) in &[
+            (64, Difficulty::Easy),
+            (104, Difficulty::Normal),
+            (160, Difficulty::Hard),
+        ] {
+            let config = GameConfig {
+                difficulty,
+                ..GameConfig::default()
+            };
+            let (ball, peddles, _) =
+                BreakoutGame::<io::Empty, CaptureScreen>::get_start_values(width, 30, &config, 1);
+            let peddle = &peddles[0];
+            assert_eq!(ball.x(), peddle.x() + peddle.get_width() / 2);
+            assert_eq!(peddle.x() + peddle.get_width() / 2, width / 2);
+            assert_eq!(peddle.y() - ball.y(), difficulty.get_start_gap());
+        }
+
+        let config = GameConfig {
+            start_gap: Some(3),
+            ball_size: 2,
+            ..GameConfig::default()
+        };
+        let (ball, peddles, cells) =
+            BreakoutGame::<io::Empty, CaptureScreen>::get_start_values(104, 30, &config, 1);
+        assert_eq!(ball.x(), peddles[0].x() + peddles[0].get_width() / 2 - 1);
+        assert_eq!(peddles[0].y() - ball.y(), 3);
+        assert!(cells.iter().all(|cell| cell.y() < ball.y()));
+    }
+
+    #[test]
+    fn skipping_the_intro_starts_in_playing() {
+        let mut game = headless_game(104, 30, GameConfig::default());
+        game.reset_game();
+        assert_eq!(
+            game.state,
+            GameState::Countdown(BreakoutGame::<io::Empty, CaptureScreen>::get_countdown_ticks())
+        );
+
+        let config = GameConfig {
+            skip_intro: true,
+            ..GameConfig::default()
+        };
+        let mut game = headless_game(104, 30, config);
+        game.reset_game();
+        assert_eq!(game.state, GameState::Playing);
+        assert!(game.stopwatch.running_since.is_some());
+    }
+
+    #[test]
+    fn descending_bricks_move_down_and_end_the_game_at_the_peddle() {
+        let config = GameConfig {
+            descend_secs: 1,
+            ..GameConfig::default()
+        };
+        let mut game = headless_game(104, 30, config);
+        game.reset_game();
+        game.cells = vec![Cell::new(
+            (20, 5),
+            8,
+            Box::new(color::Red),
+            BrickKind::Solid,
+        )];
+        game.cells.push(Cell::new(
+            (80, 5),
+            8,
+            Box::new(color::Red),
+            BrickKind::Normal,
+        ));
+        game.index_bricks();
+        game.balls = vec![ball_at((50.0, 15.0), (0.0, 0.0))];
+        let interval = game.descend_interval();
+        assert!(interval > 1);
+        for _ in 1..interval {
+            assert_eq!(game.tick(), TickResult::Running);
+        }
+        assert_eq!(game.cells[0].y(), 5);
+        assert_eq!(game.tick(), TickResult::Running);
+        assert_eq!(game.cells[0].y(), 6);
+        assert_eq!(game.cells[1].y(), 6);
+
+        // The solid brick cannot be cleared, so it goes instead of ending the game
+        let peddle_row = game.peddles[0].y();
+        game.cells[0].pos.1 = peddle_row - 1;
+        game.index_bricks();
+        game.descend_ticks = 1;
+        assert_eq!(game.tick(), TickResult::Running);
+        assert_eq!(game.cells.len(), 1);
+        assert_eq!(game.cells[0].kind, BrickKind::Normal);
+
+        game.cells[0].pos.1 = peddle_row - 1;
+        game.index_bricks();
+        game.descend_ticks = 1;
+        assert_eq!(game.tick(), TickResult::Overrun);
+        assert_eq!(game.cells[0].y(), peddle_row);
+    }
+
+    #[test]
+    fn game_is_drawn_in_the_alternate_screen() {
+        let mut terminal = fake_terminal(false);
+        terminal.enter().unwrap();
+        terminal.clear_all();
+        terminal.leave().unwrap();
+        let output = String::from_utf8(terminal.output.clone()).unwrap();
+        let enter = format!("{}", ToAlternateScreen);
+        let exit = format!("{}", ToMainScreen);
+        assert!(output.starts_with(&enter));
+        assert!(output.ends_with(&exit));
+        // Clearing happens in the alternate screen, not over the shell
+        let clear = output.find(&format!("{}", clear::All)).unwrap();
+        assert!(output.find(&enter).unwrap() < clear && clear < output.find(&exit).unwrap());
+    }
+
+    #[test]
+    fn ball_count_follows_the_balls_in_play_and_repaints_on_change() {
+        let mut game = captured_game(GameConfig::default());
+        game.reset_game();
+        game.stdout.output.clear();
+        game.draw_ball_count();
+        assert_eq!(game.balls_label, " balls  1 ");
+        assert!(game.stdout.text().contains(" balls  1 "));
+
+        game.stdout.output.clear();
+        game.draw_ball_count();
+        assert!(game.stdout.output.is_empty());
+
+        let extra = game.balls[0].spawn_at((50.0, 10.0));
+        game.balls.push(extra);
+        let extra = game.balls[0].spawn_at((60.0, 10.0));
+        game.balls.push(extra);
+        game.draw_ball_count();
+        assert_eq!(game.balls_label, " balls  3 ");
+        assert!(game.stdout.text().contains(" balls  3 "));
+
+        game.balls.truncate(2);
+        game.draw_ball_count();
+        assert_eq!(game.balls_label, format!(" balls {:2} ", game.balls.len()));
+    }
+
+    #[test]
+    fn soft_reset_keeps_the_bricks_and_hard_reset_regenerates_them() {
+        let config = GameConfig {
+            seed: Some(5),
+            ..GameConfig::default()
+        };
+        let keys = io::Cursor::new(b"e".to_vec());
+        let mut game = BreakoutGame::new(keys, CaptureScreen::new(false), 104, 30, config);
+        game.reset_game();
+        let full_grid = game.brick_count();
+        let (start_ball, start_peddle) = (game.balls[0].game_pos, game.peddles[0].game_x);
+        game.state = GameState::Playing;
+        game.cells.truncate(full_grid - 4);
+        game.index_bricks();
+        game.score = 40;
+        game.balls[0].game_pos = (70.0, 12.0);
+        game.peddles[0].jump_to(2.0, 104);
+        game.sticky_ticks = 50;
+        game.sticky_catches = 2;
+        game.laser_ticks = 50;
+        game.safety_net_ticks = 50;
+        game.mercy_ticks = 50;
+        game.nudges_left = 0;
+        game.shake_ticks = 3;
+        game.stdout.set_offset(2);
+
+        assert!(game.update());
+        assert_eq!(game.brick_count(), full_grid - 4);
+        assert_eq!(game.score, 40);
+        assert_eq!(game.balls[0].game_pos, start_ball);
+        assert_eq!(game.peddles[0].game_x, start_peddle);
+        assert_eq!((game.sticky_ticks, game.sticky_catches), (0, 0));
+        assert_eq!((game.laser_ticks, game.safety_net_ticks), (0, 0));
+        assert_eq!((game.mercy_ticks, game.shake_ticks), (0, 0));
+        assert_eq!(game.nudges_left, game.config.nudges);
+        assert_eq!(game.stdout.offset(), 0);
+
+        game.stdin = io::Cursor::new(b"r".to_vec());
+        game.state = GameState::Playing;
+        assert!(game.update());
+        assert_eq!(game.brick_count(), full_grid);
+        assert_eq!(game.score, 0);
+    }
+
+    #[test]
+    fn grace_row_saves_a_ball_that_slipped_below_the_peddle() {
+        let peddles = [peddle_at(40.0)];
+        let bottom = coords::bottom(30) as f32;
+        let mut ball = ball_at((45.0, bottom), (0.2, 0.5));
+        assert_eq!(
+            ball.update((104, 30), &peddles, None, 1.0),
+            BallUpdate::Lost
+        );
+
+        let mut ball = ball_at((45.0, bottom), (0.2, 0.5));
+        ball.grace = true;
+        assert_eq!(
+            ball.update((104, 30), &peddles, None, 1.0),
+            BallUpdate::HitPeddle(0)
+        );
+        assert!(ball.vel.1 < 0.0);
+        assert!(ball.y() < peddles[0].y());
+
+        // Missing the peddle entirely is still a loss
+        let mut ball = ball_at((80.0, bottom), (0.2, 0.5));
+        ball.grace = true;
+        assert_eq!(
+            ball.update((104, 30), &peddles, None, 1.0),
+            BallUpdate::Lost
+        );
+    }
+
+    #[test]
+    fn telemetry_counts_bounces_colors_combos_and_peddle_hits() {
+        let mut game = headless_game(104, 30, GameConfig::default());
+        game.cells = vec![
+            Cell::colored((40, 5), 8, BrickKind::Normal, NamedColor::Red),
+            Cell::colored((80, 5), 8, BrickKind::Normal, NamedColor::Blue),
+            Cell::colored((60, 5), 8, BrickKind::Normal, NamedColor::Blue),
+        ];
+        game.index_bricks();
+        game.balls = vec![ball_at((44.0, 6.0), (0.0, -1.0))];
+        game.tick();
+        assert_eq!(game.stats().bricks_of(NamedColor::Red), 1);
+        assert_eq!(game.stats().bounces(), 1);
+        assert_eq!(game.stats().longest_combo(), 1);
+
+        game.balls = vec![ball_at((84.0, 6.0), (0.0, -1.0))];
+        game.tick();
+        assert_eq!(game.stats().bricks_of(NamedColor::Blue), 1);
+        assert_eq!(game.stats().longest_combo(), 2);
+
+        let pos = (
+            (game.peddles[0].x() + 2) as f32,
+            (game.peddles[0].y() - 2) as f32,
+        );
+        game.balls = vec![ball_at(pos, (0.0, 1.0))];
+        for _ in 0..10 {
+            if game.stats().peddle_hits() > 0 {
+                break;
             }
+            game.tick();
         }
-        return cells;
+        assert_eq!(game.stats().peddle_hits(), 1);
+        assert_eq!(game.stats().bounces(), 3);
+
+        // The peddle hit ended the combo, a single brick does not beat it
+        game.balls = vec![ball_at((64.0, 6.0), (0.0, -1.0))];
+        game.tick();
+        assert_eq!(game.stats().bricks_of(NamedColor::Blue), 2);
+        assert_eq!(game.stats().longest_combo(), 2);
+
+        game.balls = vec![ball_at((coords::left() as f32, 15.0), (-1.0, 0.0))];
+        game.tick();
+        assert_eq!(game.stats().bounces(), 5);
+        assert_eq!(game.stats().top_color(), Some(NamedColor::Blue));
+
+        game.reset_game();
+        assert_eq!(game.stats().bounces(), 0);
+        assert_eq!(game.stats().bricks_of(NamedColor::Blue), 0);
+        assert_eq!(game.stats().longest_combo(), 0);
+        assert_eq!(game.stats().peddle_hits(), 0);
     }
-}
 
-fn init(width: Unit, height: Unit) {
-    let stdout = stdout();
-    let stdout = stdout.lock().into_raw_mode().unwrap();
-    let stdin = async_stdin();
-    let mut game = BreakoutGame::new(stdin, stdout, width, height);
-    game.run();
-}
+    #[test]
+    fn spin_decay_eases_the_horizontal_velocity_towards_the_baseline() {
+        let config = GameConfig {
+            spin_decay: 1.0,
+            ..GameConfig::default()
+        };
+        assert!(config.check((104, 30)).is_ok());
+        let mut game = headless_game(104, 30, config);
+        game.cells = vec![];
+        game.index_bricks();
+        let mut ball =
+            BreakoutGame::<std::io::Empty, CaptureScreen>::new_ball((50.0, 27.0), &game.config);
+        ball.vel = (2.0, -0.5);
+        ball.normalize_vel();
+        let speed = ball.speed;
+        let baseline = speed * std::f32::consts::FRAC_1_SQRT_2;
+        game.balls = vec![ball];
 
-fn main() {
-    // Store default game size
-    let mut width = Cell::get_width() * 13;
-    let mut height = 30;
+        // Most of the spin is gone after a second
+        let mut previous = game.balls[0].vel.0;
+        for _ in 0..1000 / FRAME_MILLIS {
+            game.tick();
+            let vel = game.balls[0].vel;
+            assert!(vel.0 < previous && vel.0 > baseline);
+            assert!(vel.1 < 0.0);
+            assert!(((vel.0 * vel.0 + vel.1 * vel.1).sqrt() - speed).abs() < 1e-4);
+            previous = vel.0;
+        }
+        assert!(previous - baseline < 0.5 * (0.97 * speed - baseline));
 
-    let mut auto_scale_to_terminal = false;
+        // A steep shot off the center of the peddle keeps its angle
+        let mut steep = ball_at((50.0, 26.0), (0.0, 1.0));
+        steep.spin_decay = 0.5;
+        let peddle = peddle_at(45.0);
+        steep.aim_from(&peddle);
+        let shot = steep.vel;
+        assert!(shot.0 > 0.0 && shot.0 < 0.5 * speed);
+        update_in_open_field(&mut steep, 1.0);
+        assert!((steep.vel.0 - shot.0).abs() < 1e-6);
+        // And only loses the spin added to it since
+        steep.vel.0 += 0.1;
+        steep.normalize_vel();
+        for _ in 0..20 {
+            update_in_open_field(&mut steep, 1.0);
+        }
+        assert!(steep.vel.0 > shot.0 && steep.vel.0 - shot.0 < 0.01);
 
-    {
-        // this block limits scope of borrows by ap.refer() method
+        let too_strong = GameConfig {
+            spin_decay: 5.0,
+            ..GameConfig::default()
+        };
+        assert!(too_strong.check((104, 30)).is_err());
 
-        let mut ap = ArgumentParser::new();
-        ap.set_description("A simple breakout clone built with rust, playable in the terminal");
+        let mut steady = headless_game(104, 30, GameConfig::default());
+        steady.cells = vec![];
+        steady.index_bricks();
+        let mut ball =
+            BreakoutGame::<std::io::Empty, CaptureScreen>::new_ball((50.0, 15.0), &steady.config);
+        ball.vel = (2.0, -0.5);
+        ball.normalize_vel();
+        let vel = ball.vel;
+        steady.balls = vec![ball];
+        steady.tick();
+        assert_eq!(steady.balls[0].vel, vel);
+    }
 
-        ap.refer(&mut width).add_option(
-            &["-w", "--width"],
-            Store,
-            "Preferable game width greater than 32,
-                 gets scaled according to the in-game cell width",
+    #[test]
+    fn exported_result_reads_back_as_the_final_game() {
+        let path = env::temp_dir().join(format!("rbreakout-result-{}", std::process::id()));
+        let config = GameConfig {
+            seed: Some(5),
+            result_path: Some(path.clone()),
+            ..GameConfig::default()
+        };
+        let mut game = headless_game(104, 30, config);
+        game.reset_game();
+        game.score = 90;
+        game.cells.truncate(3);
+        game.index_bricks();
+        game.export_result(false).unwrap();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        let result: GameResult = serde_json::from_str(&text).unwrap();
+        assert_eq!(result, game.result(false));
+        assert_eq!(result.seed, 5);
+        assert!(result.level.is_none() && !result.won);
+        assert_eq!((result.score, result.lives), (90, game.lives));
+        assert_eq!(result.bricks.len(), 3);
+        assert_eq!(result.bricks_remaining, game.remaining_breakable());
+        std::fs::remove_file(&path).unwrap();
+
+        game.config.level = Some(Level::parse("#T\n.X").unwrap());
+        game.export_result(true).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        let result: GameResult = serde_json::from_str(&text).unwrap();
+        assert_eq!(result.level, game.config.level);
+        std::fs::remove_file(&path).unwrap();
+
+        game.config.result_path = Some(env::temp_dir().join("rbreakout-missing").join("result"));
+        let error = game.export_result(true).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn damaged_bricks_take_the_configured_color_for_their_hits_left() {
+        let fg = |shade: &dyn color::Color| format!("{}", color::Fg(shade));
+        let damage_colors: DamageColors = "cyan,magenta".parse().unwrap();
+        let mut tough = Cell::colored((10, 5), 8, BrickKind::Tough, NamedColor::Blue);
+        tough.damage_colors = Some(Rc::new(damage_colors.clone()));
+        assert_eq!(fg(tough.get_color()), fg(&color::Blue));
+        tough.hit();
+        assert_eq!(tough.hits_left, 1);
+        assert_eq!(fg(tough.get_color()), fg(&color::Cyan));
+        assert_eq!(damage_colors.get(2), Some(NamedColor::Magenta));
+        assert_eq!(damage_colors.get(3), None);
+
+        // Every brick of a layout shares the configured colors
+        let config = GameConfig {
+            damage_colors,
+            ..GameConfig::default()
+        };
+        let cells =
+            BreakoutGame::<io::Empty, CaptureScreen>::generate_cell_grid((104, 30), &config, 5);
+        let shared = cells[0].damage_colors.clone().unwrap();
+        assert_eq!(*shared, config.damage_colors);
+        assert!(cells
+            .iter()
+            .all(|cell| Rc::ptr_eq(cell.damage_colors.as_ref().unwrap(), &shared)));
+
+        let default = DamageColors::default();
+        assert_eq!(default.get(1), Some(NamedColor::Red));
+        assert_eq!(default.get(0), None);
+        let none: DamageColors = "none".parse().unwrap();
+        assert_eq!(none.get(1), None);
+        assert!("red,pink".parse::<DamageColors>().is_err());
+    }
+
+    #[test]
+    fn peddle_dips_for_a_frame_when_the_ball_bounces_off_it() {
+        let hit_peddle = |effects| {
+            let config = GameConfig {
+                effects,
+                ..GameConfig::default()
+            };
+            let mut game = headless_game(104, 30, config);
+            game.cells = vec![];
+            game.index_bricks();
+            let pos = (
+                (game.peddles[0].x() + 2) as f32,
+                (game.peddles[0].y() - 2) as f32,
+            );
+            game.balls = vec![ball_at(pos, (0.0, 1.0))];
+            for _ in 0..10 {
+                if game.stats.peddle_hits > 0 {
+                    break;
+                }
+                game.tick();
+            }
+            game
+        };
+
+        let mut game = hit_peddle(true);
+        let peddle = &game.peddles[0];
+        assert!(peddle.bounce_ticks > 0);
+        assert_eq!(peddle.get_draw_pos(0), (peddle.x(), peddle.y() + 1));
+        game.balls = vec![ball_at((50.0, 10.0), (0.0, -1.0))];
+        game.tick();
+        let peddle = &game.peddles[0];
+        assert_eq!(peddle.bounce_ticks, 0);
+        assert_eq!(peddle.get_draw_pos(0), (peddle.x(), peddle.y()));
+
+        assert_eq!(hit_peddle(false).peddles[0].bounce_ticks, 0);
+    }
+
+    #[test]
+    fn grid_share_caps_how_far_down_the_bricks_reach() {
+        for &(height, gap) in &[(30, 0), (48, 1), (80, 0)] {
+            let config = GameConfig {
+                grid_share: Some(0.25),
+                brick_gap: gap,
+                ..GameConfig::default()
+            };
+            let limit = GameConfig::get_grid_top() + height / 4;
+            let cells = BreakoutGame::<std::io::Empty, CaptureScreen>::generate_cell_grid(
+                (104, height),
+                &config,
+                3,
+            );
+            assert!(!cells.is_empty());
+            assert!(cells.iter().all(|cell| cell.y() < limit));
+        }
+
+        let explicit = GameConfig {
+            grid_share: Some(0.25),
+            rows: Some(9),
+            ..GameConfig::default()
+        };
+        assert!(explicit.grid_dimensions((104, 30)).is_err());
+        assert!(explicit.grid_dimensions(explicit.min_size()).is_ok());
+        let invalid = GameConfig {
+            grid_share: Some(1.5),
+            ..GameConfig::default()
+        };
+        assert!(invalid.grid_dimensions((104, 30)).is_err());
+    }
+
+    #[test]
+    fn speed_keys_change_the_ball_speed_within_the_limits() {
+        let config = GameConfig {
+            speed_limits: SpeedLimits { min: 0.5, max: 1.5 },
+            ..GameConfig::default()
+        };
+        let keys = io::Cursor::new(b"x".to_vec());
+        let mut game = BreakoutGame::new(keys, CaptureScreen::new(false), 104, 30, config);
+        game.state = GameState::Playing;
+        let press = |game: &mut BreakoutGame<io::Cursor<Vec<u8>>, CaptureScreen>, key: &[u8]| {
+            game.stdin = io::Cursor::new(key.to_vec());
+            assert!(game.update());
+        };
+
+        press(&mut game, b"+");
+        assert_eq!(game.speed_multiplier, 1.25);
+        assert_eq!(game.toasts.back().unwrap().text, "Speed x1.25");
+        for _ in 0..4 {
+            press(&mut game, b"+");
+        }
+        assert_eq!(game.speed_multiplier, 1.5);
+        assert_eq!(game.ball_time_scale(), 1.5);
+
+        game.reset_game();
+        assert_eq!(game.speed_multiplier, 1.5);
+        game.state = GameState::Playing;
+        for _ in 0..10 {
+            press(&mut game, b"-");
+        }
+        assert_eq!(game.speed_multiplier, 0.5);
+    }
+
+    #[test]
+    fn peddle_width_below_two_columns_is_rejected() {
+        let width = |peddle_width| GameConfig {
+            peddle_width,
+            ball_size: 3,
+            ..GameConfig::default()
+        };
+        assert!(GameConfig::default().check_peddle_width(104).is_ok());
+        assert!(width(2).check_peddle_width(104).is_ok());
+        let message = width(0).check_peddle_width(104).err().unwrap().to_string();
+        assert!(message.contains("at least 2 columns"));
+        assert!(width(1).check_peddle_width(104).is_err());
+        assert!(width(200).check_peddle_width(104).is_err());
+
+        // The start position and aiming stay defined even without validation
+        let mut game = headless_game(104, 30, width(0));
+        assert_eq!(game.peddles[0].get_width(), 0);
+        let right_of = game.peddles[0].game_x + 1.0;
+        assert_eq!(game.peddles[0].third_at(right_of), Some(Direction::Right));
+        game.tick();
+    }
+
+    #[test]
+    fn progress_is_the_share_of_destroyed_breakable_bricks() {
+        let config = GameConfig {
+            progress: true,
+            ..GameConfig::default()
+        };
+        let mut game = headless_game(104, 30, config);
+        let initial = game.remaining_breakable();
+        assert_eq!(game.initial_breakable, initial);
+        assert_eq!(game.progress(), 0.0);
+
+        let target = game
+            .cells
+            .iter()
+            .position(|cell| cell.kind == BrickKind::Normal)
+            .unwrap();
+        let cell = &game.cells[target];
+        let pos = ((cell.x() + 2) as f32, (cell.y() + 1) as f32);
+        game.balls = vec![ball_at(pos, (0.0, -1.0))];
+        game.tick();
+        assert_eq!(game.remaining_breakable(), initial - 1);
+        assert_eq!(game.progress(), 1.0 / initial as f32);
+
+        let mut destroyed = 1;
+        while destroyed < initial / 2 {
+            let index = game
+                .cells
+                .iter()
+                .position(|cell| cell.is_breakable())
+                .unwrap();
+            game.cells.remove(index);
+            destroyed += 1;
+        }
+        game.index_bricks();
+        assert_eq!(game.progress(), destroyed as f32 / initial as f32);
+        game.stdout.output.clear();
+        game.draw_progress();
+        let percent = destroyed * 100 / initial;
+        assert!(game.stdout.text().contains(&format!("{:3}% ", percent)));
+        game.stdout.output.clear();
+        game.draw_progress();
+        assert!(game.stdout.output.is_empty());
+
+        game.reset_game();
+        assert_eq!(game.progress(), 0.0);
+    }
+
+    #[test]
+    fn custom_title_is_shown_in_the_start_banner() {
+        assert_eq!(
+            Menu::header_lines(graphics::MENU_TITLE)[1],
+            "║──   Welcome to rbreakout   ──║"
         );
 
-        ap.refer(&mut height).add_option(
-            &["-h", "--height"],
-            Store,
-            "Preferable game height greater than 20",
+        let config = GameConfig {
+            title: "Arcade Hall".to_string(),
+            ..GameConfig::default()
+        };
+        assert!(config.check_title().is_ok());
+        let mut game = headless_game(104, 30, config);
+        game.stdout.output.clear();
+        game.draw_menu(&Menu::new());
+        let text = game.stdout.text();
+        assert!(text.contains("║──       Arcade Hall        ──║"));
+        assert!(!text.contains(graphics::MENU_TITLE));
+        assert!(text.contains("Controls"));
+
+        let too_long = GameConfig {
+            title: "x".repeat(Menu::get_max_title_width() + 1),
+            ..GameConfig::default()
+        };
+        assert!(too_long.check_title().is_err());
+    }
+
+    #[test]
+    fn ball_bounces_off_the_walls_of_a_funnel_field() {
+        let size = (104, 30);
+        let walls = FieldShape::Funnel.wall_cells(size);
+        assert!(!walls.is_empty());
+        assert!(FieldShape::Rectangle.wall_cells(size).is_empty());
+        let peddle_row = coords::bottom(size.1) - 1;
+        assert!(walls.iter().all(|&(_, y)| y < peddle_row - 1));
+
+        // Falling straight onto the top of the left funnel wall
+        let &(x, y) = walls.iter().find(|&&(x, _)| x == coords::left()).unwrap();
+        let mut ball = ball_at((x as f32 + 1.0, y as f32 - 0.7), (0.0, 0.4));
+        ball.field = FieldShape::Funnel;
+        assert_eq!(
+            ball.update(size, &[peddle_at(40.0)], None, 1.0),
+            BallUpdate::Moving
         );
+        assert!(ball.vel.1 < 0.0);
+        assert!(!FieldShape::Funnel.is_wall(ball.get_pos(), size));
+        assert_eq!(ball.bounces, 1);
 
-        ap.refer(&mut auto_scale_to_terminal).add_option(
-            &["-f", "--fill"],
-            StoreTrue,
-            "Fill game to current terminal size",
+        let mut open = ball_at((x as f32 + 1.0, y as f32 - 0.7), (0.0, 0.4));
+        open.update(size, &[peddle_at(40.0)], None, 1.0);
+        assert!(open.vel.1 > 0.0);
+
+        let config = GameConfig {
+            field: "funnel".parse().unwrap(),
+            ..GameConfig::default()
+        };
+        let mut game = headless_game(104, 30, config);
+        game.stdout.output.clear();
+        game.draw_game_borders();
+        assert_eq!(
+            game.stdout.text().matches(graphics::BORDER_FILL).count(),
+            walls.len()
         );
+        assert!("circle".parse::<FieldShape>().is_err());
+    }
 
-        ap.parse_args_or_exit();
+    #[test]
+    fn reserve_balls_show_one_glyph_per_life() {
+        let config = GameConfig {
+            reserve_balls: true,
+            ..GameConfig::default()
+        };
+        let mut game = headless_game(104, 30, config);
+        game.lives = 3;
+        let glyphs = |game: &mut BreakoutGame<io::Empty, CaptureScreen>| {
+            game.stdout.output.clear();
+            game.draw_lives();
+            game.stdout.text().matches(graphics::BALL_GRAPHIC).count()
+        };
+        assert_eq!(glyphs(&mut game), game.lives as usize);
+        let width = display_width(&game.lives_label());
+
+        let lives = game.lives;
+        let bottom = (coords::bottom(30) - 1) as f32 + 0.9;
+        game.balls = vec![ball_at((10.0, bottom), (0.0, 1.0))];
+        game.tick();
+        assert_eq!(game.lives, lives - 1);
+        assert_eq!(glyphs(&mut game), game.lives as usize);
+        assert_eq!(display_width(&game.lives_label()), width);
+
+        game.lives = BreakoutGame::<io::Empty, CaptureScreen>::get_max_lives();
+        assert_eq!(glyphs(&mut game), game.lives as usize);
+        assert_eq!(display_width(&game.lives_label()), width);
     }
 
-    if auto_scale_to_terminal {
-        let terminal_size = termion::terminal_size();
-        match terminal_size {
-            Ok(size) => {
-                width = size.0 as usize;
-                height = size.1 as usize;
+    #[test]
+    fn a_burst_of_moves_in_one_frame_is_capped() {
+        let keys = io::Cursor::new(b"x".to_vec());
+        let mut game = BreakoutGame::new(
+            keys,
+            CaptureScreen::new(false),
+            104,
+            30,
+            GameConfig::default(),
+        );
+        game.state = GameState::Playing;
+        let start = game.peddles[0].target_x;
+        let max_step = game.config.max_peddle_step as f32;
+
+        game.stdin = io::Cursor::new(b"llllllll".to_vec());
+        assert!(game.update());
+        assert_eq!(game.peddles[0].target_x, start + max_step);
+
+        // The next frame may move again, back and forth within the cap
+        game.stdin = io::Cursor::new(b"hlhhhhhhh".to_vec());
+        assert!(game.update());
+        assert_eq!(game.peddles[0].target_x, start);
+
+        game.stdin = io::Cursor::new(b"h".to_vec());
+        assert!(game.update());
+        assert_eq!(game.peddles[0].target_x, start - Peddle::get_speed() as f32);
+    }
+
+    #[test]
+    fn bricks_colored_by_row_share_the_row_color() {
+        let config = GameConfig {
+            brick_colors: "rows".parse().unwrap(),
+            ..GameConfig::default()
+        };
+        let cells = BreakoutGame::<std::io::Empty, CaptureScreen>::generate_cell_grid(
+            (100, 30),
+            &config,
+            7,
+        );
+        let row_colors = BrickColors::get_row_colors();
+        let step = 1 + config.brick_gap;
+        for cell in cells.iter().filter(|cell| cell.named_color.is_some()) {
+            let row = (cell.pos.1 - GameConfig::get_grid_top()) / step;
+            assert_eq!(cell.named_color, Some(row_colors[row % row_colors.len()]));
+        }
+
+        // The kinds are the same as with random colors
+        let random = BreakoutGame::<std::io::Empty, CaptureScreen>::generate_cell_grid(
+            (100, 30),
+            &GameConfig::default(),
+            7,
+        );
+        let kinds = |cells: &[Cell]| cells.iter().map(|cell| cell.kind).collect::<Vec<_>>();
+        assert_eq!(kinds(&cells), kinds(&random));
+
+        let custom: BrickColors = "rows=blue, red".parse().unwrap();
+        assert_eq!(custom.row_color(3), Some(NamedColor::Red));
+        assert!("columns".parse::<BrickColors>().is_err());
+    }
+
+    #[test]
+    fn a_longer_charge_launches_faster_up_to_the_cap() {
+        let launch_speed = |held_frames: u32| {
+            let config = GameConfig {
+                charge_launch: true,
+                ..GameConfig::default()
+            };
+            let keys = io::Cursor::new(b"x".to_vec());
+            let mut game = BreakoutGame::new(keys, CaptureScreen::new(false), 104, 30, config);
+            game.state = GameState::Playing;
+            game.respawn_ball();
+            for _ in 0..held_frames {
+                game.stdin = io::Cursor::new(b" ".to_vec());
+                assert!(game.update());
+                game.tick();
+            }
+            assert!(
+                game.balls[0].stuck_offset.is_some(),
+                "held until the key is let go"
+            );
+            for _ in
+                0..=BreakoutGame::<io::Cursor<Vec<u8>>, CaptureScreen>::get_charge_release_ticks()
+            {
+                game.tick();
+            }
+            assert!(game.balls[0].stuck_offset.is_none());
+            let vel = game.balls[0].vel;
+            (vel.0 * vel.0 + vel.1 * vel.1).sqrt()
+        };
+
+        let full = BreakoutGame::<io::Cursor<Vec<u8>>, CaptureScreen>::get_full_charge_ticks();
+        let tap = launch_speed(1);
+        let half = launch_speed(full / 2);
+        // The first press starts the charge, each repeat after it adds a frame
+        let capped = launch_speed(full + 1);
+        assert!(tap < half && half < capped);
+        assert!((capped - tap * 1.5).abs() < 1e-4);
+        assert!((launch_speed(2 * full) - capped).abs() < 1e-4);
+    }
+
+    #[test]
+    fn converging_balls_bounce_off_each_other() {
+        let collide = |ball_collisions: bool| {
+            let config = GameConfig {
+                ball_collisions,
+                ..GameConfig::default()
+            };
+            let mut game = headless_game(104, 30, config);
+            game.balls = vec![
+                ball_at((40.0, 20.0), (0.4, -0.1)),
+                ball_at((40.4, 20.0), (-0.4, -0.1)),
+            ];
+            game.tick();
+            (game.balls[0].vel, game.balls[1].vel)
+        };
+
+        let (left, right) = collide(true);
+        assert!(left.0 < 0f32 && right.0 > 0f32);
+        assert_eq!((left.1, right.1), (-0.1, -0.1));
+
+        let (left, right) = collide(false);
+        assert!(left.0 > 0f32 && right.0 < 0f32);
+
+        // Balls already moving apart are left alone
+        let mut first = ball_at((40.0, 20.0), (-0.4, 0.0));
+        let mut second = ball_at((40.4, 20.0), (0.4, 0.0));
+        assert!(!first.collide_with(&mut second));
+    }
+
+    #[test]
+    fn losing_focus_pauses_the_game_until_focus_returns() {
+        let focus_game = |autopause: bool| {
+            let config = GameConfig {
+                autopause,
+                ..GameConfig::default()
+            };
+            let keys = io::Cursor::new(b"x".to_vec());
+            let mut game = BreakoutGame::new(keys, CaptureScreen::new(false), 104, 30, config);
+            game.state = GameState::Playing;
+            game.stdin = io::Cursor::new(b"\x1b[Ol".to_vec());
+            assert!(game.update());
+            game
+        };
+
+        let mut game = focus_game(true);
+        assert!(game.paused);
+        // Keys after the focus change are still handled
+        assert!(game.peddles[0].target_x > game.peddles[0].game_x);
+        game.stdin = io::Cursor::new(b"\x1b[I".to_vec());
+        assert!(game.update());
+        assert!(!game.paused);
+
+        assert!(!focus_game(false).paused);
+
+        let mut output = Vec::new();
+        let mut terminal = Terminal::new(&mut output, FakeTty::default(), false).report_focus(true);
+        terminal.enter().unwrap();
+        terminal.leave().unwrap();
+        drop(terminal);
+        let written = String::from_utf8(output).unwrap();
+        assert!(written.contains(FOCUS_REPORTING_ON) && written.contains(FOCUS_REPORTING_OFF));
+    }
+
+    #[test]
+    fn a_cracked_frozen_brick_breaks_in_time_or_freezes_over() {
+        let mut brick = Cell::colored((20, 5), 8, BrickKind::Frozen, NamedColor::Red);
+        assert_eq!(brick.get_graphics(), graphics::BRICK_FROZEN.repeat(8));
+
+        assert!(!brick.hit());
+        assert!(brick.is_cracked());
+        assert_eq!(brick.get_graphics(), graphics::BRICK_CRACKED.repeat(8));
+        for _ in 1..Cell::get_thaw_ticks() {
+            brick.refreeze();
+        }
+        assert!(brick.is_cracked());
+        assert!(brick.hit());
+
+        // Waiting too long freezes the crack over and starts from scratch
+        let mut brick = Cell::colored((20, 5), 8, BrickKind::Frozen, NamedColor::Red);
+        assert!(!brick.hit());
+        for _ in 0..Cell::get_thaw_ticks() {
+            brick.refreeze();
+        }
+        assert!(!brick.is_cracked());
+        assert_eq!(brick.hits_left, 2);
+        assert!(brick.dirty);
+        assert!(!brick.hit());
+        assert!(brick.hit());
+    }
+
+    #[test]
+    fn regenerating_the_level_draws_new_bricks_and_keeps_the_score() {
+        let config = GameConfig {
+            seed: Some(5),
+            ..GameConfig::default()
+        };
+        let keys = io::Cursor::new(b"x".to_vec());
+        let mut game = BreakoutGame::new(keys, CaptureScreen::new(false), 104, 30, config);
+        game.state = GameState::Playing;
+        game.score = 120;
+        game.balls[0].game_pos = (70.0, 20.0);
+        let layout = |game: &BreakoutGame<io::Cursor<Vec<u8>>, CaptureScreen>| {
+            game.cells
+                .iter()
+                .map(|cell| (cell.pos, cell.kind, cell.named_color))
+                .collect::<Vec<_>>()
+        };
+        let before = layout(&game);
+        let peddle_x = game.peddles[0].game_x;
+
+        game.stdin = io::Cursor::new(b"n".to_vec());
+        assert!(game.update());
+        assert_ne!(game.seed, 5);
+        assert_ne!(layout(&game), before);
+        assert_eq!(game.score, 120);
+        assert_eq!(game.balls[0].game_pos, (70.0, 20.0));
+        assert_eq!(game.peddles[0].game_x, peddle_x);
+
+        // The new seed comes from the game's seed, and bricks keep off the ball
+        let mut again = headless_game(
+            104,
+            30,
+            GameConfig {
+                seed: Some(5),
+                ..GameConfig::default()
+            },
+        );
+        let inside_grid = (game.cells[0].x() as f32, game.cells[0].y() as f32);
+        again.balls[0].game_pos = inside_grid;
+        again.regenerate_level();
+        assert_eq!(again.seed, game.seed);
+        assert!(!again.cells.iter().any(|cell| again.balls[0].overlaps(cell)));
+        assert!(again.cells.len() < game.cells.len());
+
+        let config = GameConfig {
+            level: Some(Level::parse("#T\n.X").unwrap()),
+            ..GameConfig::default()
+        };
+        let mut custom = headless_game(104, 30, config);
+        let (seed, bricks) = (custom.seed, custom.brick_count());
+        custom.regenerate_level();
+        assert_eq!((custom.seed, custom.brick_count()), (seed, bricks));
+        assert_eq!(
+            custom.toasts.back().unwrap().text,
+            "The level has a fixed layout"
+        );
+    }
+
+    #[test]
+    fn a_very_fast_ball_still_hits_the_brick_in_its_path() {
+        let brick = Cell::new((40, 8), 8, Box::new(color::Red), BrickKind::Normal);
+        // A single step per frame would jump from row 10 to row 4, past the brick
+        let mut game = game_with_bricks(vec![brick], (44.0, 22.0), (0.0, -6.0));
+        assert_eq!(game.balls[0].substeps(1.0), 12);
+        for _ in 0..3 {
+            game.tick();
+        }
+        assert!(game.cells.is_empty());
+        assert!(game.balls[0].vel.1 > 0f32);
+        assert_eq!(game.stats.bricks_destroyed, 1);
+
+        assert_eq!(ball_at((44.0, 20.0), (0.3, -0.4)).substeps(1.0), 1);
+    }
+
+    #[test]
+    fn losing_a_ball_during_mercy_keeps_the_lives() {
+        let config = GameConfig {
+            mercy_secs: 2,
+            ..GameConfig::default()
+        };
+        let mut game = headless_game(104, 30, config);
+        game.lives = 3;
+        let bottom = coords::bottom(30) as f32;
+        game.balls = vec![ball_at((10.0, bottom), (0.0, 1.0))];
+        assert_eq!(game.tick(), TickResult::Running);
+        assert_eq!(game.lives, 2);
+        // The mercy waits for the new ball to leave the peddle
+        assert!(game.balls[0].stuck_offset.is_some());
+        for _ in 0..200 {
+            game.tick();
+        }
+        assert_eq!(game.mercy_ticks, 0);
+        game.balls[0].launch(&game.peddles);
+        game.tick();
+        assert_eq!(game.mercy_ticks, 2 * 50 - 1);
+        let flash = |game: &BreakoutGame<io::Empty, CaptureScreen>| {
+            format!("{}", color::Fg(game.peddles[0].get_color()))
+        };
+        let colors: Vec<String> = (0..2 * Peddle::get_flash_ticks())
+            .map(|_| {
+                game.tick();
+                flash(&game)
+            })
+            .collect();
+        assert!(colors.iter().any(|color| *color != colors[0]));
+
+        game.balls = vec![ball_at((10.0, bottom), (0.0, 1.0))];
+        assert_eq!(game.tick(), TickResult::Running);
+        assert_eq!(game.lives, 2);
+        assert_eq!(game.stats.balls_lost, 1);
+        assert!(game.balls[0].stuck_offset.is_none());
+        assert!(game.balls[0].vel.1 < 0f32);
+
+        // Once the window is over balls are lost again
+        game.mercy_ticks = 0;
+        game.balls = vec![ball_at((10.0, bottom), (0.0, 1.0))];
+        game.tick();
+        assert_eq!(game.lives, 1);
+    }
+
+    #[test]
+    fn invalid_configurations_yield_their_error_variant() {
+        let invalid = |config: GameConfig, needle: &str| match config.check((104, 30)) {
+            Err(GameError::InvalidConfig(ref message)) => {
+                assert!(message.contains(needle), "{}", message);
+            }
+            other => panic!("expected an invalid config, got {:?}", other),
+        };
+        invalid(
+            GameConfig {
+                peddle_width: 1,
+                ..GameConfig::default()
+            },
+            "Invalid peddle",
+        );
+        invalid(
+            GameConfig {
+                title: "x".repeat(40),
+                ..GameConfig::default()
+            },
+            "Invalid title",
+        );
+        invalid(
+            GameConfig {
+                grid_share: Some(2.0),
+                ..GameConfig::default()
+            },
+            "Invalid brick grid",
+        );
+        invalid(
+            GameConfig {
+                collision_padding: CollisionPadding {
+                    side: 0.3,
+                    face: 2.0,
+                },
+                ..GameConfig::default()
+            },
+            "face padding",
+        );
+        invalid(
+            GameConfig {
+                speed_limits: SpeedLimits { min: 2.0, max: 1.0 },
+                ..GameConfig::default()
+            },
+            "minimum speed",
+        );
+        assert!(GameConfig::default().check((104, 30)).is_ok());
+
+        let config = GameConfig::default();
+        let error = config.check_size((20, 30)).unwrap_err();
+        match error {
+            GameError::TooSmall { size, min_size } => {
+                assert_eq!(size, (20, 30));
+                assert_eq!(min_size, config.min_size());
+            }
+            ref other => panic!("expected too small, got {:?}", other),
+        }
+        assert_eq!(error.exit_code(), 1);
+        assert!(error
+            .to_string()
+            .starts_with("A game of 20x30 is too small"));
+        assert!(config.check_size((104, 30)).is_ok());
+
+        let missing = || io::Error::new(io::ErrorKind::NotFound, "gone");
+        let error = GameError::Io("open the log file".to_string(), missing());
+        assert_eq!(
+            error.to_string(),
+            "Failed to open the log file with error: gone"
+        );
+        let codes: Vec<u8> = vec![
+            GameError::InvalidConfig(String::new()).exit_code(),
+            error.exit_code(),
+            GameError::TerminalSizeUnavailable(missing()).exit_code(),
+        ];
+        assert_eq!(codes, vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn top_peddle_loses_the_ball_at_the_top_with_the_bricks_near_the_bottom() {
+        let config = GameConfig {
+            peddle_side: PeddleSide::Top,
+            ..GameConfig::default()
+        };
+        assert_eq!("top".parse::<PeddleSide>(), Ok(PeddleSide::Top));
+        let game = headless_game(104, 30, config);
+        assert_eq!(game.peddles[0].y(), coords::top() + 1);
+        assert!(game.cells.iter().all(|cell| cell.y() > 30 / 2));
+        let ball = &game.balls[0];
+        assert!(ball.y() > game.peddles[0].y());
+        assert!(game.cells.iter().all(|cell| cell.y() > ball.y()));
+        assert!(ball.vel.1 < 0f32);
+
+        let peddles = &game.peddles;
+        let mut escaping = game.balls[0].spawn_at((50.0, coords::top() as f32));
+        escaping.vel = (0.2, -1.0);
+        assert_eq!(
+            escaping.update((104, 30), peddles, None, 1.0),
+            BallUpdate::Lost
+        );
+        let mut bouncing = game.balls[0].spawn_at((50.0, coords::bottom(30) as f32));
+        bouncing.vel = (0.2, 1.0);
+        assert_eq!(
+            bouncing.update((104, 30), peddles, None, 1.0),
+            BallUpdate::Moving
+        );
+        assert!(bouncing.vel.1 < 0f32);
+
+        // The peddle sends the ball back down
+        let peddle = &game.peddles[0];
+        let x = (peddle.x() + peddle.get_width() / 2) as f32;
+        let mut returning = game.balls[0].spawn_at((x, (peddle.y() + 1) as f32));
+        returning.vel = (0.0, -1.0);
+        assert_eq!(
+            returning.update((104, 30), peddles, None, 1.0),
+            BallUpdate::HitPeddle(0)
+        );
+        assert!(returning.vel.1 > 0f32);
+
+        let with_boss = GameConfig {
+            peddle_side: PeddleSide::Top,
+            boss: true,
+            ..GameConfig::default()
+        };
+        assert!(with_boss.check((104, 30)).is_err());
+    }
+
+    /// Bounces `ball` off `cells` like `hit_bricks` but testing every brick.
+    fn hit_all_bricks(ball: &mut Ball, cells: &mut [Cell], to_kill: &mut Vec<usize>) {
+        for (index, cell) in cells.iter_mut().enumerate() {
+            if cell.is_destroyed() {
+                continue;
+            }
+            if let Some(hit_dir) = ball.collides_with(cell) {
+                if cell.hit() {
+                    to_kill.push(index);
+                }
+                ball.change_direction(hit_dir);
+            }
+        }
+    }
+
+    #[test]
+    fn brick_index_hits_the_same_bricks_as_testing_every_brick() {
+        let mut rng = StdRng::from_seed(&[7][..]);
+        let mut bounces = 0;
+        for seed in 0..2000 {
+            // Random bricks and a ball of random size somewhere around them
+            let config = GameConfig {
+                brick_width: rng.gen_range(1, 7),
+                brick_gap: rng.gen_range(0, 3),
+                ball_size: rng.gen_range(1, 4),
+                collision_padding: CollisionPadding {
+                    side: rng.gen_range(0f32, 3f32),
+                    face: rng.gen_range(0f32, 1f32),
+                },
+                ..GameConfig::default()
+            };
+            let size = (rng.gen_range(60, 140), rng.gen_range(20, 50));
+            let pos = (
+                rng.gen_range(coords::left() as f32, coords::right(size.0) as f32),
+                rng.gen_range(coords::top() as f32, (size.1 / 2) as f32),
+            );
+            let vel = (
+                rng.gen_range(-1.5f32, 1.5f32),
+                rng.gen_range(-1.5f32, 1.5f32),
+            );
+            let new_ball = || Ball {
+                size: config.ball_size,
+                padding: config.collision_padding,
+                ..ball_at(pos, vel)
+            };
+            let generate = || {
+                BreakoutGame::<std::io::Empty, CaptureScreen>::generate_cell_grid(
+                    size, &config, seed,
+                )
+            };
+
+            let (mut brute_ball, mut brute_cells, mut brute_kills) =
+                (new_ball(), generate(), vec![]);
+            hit_all_bricks(&mut brute_ball, &mut brute_cells, &mut brute_kills);
+
+            let (mut ball, mut cells, mut kills) = (new_ball(), generate(), vec![]);
+            let mut bricks = BrickIndex::new(&cells, size);
+            BreakoutGame::<std::io::Empty, CaptureScreen>::hit_bricks(
+                &mut ball,
+                &mut cells,
+                &mut bricks,
+                &None,
+                &mut kills,
+            );
+
+            assert_eq!(kills, brute_kills);
+            assert_eq!(ball.vel, brute_ball.vel);
+            let hits_left =
+                |cells: &[Cell]| cells.iter().map(|cell| cell.hits_left).collect::<Vec<_>>();
+            assert_eq!(hits_left(&cells), hits_left(&brute_cells));
+            if ball.vel != vel {
+                bounces += 1;
+            }
+        }
+        assert!(bounces > 100);
+    }
+
+    #[test]
+    fn brick_index_tests_a_small_share_of_the_bricks() {
+        let size = (400, 200);
+        let config = GameConfig {
+            brick_width: 2,
+            grid_share: Some(0.5),
+            ..GameConfig::default()
+        };
+        let cells =
+            BreakoutGame::<std::io::Empty, CaptureScreen>::generate_cell_grid(size, &config, 1);
+        let mut rng = StdRng::from_seed(&[1][..]);
+        let mut bricks = BrickIndex::new(&cells, size);
+        let (mut tested, mut brute, mut indexed) = (0, 0, 0);
+        for _ in 0..100 {
+            let pos = (rng.gen_range(2f32, 398f32), rng.gen_range(2f32, 100f32));
+            let ball = ball_at(
+                pos,
+                (rng.gen_range(-1f32, 1f32), rng.gen_range(-1f32, 1f32)),
+            );
+            brute += cells
+                .iter()
+                .filter(|&cell| ball.collides_with(cell).is_some())
+                .count();
+            let near = bricks.near(&ball);
+            tested += near.len();
+            indexed += near
+                .iter()
+                .filter(|&&i| ball.collides_with(&cells[i]).is_some())
+                .count();
+        }
+        assert_eq!(indexed, brute);
+        assert!(brute > 0);
+        // A few dozen bricks per ball out of thousands
+        assert!(tested < 100 * 50, "{} of {}", tested, 100 * cells.len());
+
+        // Rebuilding drops the removed bricks
+        bricks.rebuild(&cells[..1]);
+        let ball = ball_at((cells[0].x() as f32, cells[0].y() as f32), (0.0, -1.0));
+        assert_eq!(bricks.near(&ball), &[0]);
+    }
+
+    #[test]
+    fn fireball_burns_through_bricks_without_bouncing() {
+        let config = GameConfig {
+            fireball_combo: 2,
+            ..GameConfig::default()
+        };
+        let mut game = headless_game(104, 30, config);
+        game.cells = vec![
+            Cell::colored((40, 5), 8, BrickKind::Normal, NamedColor::Red),
+            Cell::colored((80, 5), 8, BrickKind::Normal, NamedColor::Red),
+        ];
+        game.cells
+            .extend((6..10).map(|y| Cell::colored((60, y), 8, BrickKind::Tough, NamedColor::Blue)));
+        game.index_bricks();
+        game.balls = vec![ball_at((44.0, 6.0), (0.0, -1.0))];
+        game.tick();
+        assert_eq!(game.balls[0].fire_ticks, 0);
+        game.balls = vec![ball_at((84.0, 6.0), (0.0, -1.0))];
+        game.tick();
+        assert_eq!(game.cells.len(), 4);
+        assert!(game.balls.iter().all(|ball| ball.fire_ticks > 0));
+
+        // The combo ignited the ball, it burns up through the whole column
+        let fire_ticks = game.balls[0].fire_ticks;
+        game.balls = vec![Ball {
+            fire_ticks,
+            ..ball_at((64.0, 10.0), (0.0, -1.0))
+        }];
+        for _ in 0..5 {
+            game.tick();
+            assert_eq!(game.balls[0].vel, (0.0, -1.0));
+        }
+        assert!(game.cells.is_empty());
+
+        // Once the fire is out bricks bounce the ball again
+        game.cells = vec![Cell::colored(
+            (60, 5),
+            8,
+            BrickKind::Normal,
+            NamedColor::Red,
+        )];
+        game.index_bricks();
+        game.balls = vec![ball_at((64.0, 6.0), (0.0, -1.0))];
+        game.tick();
+        assert!(game.balls[0].vel.1 > 0f32);
+    }
+
+    #[test]
+    fn nudges_push_the_ball_towards_the_peddle_until_they_run_out() {
+        let config = GameConfig {
+            nudges: 2,
+            ..GameConfig::default()
+        };
+        let mut game = headless_game(104, 30, config);
+        let peddle_center = (game.peddles[0].x() + game.peddles[0].get_width() / 2) as f32;
+        game.balls = vec![ball_at((peddle_center - 20.0, 20.0), (-0.1, 0.5))];
+        game.handle_key(Some(Key::Char('x')));
+        let vel = game.balls[0].vel;
+        assert!(vel.0 > -0.1 && vel.1 > 0f32);
+        // Only the direction changes, not the speed
+        assert!(((vel.0 * vel.0 + vel.1 * vel.1).sqrt() - game.balls[0].speed).abs() < 1e-5);
+        assert_eq!(game.nudges_left, 1);
+        game.draw_nudges();
+        assert_eq!(game.nudges_label, " nudges 1 ");
+
+        game.handle_key(Some(Key::Char('x')));
+        let nudged = game.balls[0].vel;
+        assert!(nudged.0 > 0f32);
+        assert_eq!(game.nudges_left, 0);
+        game.handle_key(Some(Key::Char('x')));
+        assert_eq!(game.balls[0].vel, nudged);
+
+        // A new life brings the nudges back
+        game.lives = 3;
+        game.balls = vec![ball_at((10.0, coords::bottom(30) as f32), (0.0, 1.0))];
+        assert_eq!(game.tick(), TickResult::Running);
+        assert_eq!(game.nudges_left, 2);
+
+        // Nudging is opt-in
+        let mut game = headless_game(104, 30, GameConfig::default());
+        game.balls = vec![ball_at((peddle_center - 20.0, 20.0), (-0.1, 0.5))];
+        game.handle_key(Some(Key::Char('x')));
+        assert_eq!(game.balls[0].vel, (-0.1, 0.5));
+    }
+
+    #[test]
+    fn brick_grid_is_centered_between_the_side_borders() {
+        for width in 60..140 {
+            for &(brick_width, brick_gap, cols) in
+                &[(8, 0, None), (5, 1, None), (3, 2, None), (8, 1, Some(4))]
+            {
+                let config = GameConfig {
+                    brick_width,
+                    brick_gap,
+                    cols,
+                    ..GameConfig::default()
+                };
+                let cells = BreakoutGame::<std::io::Empty, CaptureScreen>::generate_cell_grid(
+                    (width, 30),
+                    &config,
+                    3,
+                );
+                let first = cells.iter().map(|cell| cell.x()).min().unwrap();
+                let last = cells
+                    .iter()
+                    .map(|cell| cell.x() + cell.get_width() - 1)
+                    .max()
+                    .unwrap();
+                let left_margin = first - coords::left();
+                let right_margin = coords::right(width) - last;
+                assert!(
+                    right_margin == left_margin || right_margin == left_margin + 1,
+                    "margins {} and {} in a width of {} with bricks {} wide",
+                    left_margin,
+                    right_margin,
+                    width,
+                    brick_width
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn guides_span_the_peddle_and_a_column_to_either_side() {
+        let game = headless_game(104, 30, GameConfig::default());
+        assert!(game.guide_cells().is_empty());
+
+        let config = GameConfig {
+            guides: true,
+            ..GameConfig::default()
+        };
+        let mut game = headless_game(104, 30, config);
+        let peddle = &game.peddles[0];
+        let expected: Vec<(Unit, Unit)> = (peddle.x() - 1..peddle.x() + peddle.get_width() + 1)
+            .map(|x| (x, coords::bottom(30)))
+            .collect();
+        assert_eq!(game.guide_cells(), expected);
+
+        // Against the border the guide stops at the playfield
+        game.peddles[0].jump_to(0.0, 104);
+        let guides = game.guide_cells();
+        assert_eq!(guides[0], (coords::left(), coords::bottom(30)));
+        assert_eq!(guides.len(), game.peddles[0].get_width() + 1);
+
+        // The safety net takes the row over
+        game.safety_net_ticks = 10;
+        assert!(game.guide_cells().is_empty());
+    }
+
+    #[test]
+    fn recorded_simulations_verify_until_tampered_with() {
+        let config = GameConfig {
+            seed: Some(3),
+            ..GameConfig::default()
+        };
+        let mut game = BreakoutGame::new(io::empty(), NullScreen, 104, 30, config);
+        let (frames, _) = game.simulate(1500);
+        let recording = game.recording(frames);
+        assert!(recording.score > 0);
+
+        let path = env::temp_dir().join(format!("rbreakout-recording-{}", std::process::id()));
+        recording.save(&path).unwrap();
+        let loaded = Recording::load(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(loaded, recording);
+        assert!(loaded.verify(GameConfig::default()).is_ok());
+
+        let tampered = Recording {
+            score: recording.score + 10,
+            ..loaded
+        };
+        let error = tampered.verify(GameConfig::default()).unwrap_err();
+        assert_eq!(error.exit_code(), 5);
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "The replay diverged from the recording: it scored {} instead of {}",
+                recording.score,
+                recording.score + 10
+            )
+        );
+
+        // Different options are a different game
+        let hard = GameConfig {
+            difficulty: Difficulty::Hard,
+            ..GameConfig::default()
+        };
+        let error = recording.verify(hard).unwrap_err();
+        assert_eq!(error.exit_code(), 2);
+        assert_eq!(
+            error.to_string(),
+            "Invalid replay: the options differ from the ones the recording was made with"
+        );
+        let seeded = GameConfig {
+            seed: Some(7),
+            ..GameConfig::default()
+        };
+        assert!(recording.verify(seeded).is_ok());
+    }
+
+    #[test]
+    fn random_layouts_hold_special_bricks_only_when_asked_for() {
+        let kinds = |special_bricks| {
+            let config = GameConfig {
+                special_bricks,
+                ..GameConfig::default()
+            };
+            let cells = BreakoutGame::<std::io::Empty, CaptureScreen>::generate_cell_grid(
+                (104, 30),
+                &config,
+                7,
+            );
+            cells.iter().map(|cell| cell.kind).collect::<Vec<_>>()
+        };
+        assert!(kinds(false).iter().all(|&kind| kind == BrickKind::Normal));
+        let special = kinds(true);
+        assert!(special.contains(&BrickKind::Tough));
+        assert!(special.contains(&BrickKind::Solid));
+        assert_eq!(special.len(), kinds(false).len());
+    }
+
+    #[test]
+    fn records_are_only_written_to_the_configured_records_path() {
+        assert!(GameConfig::default().records_path.is_none());
+        let mut game = headless_game(104, 30, GameConfig::default());
+        game.score = 120;
+        assert!(game.submit_score());
+        game.save_records();
+
+        let path = env::temp_dir().join(format!("rbreakout-records-{}", std::process::id()));
+        let config = GameConfig {
+            records_path: Some(path.clone()),
+            ..GameConfig::default()
+        };
+        let mut game = headless_game(104, 30, config);
+        assert!(game.records.scores.is_empty());
+        game.score = 120;
+        assert!(game.submit_score());
+        game.save_records();
+        let saved = Records::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(saved.scores.len(), 1);
+        assert_eq!(saved.scores[0].score, 120);
+    }
+
+    #[test]
+    fn ctrl_c_quits_the_game_and_restores_the_terminal() {
+        static KEY_PRESSED: AtomicBool = AtomicBool::new(false);
+        static SIGNALED: AtomicBool = AtomicBool::new(false);
+        let quit = |keys: &'static [u8], interrupt: &'static AtomicBool| {
+            let config = GameConfig {
+                skip_intro: true,
+                ..GameConfig::default()
+            };
+            let terminal = fake_terminal(true);
+            let raw = terminal.tty.raw.clone();
+            let mut game = BreakoutGame::new(keys, terminal, 104, 30, config);
+            game.interrupt = interrupt;
+            game.run().unwrap();
+            assert!(interrupt.load(Ordering::SeqCst));
+            assert!(!raw.get());
+            let output = String::from_utf8(game.stdout.output.clone()).unwrap();
+            let restore = format!(
+                "{}{}{}",
+                color::Fg(color::Reset),
+                cursor::Show,
+                ToMainScreen
+            );
+            assert!(output.ends_with(&restore));
+        };
+        // Raw mode delivers Ctrl-C as a key
+        quit(b"\x03", &KEY_PRESSED);
+        // Outside of raw mode SIGINT sets the flag without a key
+        SIGNALED.store(true, Ordering::SeqCst);
+        quit(b"", &SIGNALED);
+    }
+
+    #[test]
+    fn game_positions_convert_to_terminal_cells_and_back() {
+        let corner = (coords::left_border(), coords::top_border());
+        assert_eq!(coords::to_terminal(corner, 0), (1, 1));
+        assert_eq!(coords::to_terminal((coords::right(104), 5), 2), (105, 5));
+        for shift in 0..4 {
+            for &pos in &[corner, (coords::left(), coords::top()), (104, 30), (37, 12)] {
+                let cell = coords::to_terminal(pos, shift);
+                assert_eq!(coords::from_terminal(cell, shift), Some(pos));
             }
-            Err(e) => println!("Failed to get terminal size with error: {}", e),
+        }
+        // Cells left of a shifted game show no game position
+        assert_eq!(coords::from_terminal((2, 1), 2), None);
+        assert_eq!(coords::from_terminal((3, 1), 2), Some(corner));
+    }
+
+    #[test]
+    fn ball_size_must_fit_the_playfield() {
+        let check = |ball_size| {
+            let config = GameConfig {
+                ball_size,
+                ..GameConfig::default()
+            };
+            config.check((104, 30)).is_ok()
+        };
+        assert!(!check(0));
+        assert!(check(1));
+        assert!(check(28));
+        assert!(!check(29));
+    }
+
+    #[test]
+    fn wide_bosses_are_narrowed_to_fit_and_centered_between_the_borders() {
+        let config = GameConfig {
+            boss: true,
+            brick_width: 20,
+            ..GameConfig::default()
+        };
+        let y = GameConfig::get_grid_top() + 2;
+        let boss =
+            BreakoutGame::<io::Empty, CaptureScreen>::place_boss(&mut vec![], 40, &config).unwrap();
+        assert!(boss.covers((1, y)) && boss.covers((38, y)));
+        assert!(!boss.covers((0, y)) && !boss.covers((39, y)));
+
+        let config = GameConfig {
+            boss: true,
+            ..GameConfig::default()
+        };
+        let boss = BreakoutGame::<io::Empty, CaptureScreen>::place_boss(&mut vec![], 104, &config)
+            .unwrap();
+        let span = config.brick_width * Boss::get_brick_span();
+        let left = (104 + 1 - span) / 2;
+        assert!(boss.covers((left, y)) && boss.covers((left + span - 1, y)));
+        assert!(!boss.covers((left - 1, y)) && !boss.covers((left + span, y)));
+    }
+
+    #[test]
+    fn inverting_the_controls_swaps_left_and_right_in_place() {
+        let original = KeyBindings::wasd();
+        let mut inverted = KeyBindings::wasd();
+        inverted.invert();
+        assert_eq!(inverted.action(Key::Char('a')), Some(Action::Right));
+        assert_eq!(inverted.action(Key::Char('d')), Some(Action::Left));
+        assert_eq!(inverted.bindings.len(), original.bindings.len());
+        for (&(action, ref keys), &(original_action, ref original_keys)) in
+            inverted.bindings.iter().zip(&original.bindings)
+        {
+            let expected = match original_action {
+                Action::Left => Action::Right,
+                Action::Right => Action::Left,
+                other => other,
+            };
+            assert_eq!((action, keys), (expected, original_keys));
         }
     }
 
-    if width < 32 {
-        println!("The specified or computed width is too small!");
-        return;
+    #[test]
+    fn the_fastest_speed_multiplier_still_hits_a_single_row_of_bricks() {
+        let brick = Cell::new((40, 5), 8, Box::new(color::Red), BrickKind::Normal);
+        let mut game = game_with_bricks(vec![brick], (44.0, 12.0), (0.0, -1.4));
+        game.speed_multiplier = SpeedLimits::default().max;
+        let time_scale = game.ball_time_scale();
+        assert_eq!(time_scale, 2.0);
+        // Nearly three rows per frame, moved in half-cell substeps
+        assert_eq!(game.balls[0].substeps(time_scale), 6);
+        for _ in 0..3 {
+            game.tick();
+        }
+        assert!(game.cells.is_empty());
+        assert!(game.balls[0].vel.1 > 0.0);
+        assert!(game.balls[0].game_pos.1 > 5.0);
+    }
+
+    #[test]
+    fn shallow_frames_are_counted_once_per_frame_for_fast_balls() {
+        let mut game = game_with_bricks(vec![], (20.0, 10.0), (3.0, 0.05));
+        assert!(game.balls[0].substeps(1.0) > 1);
+        game.tick();
+        assert_eq!(game.balls[0].shallow_ticks, 1);
+        game.tick();
+        assert_eq!(game.balls[0].shallow_ticks, 2);
     }
-    if height < 20 {
-        println!("The specified or computed height is too small!");
-        return;
+
+    #[test]
+    fn replay_options_are_rejected_where_they_would_be_ignored() {
+        assert!(check_replay_options(false, false, false).is_ok());
+        assert!(check_replay_options(true, true, false).is_ok());
+        assert!(check_replay_options(false, false, true).is_ok());
+        for &(bench, record, verify, needle) in &[
+            (false, true, false, "--record only works with --bench"),
+            (true, false, true, "--verify"),
+            (true, true, true, "--verify"),
+        ] {
+            match check_replay_options(bench, record, verify) {
+                Err(GameError::InvalidConfig(ref message)) => {
+                    assert!(message.contains(needle), "{}", message);
+                }
+                other => panic!("expected an invalid config, got {:?}", other),
+            }
+        }
     }
 
-    width = (width / Cell::get_width()) * Cell::get_width();
-    init(width, height);
+    #[test]
+    fn laser_shot_passes_a_brick_a_ball_destroyed_in_the_same_frame() {
+        let cells = vec![
+            Cell::new((40, 5), 8, Box::new(color::Red), BrickKind::Normal),
+            Cell::new((40, 4), 8, Box::new(color::Red), BrickKind::Normal),
+            Cell::new((80, 5), 8, Box::new(color::Red), BrickKind::Normal),
+        ];
+        let mut game = game_with_bricks(cells, (44.0, 6.0), (0.0, -1.0));
+        game.projectiles = vec![Projectile {
+            game_pos: (42.0, 6.0),
+        }];
+        game.tick();
+        // The ball destroyed the lower brick, the shot flies on to the one behind it
+        assert!(game.balls[0].vel.1 > 0.0);
+        assert_eq!(game.projectiles.len(), 1);
+        game.tick();
+        assert!(game.projectiles.is_empty());
+        let left: Vec<_> = game.bricks().map(|cell| cell.pos).collect();
+        assert_eq!(left, vec![(80, 5)]);
+    }
 }